@@ -1,6 +1,30 @@
 use chrono::{DateTime, Local};
 use std::fs;
+use std::io;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Which of a file's times a date was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileTime {
+    /// When the file was created on this file system.
+    Birth,
+    /// When the file's contents were last modified.
+    Modified,
+    /// When the file's contents or attributes last changed, `ctime` on Unix.
+    StatusChanged,
+}
+
+/// How the creation time of a file is found on file systems that may not record birth
+/// times, such as NFS mounts and some FAT-formatted SD cards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CreatedStrategy {
+    /// Only the birth time. Files without one have no creation time.
+    Birth,
+    /// The birth time, else the earlier of the modification and status change times.
+    #[default]
+    BirthOrEarliest,
+}
 
 /// Reads the metadata of a file at the given path and returns it as `fs::Metadata`.
 ///
@@ -19,6 +43,79 @@ fn read_metadata(path_str: &Path) -> Result<fs::Metadata, String> {
     }
 }
 
+/// Converts a file time, treating times the platform does not report, and the zero time
+/// some file systems report instead, as missing.
+pub(crate) fn local_time(time: io::Result<SystemTime>) -> Option<DateTime<Local>> {
+    time.ok()
+        .filter(|time| *time != UNIX_EPOCH)
+        .map(DateTime::from)
+}
+
+/// Reads the status change time, which only Unix records.
+#[cfg(unix)]
+fn status_changed(metadata: &fs::Metadata) -> Option<DateTime<Local>> {
+    use std::os::unix::fs::MetadataExt;
+
+    DateTime::from_timestamp(metadata.ctime(), metadata.ctime_nsec() as u32)
+        .filter(|time| time.timestamp() != 0)
+        .map(|time| time.with_timezone(&Local))
+}
+
+#[cfg(not(unix))]
+fn status_changed(_metadata: &fs::Metadata) -> Option<DateTime<Local>> {
+    None
+}
+
+/// Returns the birth time of the file at the given path. On Linux it is read with `statx`,
+/// which only reports it on kernels and file systems that record it.
+///
+/// # Arguments
+///
+/// * `path_str` - A reference to a `Path` representing the file path.
+///
+/// # Returns
+///
+/// * `Ok(Some(DateTime<Local>))` if the file system recorded when the file was created.
+/// * `Ok(None)` if it did not.
+/// * `Err(String)` if the metadata could not be read.
+pub fn birth_time(path_str: &Path) -> Result<Option<DateTime<Local>>, String> {
+    Ok(local_time(read_metadata(path_str)?.created()))
+}
+
+/// Returns when the file at the given path was created according to `strategy`, along
+/// with which of its times that is.
+///
+/// # Arguments
+///
+/// * `path_str` - A reference to a `Path` representing the file path.
+/// * `strategy` - Whether to fall back to the earlier of the modification and status change
+///   times when the file system has no birth time.
+///
+/// # Returns
+///
+/// * `Ok(Some((DateTime<Local>, FileTime)))` with the creation time and where it came from.
+/// * `Ok(None)` if the strategy finds no time.
+/// * `Err(String)` if the metadata could not be read.
+pub fn created_time(
+    path_str: &Path,
+    strategy: CreatedStrategy,
+) -> Result<Option<(DateTime<Local>, FileTime)>, String> {
+    let metadata = read_metadata(path_str)?;
+    if let Some(birth) = local_time(metadata.created()) {
+        return Ok(Some((birth, FileTime::Birth)));
+    }
+    if strategy == CreatedStrategy::Birth {
+        return Ok(None);
+    }
+
+    let modified = local_time(metadata.modified()).map(|time| (time, FileTime::Modified));
+    let changed = status_changed(&metadata).map(|time| (time, FileTime::StatusChanged));
+    Ok(modified
+        .into_iter()
+        .chain(changed)
+        .min_by_key(|(time, _)| *time))
+}
+
 /// Returns the creation date of the file at the given path as a formatted string (`YYYY-MM-DD`).
 ///
 /// # Arguments
@@ -28,17 +125,17 @@ fn read_metadata(path_str: &Path) -> Result<fs::Metadata, String> {
 /// # Returns
 ///
 /// * `Ok(String)` containing the formatted creation date if successful.
-/// * `Err(String)` if the file does not exist or the creation date cannot be retrieved.
+/// * `Err(String)` if the file does not exist or the file system does not record its
+///   creation date.
 pub fn file_created(path_str: &Path) -> Result<String, String> {
     if !path_str.exists() {
         return Err("File does not exist".to_string());
     }
 
-    let created_date = read_metadata(path_str)?.created().unwrap();
-    let datetime: DateTime<Local> = created_date.into();
-    let formatted_date = datetime.format("%Y-%m-%d").to_string();
-
-    Ok(formatted_date)
+    match birth_time(path_str)? {
+        Some(datetime) => Ok(datetime.format("%Y-%m-%d").to_string()),
+        None => Err("The file system does not record when the file was created".to_string()),
+    }
 }
 
 /// Returns the last modification date of the file at the given path as a formatted string (`YYYY-MM-DD`).
@@ -56,7 +153,9 @@ pub fn file_modified(path_str: &Path) -> Result<String, String> {
         return Err("File does not exist".to_string());
     }
 
-    let modified_date = read_metadata(path_str)?.modified().unwrap();
+    let modified_date = read_metadata(path_str)?
+        .modified()
+        .map_err(|err| err.to_string())?;
     let datetime: DateTime<Local> = modified_date.into();
     let formatted_date = datetime.format("%Y-%m-%d").to_string();
 
@@ -78,7 +177,9 @@ pub fn last_accessed(path_str: &Path) -> Result<String, String> {
         return Err("File does not exist".to_string());
     }
 
-    let accessed_date = read_metadata(path_str)?.accessed().unwrap();
+    let accessed_date = read_metadata(path_str)?
+        .accessed()
+        .map_err(|err| err.to_string())?;
     let datetime: DateTime<Local> = accessed_date.into();
     let formatted_date = datetime.format("%Y-%m-%d").to_string();
 
@@ -99,9 +200,36 @@ mod tests {
     #[test]
     fn can_read_creation_string() {
         let f = create_temp_file();
-        let result = file_created(f.path()).unwrap();
         let today = Local::now().format("%Y-%m-%d").to_string();
-        assert_eq!(result, today);
+        match birth_time(f.path()).unwrap() {
+            Some(_) => assert_eq!(file_created(f.path()).unwrap(), today),
+            None => assert!(file_created(f.path()).is_err()),
+        }
+    }
+
+    #[test]
+    fn birth_time_is_optional() {
+        let f = create_temp_file();
+        let birth = birth_time(f.path()).unwrap();
+
+        assert_eq!(
+            created_time(f.path(), CreatedStrategy::Birth).unwrap(),
+            birth.map(|time| (time, FileTime::Birth))
+        );
+        assert!(birth_time(Path::new("nonexistent.jpg")).is_err());
+    }
+
+    #[test]
+    fn creation_time_falls_back_to_the_earliest_other_time() {
+        let f = create_temp_file();
+        let (time, source) = created_time(f.path(), CreatedStrategy::BirthOrEarliest)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(time.date_naive(), Local::now().date_naive());
+        if birth_time(f.path()).unwrap().is_none() {
+            assert_ne!(source, FileTime::Birth);
+        }
     }
 
     #[test]
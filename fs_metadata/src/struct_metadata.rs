@@ -2,9 +2,11 @@ use chrono::{DateTime, Local};
 use std::fs::{self, Metadata};
 use std::path::Path;
 use faccess::PathExt;
+use crate::functions_metadata::local_time;
 
 pub struct FileMetadata {
-    pub created: String,
+    /// The birth date, or `None` on file systems that do not record it.
+    pub created: Option<String>,
     pub modified: String,
     pub accessed: String,
     pub is_file_read_only: bool,
@@ -25,7 +27,8 @@ pub struct FileMetadata {
 /// - `new(path: &Path) -> Result<Self, String>`
 ///   Constructs a new `FileMetadata` instance from the given file path, extracting
 ///   metadata such as creation, modification, and access times, as well as file
-///   permissions and type. Returns an error if metadata cannot be read.
+///   permissions and type. Returns an error if metadata cannot be read. The creation
+///   date is `None` where the file system does not record birth times.
 ///
 /// - `get_human_readable_file_size(&self) -> (f32, f32, f32, f32)`
 ///   Returns the file size in kilobytes, megabytes, gigabytes, and terabytes as a tuple.
@@ -57,23 +60,21 @@ impl FileMetadata {
 
         match metadata {
             Ok(data) => {
-                let created_system_time: DateTime<Local> = data
-                    .created()
-                    .expect("Could not read accessed system time")
-                    .into();
+                let created_system_time = local_time(data.created());
                 let modified_system_time: DateTime<Local> = data
                     .modified()
-                    .expect("Could not read modified system time")
+                    .map_err(|_| "Could not read modified system time".to_string())?
                     .into();
                 let accessed_system_time: DateTime<Local> = data
                     .accessed()
-                    .expect("Could not read accessed system time")
+                    .map_err(|_| "Could not read accessed system time".to_string())?
                     .into();
 
                 return Ok(FileMetadata {
                     accessed: accessed_system_time.format("%Y-%m-%d").to_string(),
                     modified: modified_system_time.format("%Y-%m-%d").to_string(),
-                    created: created_system_time.format("%Y-%m-%d").to_string(),
+                    created: created_system_time
+                        .map(|created| created.format("%Y-%m-%d").to_string()),
                     is_file_read_only: data.permissions().readonly(),
                     is_dir: data.is_dir(),
                     is_file: data.is_file(),
@@ -0,0 +1,440 @@
+use super::parse_tag_date;
+use crate::bmff::{Range, find_box};
+use crate::media_date::{DatePrecision, DateSource, MediaDate};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Seconds from the 1904 epoch of MP4 and AIFF timestamps to the Unix epoch.
+const MAC_EPOCH_OFFSET: u64 = 2_082_844_800;
+
+/// Tag chunks larger than this are skipped rather than read into memory.
+const MAX_TAG_SIZE: u64 = 1 << 20;
+
+/// Reads a date from the native tags of an MP4, FLAC, WAV or AIFF file, without writing to
+/// it. Returns `None` for other files, and for files whose tags hold no date.
+pub(super) fn read_container_date(path: &Path) -> io::Result<Option<MediaDate>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let len = file.get_ref().metadata()?.len();
+
+    let mut header = Vec::new();
+    file.by_ref().take(12).read_to_end(&mut header)?;
+    if header.len() < 12 {
+        return Ok(None);
+    }
+
+    let date = match (&header[..4], &header[4..8], &header[8..]) {
+        (_, b"ftyp", _) => mp4_date(&mut file, len),
+        (b"fLaC", _, _) => flac_date(&mut file),
+        (b"RIFF", _, b"WAVE") => wav_date(&mut file, len),
+        (b"FORM", _, b"AIFF" | b"AIFC") => aiff_date(&mut file, len),
+        _ => Ok(None),
+    };
+
+    // A truncated file simply has no date past the point where it ends
+    match date {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        date => date,
+    }
+}
+
+fn read_bytes(file: &mut impl Read, count: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    file.take(count).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < count {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn read_range<R: Read + Seek>(file: &mut R, (start, end): Range) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(start))?;
+    read_bytes(file, end.saturating_sub(start).min(MAX_TAG_SIZE))
+}
+
+/// Converts a count of seconds since 1904, skipping the zero of either epoch, which is
+/// written by recorders without a set clock.
+fn mac_time(seconds: u64) -> Option<NaiveDateTime> {
+    if seconds == 0 || seconds == MAC_EPOCH_OFFSET {
+        return None;
+    }
+    DateTime::from_timestamp(seconds as i64 - MAC_EPOCH_OFFSET as i64, 0)
+        .map(|date_time| date_time.naive_utc())
+}
+
+/// Marks a date written in UTC as UTC-only, since Voice Memos write `©day` in UTC without
+/// the time zone it was recorded in. Dates with any other offset are left as recorded.
+fn utc_as_utc_only(date: MediaDate) -> MediaDate {
+    match date.offset {
+        Some(offset) if offset.local_minus_utc() == 0 => {
+            MediaDate::from_utc(date.date_time, date.source)
+        }
+        _ => date,
+    }
+}
+
+/// Reads the `©day` atom of an M4A, as written by Voice Memos and iTunes, then the movie
+/// header's creation time.
+fn mp4_date<R: Read + Seek>(file: &mut R, len: u64) -> io::Result<Option<MediaDate>> {
+    let Some(moov) = find_box(file, (0, len), b"moov")? else {
+        return Ok(None);
+    };
+
+    if let Some(udta) = find_box(file, moov, b"udta")?
+        && let Some((meta_start, meta_end)) = find_box(file, udta, b"meta")?
+    {
+        // `meta` is a full box in MP4, with a version and flags before its children, but
+        // QuickTime writes it without them
+        let starts_with_child = read_range(file, (meta_start, meta_start + 8))?
+            .get(4..8)
+            .is_some_and(|name| name == b"hdlr");
+        let children = if starts_with_child {
+            (meta_start, meta_end)
+        } else {
+            (meta_start + 4, meta_end)
+        };
+
+        if let Some(ilst) = find_box(file, children, b"ilst")?
+            && let Some(day) = find_box(file, ilst, b"\xa9day")?
+            && let Some((data_start, data_end)) = find_box(file, day, b"data")?
+        {
+            // The value follows a type and a locale
+            let value = read_range(file, (data_start + 8, data_end))?;
+            let date = parse_tag_date(&String::from_utf8_lossy(&value), DateSource::Mp4Day);
+            if let Some(date) = date {
+                return Ok(Some(utc_as_utc_only(date)));
+            }
+        }
+    }
+
+    let Some(mvhd) = find_box(file, moov, b"mvhd")? else {
+        return Ok(None);
+    };
+    let header = read_range(file, (mvhd.0, mvhd.0 + 12))?;
+    let seconds = if header[0] == 1 {
+        u64::from_be_bytes(header[4..12].try_into().expect("read 12 bytes"))
+    } else {
+        u32::from_be_bytes(header[4..8].try_into().expect("read 12 bytes")) as u64
+    };
+
+    Ok(mac_time(seconds).map(|utc| MediaDate::from_utc(utc, DateSource::Mp4CreationTime)))
+}
+
+/// Takes the next `count` bytes of `bytes`, moving `position` past them.
+fn take<'a>(bytes: &'a [u8], position: &mut usize, count: usize) -> Option<&'a [u8]> {
+    let taken = bytes.get(*position..*position + count)?;
+    *position += count;
+    Some(taken)
+}
+
+/// Finds a comment in a Vorbis comment block, whose field names are case-insensitive.
+fn vorbis_comment(block: &[u8], field: &str) -> Option<String> {
+    let mut position = 0;
+    let length = |position: &mut usize| {
+        take(block, position, 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().expect("took 4 bytes")) as usize)
+    };
+
+    let vendor_length = length(&mut position)?;
+    take(block, &mut position, vendor_length)?;
+    let count = length(&mut position)?;
+
+    for _ in 0..count {
+        let comment_length = length(&mut position)?;
+        let comment = String::from_utf8_lossy(take(block, &mut position, comment_length)?);
+        if let Some((name, value)) = comment.split_once('=')
+            && name.eq_ignore_ascii_case(field)
+        {
+            return Some(value.to_string());
+        }
+    }
+
+    None
+}
+
+/// Reads the `DATE` Vorbis comment of a FLAC file.
+fn flac_date<R: Read + Seek>(file: &mut R) -> io::Result<Option<MediaDate>> {
+    file.seek(SeekFrom::Start(4))?;
+
+    loop {
+        let header = read_bytes(file, 4)?;
+        let size = u32::from_be_bytes([0, header[1], header[2], header[3]]) as u64;
+
+        if header[0] & 0x7F == 4 {
+            let block = read_bytes(file, size)?;
+            return Ok(vorbis_comment(&block, "DATE")
+                .and_then(|value| parse_tag_date(&value, DateSource::VorbisDate)));
+        }
+        if header[0] & 0x80 != 0 {
+            return Ok(None);
+        }
+        file.seek(SeekFrom::Current(size as i64))?;
+    }
+}
+
+/// Lists the chunks of a RIFF or AIFF file within `range`, which differ only in the byte
+/// order of their sizes.
+fn list_chunks<R: Read + Seek>(
+    file: &mut R,
+    (start, end): Range,
+    big_endian: bool,
+) -> io::Result<Vec<([u8; 4], Range)>> {
+    let mut chunks = Vec::new();
+    let mut position = start;
+
+    while position + 8 <= end {
+        file.seek(SeekFrom::Start(position))?;
+        let header = read_bytes(file, 8)?;
+        let id = [header[0], header[1], header[2], header[3]];
+        let size_bytes = [header[4], header[5], header[6], header[7]];
+        let size = if big_endian {
+            u32::from_be_bytes(size_bytes)
+        } else {
+            u32::from_le_bytes(size_bytes)
+        } as u64;
+
+        chunks.push((id, (position + 8, (position + 8 + size).min(end))));
+        // Chunks are padded to an even size
+        position += 8 + size + size % 2;
+    }
+
+    Ok(chunks)
+}
+
+/// Reads the origination date and time of a Broadcast Wave `bext` chunk. Both may use any
+/// separator, and a missing time leaves just the date.
+fn bext_date(contents: &[u8]) -> Option<MediaDate> {
+    let digits = |bytes: &[u8]| -> String {
+        bytes
+            .iter()
+            .filter(|b| b.is_ascii_digit())
+            .map(|&b| b as char)
+            .collect()
+    };
+    let date = digits(contents.get(320..330)?);
+    let time = contents.get(330..338).map(digits).unwrap_or_default();
+
+    match NaiveDateTime::parse_from_str(&format!("{}{}", date, time), "%Y%m%d%H%M%S") {
+        Ok(date_time) => Some(MediaDate::new(
+            date_time,
+            DatePrecision::Second,
+            DateSource::BextOriginationDate,
+        )),
+        Err(_) => NaiveDate::parse_from_str(&date, "%Y%m%d")
+            .ok()
+            .map(|date| MediaDate::from_date(date, DateSource::BextOriginationDate)),
+    }
+}
+
+/// Reads the Broadcast Wave `bext` origination date of a WAV file, then the `ICRD`
+/// creation date of its `LIST/INFO` chunk.
+fn wav_date<R: Read + Seek>(file: &mut R, len: u64) -> io::Result<Option<MediaDate>> {
+    let chunks = list_chunks(file, (12, len), false)?;
+
+    for (_, range) in chunks.iter().filter(|(id, _)| id == b"bext") {
+        if let Some(date) = bext_date(&read_range(file, *range)?) {
+            return Ok(Some(date));
+        }
+    }
+
+    for (_, range) in chunks.iter().filter(|(id, _)| id == b"LIST") {
+        let list = read_range(file, *range)?;
+        if !list.starts_with(b"INFO") {
+            continue;
+        }
+
+        let mut info = Cursor::new(&list);
+        for (id, range) in list_chunks(&mut info, (4, list.len() as u64), false)? {
+            if &id == b"ICRD"
+                && let Some(date) = parse_tag_date(
+                    &String::from_utf8_lossy(&read_range(&mut info, range)?),
+                    DateSource::RiffCreationDate,
+                )
+            {
+                return Ok(Some(date));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reads the time stamp of the first comment in an AIFF `COMT` chunk, which is in the
+/// local time of the recording.
+fn aiff_date<R: Read + Seek>(file: &mut R, len: u64) -> io::Result<Option<MediaDate>> {
+    for (id, range) in list_chunks(file, (12, len), true)? {
+        if &id != b"COMT" {
+            continue;
+        }
+
+        let comments = read_range(file, range)?;
+        if let Some(count) = comments.get(..2)
+            && count != [0, 0]
+            && let Some(time_stamp) = comments.get(2..6)
+            && let Some(date_time) =
+                mac_time(u32::from_be_bytes(time_stamp.try_into().expect("took 4 bytes")) as u64)
+        {
+            return Ok(Some(MediaDate::new(
+                date_time,
+                DatePrecision::Second,
+                DateSource::AiffCommentTime,
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(name: &str, bytes: &[u8]) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        std::fs::write(&path, bytes).unwrap();
+        (dir, path)
+    }
+
+    fn mp4_box(name: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+        let mut bytes = ((contents.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(name);
+        bytes.extend_from_slice(contents);
+        bytes
+    }
+
+    fn chunk(id: &[u8; 4], contents: &[u8], big_endian: bool) -> Vec<u8> {
+        let size = contents.len() as u32;
+        let mut bytes = id.to_vec();
+        bytes.extend(if big_endian {
+            size.to_be_bytes()
+        } else {
+            size.to_le_bytes()
+        });
+        bytes.extend_from_slice(contents);
+        if contents.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    fn date_of(name: &str, bytes: &[u8]) -> Option<MediaDate> {
+        let (_dir, path) = write(name, bytes);
+        let date = read_container_date(&path).unwrap();
+
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            bytes,
+            "{} was modified",
+            name
+        );
+        date
+    }
+
+    #[test]
+    fn reads_m4a_day_atom() {
+        let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+        data.extend_from_slice(b"2024-11-11");
+        let ilst = mp4_box(b"ilst", &mp4_box(b"\xa9day", &mp4_box(b"data", &data)));
+        let meta = mp4_box(b"meta", &[&[0, 0, 0, 0][..], &ilst].concat());
+        let mut file = mp4_box(b"ftyp", b"M4A \0\0\0\0");
+        file.extend(mp4_box(b"moov", &mp4_box(b"udta", &meta)));
+
+        let date = date_of("memo.m4a", &file).unwrap();
+        assert_eq!(date.to_string(), "2024-11-11");
+        assert_eq!(date.source, DateSource::Mp4Day);
+    }
+
+    #[test]
+    fn falls_back_to_the_mp4_creation_time() {
+        let mut mvhd = vec![0; 12];
+        let seconds = (MAC_EPOCH_OFFSET + 1_731_351_792) as u32;
+        mvhd[4..8].copy_from_slice(&seconds.to_be_bytes());
+        let mut file = mp4_box(b"ftyp", b"M4A \0\0\0\0");
+        file.extend(mp4_box(b"moov", &mp4_box(b"mvhd", &mvhd)));
+
+        let date = date_of("memo.m4a", &file).unwrap();
+        assert_eq!(date.source, DateSource::Mp4CreationTime);
+        assert!(date.utc_only);
+        assert_eq!(date.to_string(), "2024-11-11T19:03:12+00:00");
+
+        // Recorders without a set clock write zero
+        let mut file = mp4_box(b"ftyp", b"M4A \0\0\0\0");
+        file.extend(mp4_box(b"moov", &mp4_box(b"mvhd", &[0; 12])));
+        assert_eq!(date_of("memo.m4a", &file), None);
+    }
+
+    #[test]
+    fn reads_flac_vorbis_date() {
+        let mut comments = 4u32.to_le_bytes().to_vec();
+        comments.extend_from_slice(b"test");
+        comments.extend(2u32.to_le_bytes());
+        for comment in ["TITLE=Song", "date=1998-07"] {
+            comments.extend((comment.len() as u32).to_le_bytes());
+            comments.extend_from_slice(comment.as_bytes());
+        }
+        let mut file = b"fLaC".to_vec();
+        file.extend([0, 0, 0, 2, 0, 0]);
+        file.extend([0x84, 0, 0, comments.len() as u8]);
+        file.extend(comments);
+
+        let date = date_of("song.flac", &file).unwrap();
+        assert_eq!(date.to_string(), "1998-07");
+        assert_eq!(date.precision, DatePrecision::Month);
+        assert_eq!(date.source, DateSource::VorbisDate);
+    }
+
+    fn wav(chunks: &[&[u8]]) -> Vec<u8> {
+        let mut file = b"RIFF\0\0\0\0WAVE".to_vec();
+        file.extend(chunk(b"fmt ", &[0; 16], false));
+        for chunk in chunks {
+            file.extend_from_slice(chunk);
+        }
+        file
+    }
+
+    #[test]
+    fn reads_wav_bext_then_info_dates() {
+        let info = [&b"INFO"[..], &chunk(b"ICRD", b"2021\0", false)].concat();
+        let list = chunk(b"LIST", &info, false);
+        let mut bext = vec![0; 602];
+        bext[320..338].copy_from_slice(b"2023:05:0614-15-16");
+
+        let date = date_of("take.wav", &wav(&[&list])).unwrap();
+        assert_eq!(date.to_string(), "2021");
+        assert_eq!(date.source, DateSource::RiffCreationDate);
+
+        let date = date_of("take.wav", &wav(&[&list, &chunk(b"bext", &bext, false)])).unwrap();
+        assert_eq!(date.to_string(), "2023-05-06T14:15:16");
+        assert_eq!(date.source, DateSource::BextOriginationDate);
+    }
+
+    #[test]
+    fn reads_aiff_comment_time_stamp() {
+        let mut comments = 1u16.to_be_bytes().to_vec();
+        comments.extend(((MAC_EPOCH_OFFSET + 946_728_000) as u32).to_be_bytes());
+        comments.extend([0, 0, 0, 3]);
+        comments.extend_from_slice(b"Mix\0");
+        let mut file = b"FORM\0\0\0\0AIFF".to_vec();
+        file.extend(chunk(b"COMM", &[0; 18], true));
+        file.extend(chunk(b"COMT", &comments, true));
+
+        let date = date_of("mix.aiff", &file).unwrap();
+        assert_eq!(date.to_string(), "2000-01-01T12:00:00");
+        assert_eq!(date.source, DateSource::AiffCommentTime);
+    }
+
+    #[test]
+    fn other_and_truncated_files_have_no_container_date() {
+        assert_eq!(date_of("song.mp3", &[0xFF, 0xFB, 0x90, 0x64]), None);
+        assert_eq!(date_of("song.flac", b"fLaC\0\0\0\x22\0\0\0\0"), None);
+        assert_eq!(date_of("take.wav", b"RIFF\0\0\0\0WAVEbext\xff\xff"), None);
+
+        let mut oversized = mp4_box(b"ftyp", b"M4A \0\0\0\0");
+        oversized.extend_from_slice(&[0, 0, 0, 1]);
+        oversized.extend_from_slice(b"free");
+        oversized.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert_eq!(date_of("memo.m4a", &oversized), None);
+    }
+}
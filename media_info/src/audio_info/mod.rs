@@ -1,35 +1,96 @@
+use crate::error::MediaInfoError;
+use crate::media_date::{DatePrecision, DateSource, MediaDate};
+use chrono::{DateTime, NaiveDate};
+use id3::{ErrorKind, Tag as ID3Tag, TagLike};
 use std::path::Path;
 
-use chrono::NaiveDate;
-use id3::ErrorKind;
-use id3::{Tag as ID3Tag, TagLike};
-
-mod dates;
-
+mod container_dates;
 pub mod struct_audio_info;
 
-pub fn read_audio_creation_date(path: &Path) -> Result<String, String> {
+/// Reads the ID3 tag of a file, telling a file without a tag apart from a broken one.
+pub(crate) fn read_id3(path: &Path) -> Result<ID3Tag, MediaInfoError> {
     if !path.exists() {
-        return Err(format!("File does not exist: {:?}", path));
+        return Err(MediaInfoError::NotFound(path.to_path_buf()));
     }
 
-    let date_recorded = match ID3Tag::read_from_path(path) {
-        Ok(tags) => tags.date_recorded(),
-        Err(why) => match why.kind {
-            ErrorKind::NoTag => dates::make_date_recorded_from_audio_file(path),
-            _ => None,
-        },
+    ID3Tag::read_from_path(path).map_err(|why| match why.kind {
+        ErrorKind::NoTag => MediaInfoError::NoMetadata(format!("No ID3 tag in {:?}", path)),
+        ErrorKind::Io(e) => MediaInfoError::Io(e),
+        _ => MediaInfoError::Corrupt(format!("Could not read ID3 tag from {:?}: {}", path, why)),
+    })
+}
+
+/// Converts an ID3 timestamp, whose month, day and time are all optional.
+fn timestamp_to_date(timestamp: &id3::Timestamp, source: DateSource) -> Option<MediaDate> {
+    let precision = match (timestamp.month, timestamp.day, timestamp.hour) {
+        (None, _, _) => DatePrecision::Year,
+        (Some(_), None, _) => DatePrecision::Month,
+        (Some(_), Some(_), None) => DatePrecision::Day,
+        (Some(_), Some(_), Some(_)) => DatePrecision::Second,
     };
-    let year = date_recorded.map(|t| t.year).expect("No year found");
-    let month = date_recorded
-        .map(|t| t.month.unwrap())
-        .expect("No month found");
-    let day = date_recorded.map(|t| t.day.unwrap()).expect("No day found");
 
-    let assembled_date = NaiveDate::from_ymd_opt(year, month as u32, day as u32);
-    let date_str = assembled_date.unwrap().format("%Y-%m-%d").to_string();
+    let date_time = NaiveDate::from_ymd_opt(
+        timestamp.year,
+        timestamp.month.unwrap_or(1) as u32,
+        timestamp.day.unwrap_or(1) as u32,
+    )?
+    .and_hms_opt(
+        timestamp.hour.unwrap_or(0) as u32,
+        timestamp.minute.unwrap_or(0) as u32,
+        timestamp.second.unwrap_or(0) as u32,
+    )?;
 
-    Ok(date_str)
+    Some(MediaDate::new(date_time, precision, source))
+}
+
+/// Parses a date from a container tag, which may hold just a year, a year and month, a date,
+/// or a timestamp with or without a UTC offset. Dates written with colons and times set off
+/// by a space are accepted too.
+pub(crate) fn parse_tag_date(value: &str, source: DateSource) -> Option<MediaDate> {
+    let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Some(MediaDate::from_fixed(date_time, source));
+    }
+
+    // Only values that start with a four digit year, so `11/11/2024` is not read as year 11
+    let starts_with_year = value.get(..4).is_some_and(|year| {
+        year.bytes().all(|b| b.is_ascii_digit())
+            && !value[4..].starts_with(|c: char| c.is_ascii_digit())
+    });
+    if !starts_with_year {
+        return None;
+    }
+
+    let normalized: String = value
+        .char_indices()
+        .map(|(index, c)| match (index, c) {
+            (4 | 7, ':') => '-',
+            (10, ' ') => 'T',
+            _ => c,
+        })
+        .collect();
+    timestamp_to_date(&normalized.parse().ok()?, source)
+}
+
+/// Reads the date an audio file was recorded, from the native tags of its container first:
+/// the `©day` atom or movie header of an M4A, the `DATE` Vorbis comment of a FLAC, the
+/// `bext` origination date or `LIST/INFO` `ICRD` of a WAV, and the comment time stamp of an
+/// AIFF. Anything else, and containers without a native date, fall back to the ID3
+/// recorded date. Year and month dates keep their precision, and the file is never written to.
+pub fn read_audio_creation_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    if !path.exists() {
+        return Err(MediaInfoError::NotFound(path.to_path_buf()));
+    }
+    if let Some(date) = container_dates::read_container_date(path)? {
+        return Ok(date);
+    }
+
+    let date_recorded = read_id3(path)?
+        .date_recorded()
+        .ok_or_else(|| MediaInfoError::NoMetadata(format!("No recorded date in {:?}", path)))?;
+
+    timestamp_to_date(&date_recorded, DateSource::Id3Recorded)
+        .ok_or_else(|| MediaInfoError::Corrupt(format!("Invalid recorded date in {:?}", path)))
 }
 
 #[cfg(test)]
@@ -42,6 +103,51 @@ mod tests {
         let path = Path::new(raw_path_str);
         let creation_date = read_audio_creation_date(path);
 
-        assert_eq!(creation_date.unwrap().contains("2024-11-11"), true);
+        assert_eq!(
+            creation_date.unwrap().to_string().contains("2024-11-11"),
+            true
+        );
+    }
+
+    #[test]
+    fn partial_timestamps_keep_their_precision() {
+        let timestamp: id3::Timestamp = "2024-11".parse().unwrap();
+        let date = timestamp_to_date(&timestamp, DateSource::Id3Recorded).unwrap();
+
+        assert_eq!(date.precision, DatePrecision::Month);
+        assert_eq!(date.to_string(), "2024-11");
+    }
+
+    #[test]
+    fn parses_partial_and_colon_separated_tag_dates() {
+        let date_of =
+            |value| parse_tag_date(value, DateSource::VorbisDate).map(|date| date.to_string());
+
+        assert_eq!(date_of("1998").as_deref(), Some("1998"));
+        assert_eq!(date_of("2023:05:06").as_deref(), Some("2023-05-06"));
+        assert_eq!(
+            date_of("2023-05-06 14:15:16\0").as_deref(),
+            Some("2023-05-06T14:15:16")
+        );
+        assert_eq!(
+            date_of("2023-05-06T14:15:16+02:00").as_deref(),
+            Some("2023-05-06T14:15:16+02:00")
+        );
+        assert_eq!(date_of("11/11/2024"), None);
+        assert_eq!(date_of("20230506"), None);
+        assert_eq!(date_of("unknown"), None);
+    }
+
+    #[test]
+    fn audio_without_tag_is_left_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("untagged.mp3");
+        std::fs::write(&path, [0xFF, 0xFB, 0x90, 0x64]).unwrap();
+
+        assert!(matches!(
+            read_audio_creation_date(&path),
+            Err(MediaInfoError::NoMetadata(_))
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), [0xFF, 0xFB, 0x90, 0x64]);
     }
 }
@@ -29,10 +29,9 @@ fn format_date(date: Timestamp) -> String {
 /// * `tag` - Reference to the ID3 tag.
 /// # Returns
 /// A `String` representing the recorded date in "YYYY-MM-DD" format.
-/// Panics if the date is not present in the tag.
+/// Returns an empty `String` if the date is not present in the tag.
 pub fn get_date_recorded(tag: &ID3Tag) -> String {
-    let date_recorded = tag.date_recorded().unwrap();
-    format_date(date_recorded)
+    tag.date_recorded().map(format_date).unwrap_or_default()
 }
 
 /// Retrieves the artist from an ID3 tag.
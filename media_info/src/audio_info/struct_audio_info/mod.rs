@@ -1,6 +1,7 @@
 use std::path::Path;
 
-use id3::Tag as ID3Tag;
+use super::read_id3;
+use crate::error::MediaInfoError;
 
 mod id3_tags;
 
@@ -24,11 +25,12 @@ pub struct AudioInfo {
 /// # Returns
 ///
 /// * `Ok(AudioInfo)` if the file exists and metadata is successfully read.
-/// * `Err(String)` if the file does not exist or if there is an error reading metadata.
+/// * `Err(MediaInfoError)` if the file does not exist or if there is an error reading metadata.
 ///
 /// # Errors
 ///
-/// Returns an error if the file does not exist at the given path or if metadata extraction fails.
+/// Returns `NotFound` if the file does not exist at the given path, `NoMetadata` if it has no
+/// ID3 tag, and `Corrupt` or `Io` if the tag cannot be read.
 ///
 /// # Example
 ///
@@ -38,12 +40,8 @@ pub struct AudioInfo {
 /// let audio_info = AudioInfo::new(Path::new("../test-media/Recording.m4a")).unwrap();
 /// ```
 impl AudioInfo {
-  pub fn new (path: &Path) -> Result<Self, String> {
-    if !path.exists() {
-        return Err(format!("File does not exist: {:?}", path));
-    }
-
-    let tag = ID3Tag::read_from_path(path).unwrap();
+  pub fn new (path: &Path) -> Result<Self, MediaInfoError> {
+    let tag = read_id3(path)?;
 
     let date_recorded = id3_tags::get_date_recorded(&tag);
     let artist = id3_tags::get_artist(&tag);
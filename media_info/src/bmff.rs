@@ -0,0 +1,152 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// The start and end of a box's contents.
+pub(crate) type Range = (u64, u64);
+
+/// Reads the header of the box at `position`, from the bytes there, in a parent that ends
+/// at `end`. Returns the box's name and the range of its contents, or `None` when the
+/// header is cut short, its size is smaller than the header, or the box runs past `end`.
+fn box_header(header: &[u8], position: u64, end: u64) -> Option<([u8; 4], Range)> {
+    let name: [u8; 4] = header.get(4..8)?.try_into().ok()?;
+    let (header_length, size) = match u32::from_be_bytes(header.get(..4)?.try_into().ok()?) {
+        0 => (8, end.checked_sub(position)?),
+        1 => (16, u64::from_be_bytes(header.get(8..16)?.try_into().ok()?)),
+        size => (8, u64::from(size)),
+    };
+    if size < header_length {
+        return None;
+    }
+
+    let box_end = position
+        .checked_add(size)
+        .filter(|&box_end| box_end <= end)?;
+    Some((name, (position + header_length, box_end)))
+}
+
+/// Iterates over the boxes in `data` as their names and contents. A box that is cut short
+/// or runs past the end of `data` ends the iteration.
+#[cfg_attr(not(any(feature = "photo", feature = "video")), allow(dead_code))]
+pub(crate) fn boxes(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let end = data.len() as u64;
+    let mut position = 0;
+
+    std::iter::from_fn(move || {
+        let (name, (start, box_end)) =
+            box_header(data.get(usize::try_from(position).ok()?..)?, position, end)?;
+        position = box_end;
+        Some((name, &data[start as usize..box_end as usize]))
+    })
+}
+
+/// Finds the first box named `name` within `range`, returning the range of its contents.
+/// Only the headers of the boxes before it are read.
+pub(crate) fn find_box<R: Read + Seek>(
+    reader: &mut R,
+    (start, end): Range,
+    name: &[u8; 4],
+) -> io::Result<Option<Range>> {
+    let mut position = start;
+
+    while position < end {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut header = Vec::with_capacity(16);
+        reader
+            .by_ref()
+            .take((end - position).min(16))
+            .read_to_end(&mut header)?;
+
+        let Some((found, contents)) = box_header(&header, position, end) else {
+            return Ok(None);
+        };
+        if &found == name {
+            return Ok(Some(contents));
+        }
+        position = contents.1;
+    }
+
+    Ok(None)
+}
+
+/// Reads the contents of the top level `moov` box of a file without reading the media data
+/// around it.
+#[cfg_attr(not(any(feature = "photo", feature = "video")), allow(dead_code))]
+pub(crate) fn read_moov<R: Read + Seek>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let length = reader.seek(SeekFrom::End(0))?;
+    let Some((start, end)) = find_box(reader, (0, length), b"moov")? else {
+        return Ok(None);
+    };
+
+    reader.seek(SeekFrom::Start(start))?;
+    let mut moov = Vec::new();
+    reader.by_ref().take(end - start).read_to_end(&mut moov)?;
+    Ok(Some(moov))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn mp4_box(name: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+        let mut bytes = ((contents.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(name);
+        bytes.extend_from_slice(contents);
+        bytes
+    }
+
+    /// A 64 bit box header whose size reaches past any file.
+    fn oversized(name: &[u8; 4]) -> Vec<u8> {
+        let mut bytes = vec![0, 0, 0, 1];
+        bytes.extend_from_slice(name);
+        bytes.extend_from_slice(&u64::MAX.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn lists_boxes_until_one_runs_past_the_end() {
+        let mut data = mp4_box(b"free", &[0; 4]);
+        data.extend(mp4_box(b"trak", b"track"));
+        data.extend(oversized(b"trak"));
+        data.extend(mp4_box(b"udta", &[]));
+
+        let listed: Vec<_> = boxes(&data).collect();
+        assert_eq!(listed, [(*b"free", &[0; 4][..]), (*b"trak", &b"track"[..])]);
+    }
+
+    #[test]
+    fn boxes_of_size_zero_run_to_the_end() {
+        let mut data = mp4_box(b"ftyp", b"M4A ");
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&[1, 2, 3]);
+
+        let mut file = Cursor::new(&data);
+        // A range that ends inside the header holds no box
+        assert_eq!(find_box(&mut file, (0, 15), b"mdat").unwrap(), None);
+        assert_eq!(
+            find_box(&mut file, (0, data.len() as u64), b"mdat").unwrap(),
+            Some((20, 23))
+        );
+    }
+
+    #[test]
+    fn reads_the_moov_box_past_the_media_data() {
+        let mut data = mp4_box(b"ftyp", b"qt  ");
+        data.extend(mp4_box(b"mdat", &[0; 64]));
+        data.extend(mp4_box(b"moov", b"movie"));
+
+        assert_eq!(
+            read_moov(&mut Cursor::new(&data)).unwrap().as_deref(),
+            Some(&b"movie"[..])
+        );
+    }
+
+    #[test]
+    fn oversized_boxes_end_the_moov_search() {
+        let mut data = mp4_box(b"ftyp", b"crx ");
+        data.extend(oversized(b"free"));
+        data.extend(mp4_box(b"moov", &[0; 16]));
+
+        assert_eq!(read_moov(&mut Cursor::new(&data)).unwrap(), None);
+    }
+}
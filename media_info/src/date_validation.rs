@@ -0,0 +1,243 @@
+use crate::media_date::{DatePrecision, MediaDate};
+use chrono::{Local, Months, NaiveDate, NaiveDateTime, TimeDelta};
+use std::fmt;
+
+/// Why a date was not trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateProblem {
+    /// A day that devices whose clock was never set write, such as 1970-01-01.
+    Placeholder(NaiveDate),
+    /// A date before the earliest plausible day.
+    TooEarly(NaiveDate),
+    /// A date after the latest plausible day, usually in the future.
+    TooLate(NaiveDate),
+}
+
+impl fmt::Display for DateProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateProblem::Placeholder(day) => write!(f, "{} is a placeholder date", day),
+            DateProblem::TooEarly(day) => {
+                write!(f, "{} is before the earliest plausible date", day)
+            }
+            DateProblem::TooLate(day) => write!(f, "{} is after the latest plausible date", day),
+        }
+    }
+}
+
+/// The days written by devices whose clock was never set: the 1904 and 1970 epochs, the
+/// 1980 epoch of FAT and ZIP time stamps, and the 2000-01-01 many cameras reset to.
+pub const PLACEHOLDER_DAYS: [(i32, u32, u32); 4] =
+    [(1904, 1, 1), (1970, 1, 1), (1980, 1, 1), (2000, 1, 1)];
+
+/// The checks a date has to pass before it is trusted.
+///
+/// # Example
+///
+/// ```rust
+/// use media_info::{DatePrecision, DateProblem, DateSource, DateValidation, MediaDate};
+///
+/// let unset_clock = MediaDate::new(
+///     "1970-01-01T00:00:12".parse().unwrap(),
+///     DatePrecision::Second,
+///     DateSource::ExifDateTimeOriginal,
+/// );
+/// assert!(matches!(
+///     DateValidation::default().check(&unset_clock),
+///     Err(DateProblem::Placeholder(_))
+/// ));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateValidation {
+    /// The earliest plausible day. Defaults to 1826-01-01, the year of the oldest surviving
+    /// photograph.
+    pub earliest: NaiveDate,
+    /// The latest plausible day. `None` allows dates up to a day after today, leaving room
+    /// for time zones ahead of this machine's.
+    pub latest: Option<NaiveDate>,
+    /// Days rejected as placeholders. Only full dates on those days are rejected, so a
+    /// folder named `1970` is still trusted.
+    pub placeholders: Vec<NaiveDate>,
+    /// How far apart two sources' dates may be before a file is flagged for review. `None`
+    /// does not compare sources.
+    pub max_disagreement: Option<TimeDelta>,
+}
+
+impl Default for DateValidation {
+    fn default() -> Self {
+        DateValidation {
+            earliest: NaiveDate::from_ymd_opt(1826, 1, 1).unwrap_or_default(),
+            latest: None,
+            placeholders: PLACEHOLDER_DAYS
+                .iter()
+                .filter_map(|&(year, month, day)| NaiveDate::from_ymd_opt(year, month, day))
+                .collect(),
+            max_disagreement: None,
+        }
+    }
+}
+
+/// The first and last second a date covers at its precision, such as all of 1998 for a
+/// year.
+fn span(date: &MediaDate) -> (NaiveDateTime, NaiveDateTime) {
+    let start = date.date_time;
+    let next = match date.precision {
+        DatePrecision::Year => start.checked_add_months(Months::new(12)),
+        DatePrecision::Month => start.checked_add_months(Months::new(1)),
+        DatePrecision::Day => start.checked_add_signed(TimeDelta::days(1)),
+        DatePrecision::Second => return (start, start),
+    };
+    let end = next.map_or(start, |next| next - TimeDelta::seconds(1));
+    (start, end)
+}
+
+impl DateValidation {
+    /// Checks a date against the placeholders and the plausible range.
+    pub fn check(&self, date: &MediaDate) -> Result<(), DateProblem> {
+        let day = date.date();
+        let latest = self
+            .latest
+            .unwrap_or_else(|| Local::now().date_naive() + TimeDelta::days(1));
+
+        if matches!(date.precision, DatePrecision::Day | DatePrecision::Second)
+            && self.placeholders.contains(&day)
+        {
+            Err(DateProblem::Placeholder(day))
+        } else if span(date).1.date() < self.earliest {
+            Err(DateProblem::TooEarly(day))
+        } else if day > latest {
+            Err(DateProblem::TooLate(day))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// How far apart two dates are, when that is more than `max_disagreement`. Dates that
+    /// are not down to the second cover their whole year, month or day, and dates with an
+    /// offset are compared as instants when both have one.
+    pub fn disagreement(&self, date: &MediaDate, other: &MediaDate) -> Option<TimeDelta> {
+        let max = self.max_disagreement?;
+        let gap = match (date.to_fixed(), other.to_fixed()) {
+            (Some(date), Some(other)) => (date - other).abs(),
+            _ => {
+                let (date_start, date_end) = span(date);
+                let (other_start, other_end) = span(other);
+                if date_end < other_start {
+                    other_start - date_end
+                } else if other_end < date_start {
+                    date_start - other_end
+                } else {
+                    TimeDelta::zero()
+                }
+            }
+        };
+
+        (gap > max).then_some(gap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media_date::DateSource;
+
+    fn date(date_time: &str, precision: DatePrecision) -> MediaDate {
+        MediaDate::new(
+            date_time.parse().unwrap(),
+            precision,
+            DateSource::ExifDateTimeOriginal,
+        )
+    }
+
+    #[test]
+    fn rejects_placeholders_only_at_day_precision_or_finer() {
+        let validation = DateValidation::default();
+
+        assert!(matches!(
+            validation.check(&date("2000-01-01T00:03:00", DatePrecision::Second)),
+            Err(DateProblem::Placeholder(_))
+        ));
+        assert!(matches!(
+            validation.check(&date("1980-01-01T00:00:00", DatePrecision::Day)),
+            Err(DateProblem::Placeholder(_))
+        ));
+        assert_eq!(
+            validation.check(&date("1970-01-01T00:00:00", DatePrecision::Year)),
+            Ok(())
+        );
+        assert_eq!(
+            validation.check(&date("2000-01-02T00:03:00", DatePrecision::Second)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_dates_outside_the_bounds() {
+        let validation = DateValidation {
+            earliest: "1990-01-01".parse().unwrap(),
+            latest: Some("2025-12-31".parse().unwrap()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            validation.check(&date("0000-01-01T00:00:00", DatePrecision::Second)),
+            Err(DateProblem::TooEarly("0000-01-01".parse().unwrap()))
+        );
+        assert_eq!(
+            validation.check(&date("2026-01-01T00:00:00", DatePrecision::Second)),
+            Err(DateProblem::TooLate("2026-01-01".parse().unwrap()))
+        );
+        assert_eq!(
+            validation.check(&date("1989-01-01T00:00:00", DatePrecision::Year)),
+            Err(DateProblem::TooEarly("1989-01-01".parse().unwrap()))
+        );
+        assert_eq!(
+            validation.check(&date("2025-12-31T23:59:59", DatePrecision::Second)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn future_dates_are_rejected_by_default() {
+        let tomorrow_but_one = Local::now().naive_local() + TimeDelta::days(2);
+        let date = MediaDate::new(
+            tomorrow_but_one,
+            DatePrecision::Second,
+            DateSource::FileModifiedTime,
+        );
+
+        assert!(matches!(
+            DateValidation::default().check(&date),
+            Err(DateProblem::TooLate(_))
+        ));
+    }
+
+    #[test]
+    fn disagreement_allows_for_precision_and_offsets() {
+        let validation = DateValidation {
+            max_disagreement: Some(TimeDelta::days(2)),
+            ..Default::default()
+        };
+        let exif = date("2019-07-04T18:30:00", DatePrecision::Second);
+
+        assert_eq!(
+            validation.disagreement(&exif, &date("2019-01-01T00:00:00", DatePrecision::Year)),
+            None
+        );
+        assert_eq!(
+            validation.disagreement(&exif, &date("2019-07-10T00:00:00", DatePrecision::Day)),
+            Some(TimeDelta::minutes(5 * 24 * 60 + 5 * 60 + 30))
+        );
+
+        let tokyo = MediaDate::from_fixed(
+            "2019-07-07T10:30:00+09:00".parse().unwrap(),
+            DateSource::QuickTimeCreationDate,
+        );
+        let denver = MediaDate::from_fixed(
+            "2019-07-04T19:30:00-06:00".parse().unwrap(),
+            DateSource::ExifDateTimeOriginal,
+        );
+        assert_eq!(validation.disagreement(&denver, &tokyo), None);
+        assert_eq!(DateValidation::default().disagreement(&exif, &tokyo), None);
+    }
+}
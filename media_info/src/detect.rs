@@ -0,0 +1,459 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// The kind of media a file holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    Photo,
+    Video,
+    Audio,
+    Document,
+    Unknown,
+}
+
+/// How many bytes of the file are sniffed. Enough for an `ftyp` box with a few
+/// compatible brands, the MOBI header and the first two frames of MPEG audio.
+const HEADER_LEN: usize = 4096;
+
+const PHOTO_BRANDS: [&[u8; 4]; 11] = [
+    b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1", b"avif", b"avis",
+    b"crx ",
+];
+
+const AUDIO_BRANDS: [&[u8; 4]; 4] = [b"M4A ", b"M4B ", b"M4P ", b"F4A "];
+
+const VIDEO_BRANDS: [&[u8; 4]; 17] = [
+    b"qt  ", b"isom", b"iso2", b"iso4", b"iso5", b"iso6", b"mp41", b"mp42", b"avc1", b"M4V ",
+    b"M4VH", b"M4VP", b"dash", b"3gp4", b"3gp5", b"3gp6", b"3g2a",
+];
+
+/// The sync byte every MPEG transport stream packet starts with.
+const TS_SYNC: u8 = 0x47;
+
+/// The bitrates of MPEG-1 audio in kbit/s for layers I, II and III, by bitrate index from 1.
+const MPEG1_BITRATES: [[u32; 14]; 3] = [
+    [
+        32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+];
+/// The bitrates of MPEG-2 and MPEG-2.5 audio in kbit/s for layer I, then layers II and III.
+const MPEG2_BITRATES: [[u32; 14]; 2] = [
+    [
+        32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+/// The sample rates of MPEG-1 audio by index, halved for MPEG-2 and quartered for MPEG-2.5.
+const MPEG1_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// Reads the length of the MPEG audio frame `bytes` start with. Headers with the reserved
+/// version or layer, a free or invalid bitrate or an invalid sample rate are not frames.
+fn mpeg_frame_length(bytes: &[u8]) -> Option<usize> {
+    let &[sync, flags, rates, ..] = bytes else {
+        return None;
+    };
+    if sync != 0xFF || flags & 0xE0 != 0xE0 {
+        return None;
+    }
+
+    // Versions 2.5, reserved, 2 and 1, and layers reserved, III, II and I
+    let version = (flags >> 3) & 0x03;
+    let layer = (flags >> 1) & 0x03;
+    let bitrate = (rates >> 4) as usize;
+    let sample_rate = ((rates >> 2) & 0x03) as usize;
+    if version == 1 || layer == 0 || bitrate == 0 || bitrate == 15 || sample_rate == 3 {
+        return None;
+    }
+
+    let bitrate = 1000
+        * match (version, layer) {
+            (3, _) => MPEG1_BITRATES[3 - layer as usize][bitrate - 1],
+            (_, 3) => MPEG2_BITRATES[0][bitrate - 1],
+            _ => MPEG2_BITRATES[1][bitrate - 1],
+        };
+    let sample_rate = match version {
+        3 => MPEG1_SAMPLE_RATES[sample_rate],
+        2 => MPEG1_SAMPLE_RATES[sample_rate] / 2,
+        _ => MPEG1_SAMPLE_RATES[sample_rate] / 4,
+    };
+    let padding = ((rates >> 1) & 0x01) as u32;
+
+    let length = match (version, layer) {
+        (_, 3) => (12 * bitrate / sample_rate + padding) * 4,
+        (0 | 2, 1) => 72 * bitrate / sample_rate + padding,
+        _ => 144 * bitrate / sample_rate + padding,
+    };
+    Some(length as usize)
+}
+
+/// Classifies an ISO base media file by its major brand, then by its compatible brands.
+fn kind_of_brands(header: &[u8]) -> Option<MediaKind> {
+    let box_size = u32::from_be_bytes(header[0..4].try_into().ok()?) as usize;
+    let ftyp_end = box_size.clamp(12, header.len());
+    let major_brand = &header[8..12];
+    let compatible_brands = header.get(16..ftyp_end).unwrap_or_default().chunks_exact(4);
+
+    std::iter::once(major_brand)
+        .chain(compatible_brands)
+        .find_map(|brand| {
+            if PHOTO_BRANDS.iter().any(|b| b.as_slice() == brand) {
+                Some(MediaKind::Photo)
+            } else if AUDIO_BRANDS.iter().any(|b| b.as_slice() == brand) {
+                Some(MediaKind::Audio)
+            } else if VIDEO_BRANDS.iter().any(|b| b.as_slice() == brand) {
+                Some(MediaKind::Video)
+            } else {
+                None
+            }
+        })
+}
+
+/// Identifies the media kind from the first bytes of a file, or `None` when the bytes do
+/// not match a known signature.
+fn sniff(header: &[u8]) -> Option<MediaKind> {
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, &[0xFF, 0xD8, 0xFF])
+        || at(0, b"\x89PNG\r\n\x1a\n")
+        || at(0, b"II*\0")
+        || at(0, b"MM\0*")
+    {
+        return Some(MediaKind::Photo);
+    }
+    // Camera RAWs: Olympus ORF and Panasonic RW2 change the TIFF magic number, and
+    // Fujifilm RAF has a header of its own.
+    if at(0, b"IIRO")
+        || at(0, b"IIRS")
+        || at(0, b"MMOR")
+        || at(0, b"IIU\0")
+        || at(0, b"FUJIFILMCCD-RAW")
+    {
+        return Some(MediaKind::Photo);
+    }
+    if at(4, b"ftyp") && header.len() >= 12 {
+        return kind_of_brands(header);
+    }
+    // QuickTime files written before `ftyp` existed start straight with a movie atom.
+    if at(4, b"moov") || at(4, b"mdat") || at(4, b"wide") {
+        return Some(MediaKind::Video);
+    }
+    // Matroska and WebM start with an EBML header.
+    if at(0, b"\x1A\x45\xDF\xA3") {
+        return Some(MediaKind::Video);
+    }
+    if at(0, b"RIFF") {
+        return match header.get(8..12) {
+            Some(b"WAVE") => Some(MediaKind::Audio),
+            Some(b"WEBP") => Some(MediaKind::Photo),
+            Some(b"AVI ") => Some(MediaKind::Video),
+            _ => None,
+        };
+    }
+    // MPEG transport streams are 188 byte packets, or 192 byte packets behind a time stamp
+    // in the `.MTS` and `.M2TS` clips of AVCHD camcorders.
+    if (at(0, &[TS_SYNC]) && at(188, &[TS_SYNC])) || (at(4, &[TS_SYNC]) && at(196, &[TS_SYNC])) {
+        return Some(MediaKind::Video);
+    }
+    if at(0, b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")) {
+        return Some(MediaKind::Audio);
+    }
+    if at(0, b"fLaC") || at(0, b"ID3") {
+        return Some(MediaKind::Audio);
+    }
+    // MPEG audio without an ID3 tag starts straight with a frame. Its 11 bit sync word
+    // alone also matches text such as the UTF-16 byte order mark, so the next frame has to
+    // follow it.
+    if let Some(length) = mpeg_frame_length(header)
+        && mpeg_frame_length(header.get(length..).unwrap_or_default()).is_some()
+    {
+        return Some(MediaKind::Audio);
+    }
+    if at(0, b"%PDF-") || at(0, b"{\\rtf") || at(60, b"BOOKMOBI") {
+        return Some(MediaKind::Document);
+    }
+
+    None
+}
+
+/// ZIP is the container of EPUB, OpenDocument and Office files, so the entries decide.
+fn sniff_zip(path: &Path) -> Option<MediaKind> {
+    let file = File::open(path).ok()?;
+    let mut archive = zip::ZipArchive::new(file).ok()?;
+
+    if let Ok(mut mimetype) = archive.by_name("mimetype") {
+        let mut contents = String::new();
+        mimetype.read_to_string(&mut contents).ok()?;
+
+        return match contents.trim() {
+            "application/epub+zip" | "application/vnd.oasis.opendocument.text" => {
+                Some(MediaKind::Document)
+            }
+            _ => None,
+        };
+    }
+
+    if archive.index_for_name("word/document.xml").is_some() {
+        return Some(MediaKind::Document);
+    }
+
+    None
+}
+
+/// The extensions of camera RAW photos, in lower case.
+pub const RAW_EXTENSIONS: [&str; 8] = ["cr2", "cr3", "nef", "arw", "dng", "orf", "rw2", "raf"];
+
+fn lowercase_extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// The extensions of the index, playlist and clip information files AVCHD camcorders keep
+/// next to their clips, in lower case.
+const AVCHD_INDEX_EXTENSIONS: [&str; 3] = ["bdm", "mpl", "cpi"];
+
+/// Whether a file is part of the index an AVCHD camcorder keeps in the `BDMV` folder of
+/// its `PRIVATE/AVCHD/BDMV/STREAM` layout, rather than one of the clips in `STREAM`.
+pub fn is_avchd_index(path: &Path) -> bool {
+    AVCHD_INDEX_EXTENSIONS.contains(&lowercase_extension(path).as_str())
+        && path.ancestors().skip(1).any(|folder| {
+            folder
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.eq_ignore_ascii_case("BDMV"))
+        })
+}
+
+/// Whether a file is named as a camera RAW photo, ignoring case.
+pub fn is_raw(path: &Path) -> bool {
+    RAW_EXTENSIONS.contains(&lowercase_extension(path).as_str())
+}
+
+/// Classifies a file by its extension, ignoring case.
+pub fn kind_from_extension(path: &Path) -> MediaKind {
+    let ext = lowercase_extension(path);
+
+    match ext.as_str() {
+        "jpg" | "jpeg" | "png" | "tiff" | "heif" | "heic" | "avif" | "webp" => MediaKind::Photo,
+        ext if RAW_EXTENSIONS.contains(&ext) => MediaKind::Photo,
+        "mp4" | "mov" | "3gp" | "3g2" | "mkv" | "webm" | "avi" | "mts" | "m2ts" => MediaKind::Video,
+        "mp3" | "wav" | "aiff" | "m4a" | "flac" => MediaKind::Audio,
+        "docx" | "doc" | "pdf" | "epub" | "mobi" | "odt" | "txt" | "md" | "rtf" => {
+            MediaKind::Document
+        }
+        _ => MediaKind::Unknown,
+    }
+}
+
+/// Detects the media kind of a file from its content, falling back to the extension
+/// when the content has no recognizable signature.
+///
+/// # Example
+///
+/// ```rust
+/// use media_info::{MediaKind, detect};
+/// use std::path::Path;
+///
+/// let kind = detect(Path::new("../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg"));
+/// assert_eq!(kind, MediaKind::Photo);
+/// ```
+pub fn detect(path: &Path) -> MediaKind {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    let read =
+        File::open(path).and_then(|file| file.take(HEADER_LEN as u64).read_to_end(&mut header));
+
+    if read.is_err() {
+        return kind_from_extension(path);
+    }
+
+    let sniffed = if header.starts_with(b"PK\x03\x04") {
+        sniff_zip(path)
+    } else {
+        sniff(&header)
+    };
+
+    sniffed.unwrap_or_else(|| kind_from_extension(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+        let size = 16 + 4 * compatible.len();
+        let mut header = (size as u32).to_be_bytes().to_vec();
+        header.extend_from_slice(b"ftyp");
+        header.extend_from_slice(major);
+        header.extend_from_slice(&[0, 0, 0, 0]);
+        for brand in compatible {
+            header.extend_from_slice(*brand);
+        }
+        header
+    }
+
+    #[test]
+    fn detects_test_media() {
+        let cases = [
+            ("400a861d-014a-4dfb-9143-1a914212fd4d.jpg", MediaKind::Photo),
+            ("Recording.m4a", MediaKind::Audio),
+            ("TESTDOCUMENT.docx", MediaKind::Document),
+            ("TESTDOCUMENT.epub", MediaKind::Document),
+            ("TESTDOCUMENT.odt", MediaKind::Document),
+            ("TESTDOCUMENT.pdf", MediaKind::Document),
+            ("TESTDOCUMENT.txt", MediaKind::Document),
+            ("TESTDOCUMENT.md", MediaKind::Document),
+            ("TESTSPREADSHEET.xlsx", MediaKind::Unknown),
+        ];
+
+        for (file, kind) in cases {
+            let path = Path::new("../test-media").join(file);
+            assert_eq!(detect(&path), kind, "{}", file);
+        }
+    }
+
+    #[test]
+    fn sniffs_isobmff_brands() {
+        assert_eq!(sniff(&ftyp(b"heic", &[b"mif1"])), Some(MediaKind::Photo));
+        assert_eq!(sniff(&ftyp(b"mif1", &[b"avif"])), Some(MediaKind::Photo));
+        assert_eq!(sniff(&ftyp(b"M4A ", &[b"isom"])), Some(MediaKind::Audio));
+        assert_eq!(sniff(&ftyp(b"qt  ", &[])), Some(MediaKind::Video));
+        assert_eq!(sniff(&ftyp(b"xxxx", &[b"mp42"])), Some(MediaKind::Video));
+        assert_eq!(sniff(&ftyp(b"crx ", &[b"isom"])), Some(MediaKind::Photo));
+    }
+
+    #[test]
+    fn sniffs_camera_raws() {
+        assert_eq!(sniff(b"II*\0\x10\0\0\0CR\x02\0"), Some(MediaKind::Photo));
+        assert_eq!(sniff(b"IIRO\x08\0\0\0"), Some(MediaKind::Photo));
+        assert_eq!(sniff(b"IIU\0\x08\0\0\0"), Some(MediaKind::Photo));
+        assert_eq!(sniff(b"FUJIFILMCCD-RAW 0201"), Some(MediaKind::Photo));
+        assert_eq!(
+            kind_from_extension(Path::new("DSC_0001.NEF")),
+            MediaKind::Photo
+        );
+        assert_eq!(
+            kind_from_extension(Path::new("_MG_0001.cr2")),
+            MediaKind::Photo
+        );
+        assert!(is_raw(Path::new("DSCF0001.RAF")));
+        assert!(!is_raw(Path::new("DSCF0001.JPG")));
+    }
+
+    #[test]
+    fn sniffs_video_containers() {
+        assert_eq!(sniff(&ftyp(b"3gp4", &[b"isom"])), Some(MediaKind::Video));
+        assert_eq!(
+            sniff(b"\x1A\x45\xDF\xA3\x9F\x42\x86\x81"),
+            Some(MediaKind::Video)
+        );
+        assert_eq!(sniff(b"RIFF\0\0\0\0AVI LIST"), Some(MediaKind::Video));
+
+        let mut ts = vec![0; 2 * 188];
+        ts[0] = TS_SYNC;
+        ts[188] = TS_SYNC;
+        assert_eq!(sniff(&ts), Some(MediaKind::Video));
+
+        let mut m2ts = vec![0; 2 * 192];
+        m2ts[4] = TS_SYNC;
+        m2ts[196] = TS_SYNC;
+        assert_eq!(sniff(&m2ts), Some(MediaKind::Video));
+        assert_eq!(sniff(&[TS_SYNC, 0x40, 0x11]), None);
+
+        for name in ["a.MTS", "a.m2ts", "a.mkv", "a.webm", "a.AVI", "a.3gp"] {
+            assert_eq!(
+                kind_from_extension(Path::new(name)),
+                MediaKind::Video,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn avchd_index_files_are_told_apart_from_clips() {
+        assert!(is_avchd_index(Path::new(
+            "card/PRIVATE/AVCHD/BDMV/INDEX.BDM"
+        )));
+        assert!(is_avchd_index(Path::new(
+            "card/PRIVATE/AVCHD/BDMV/CLIPINF/00000.CPI"
+        )));
+        assert!(is_avchd_index(Path::new(
+            "card/PRIVATE/AVCHD/BDMV/PLAYLIST/00000.MPL"
+        )));
+        assert!(!is_avchd_index(Path::new(
+            "card/PRIVATE/AVCHD/BDMV/STREAM/00000.MTS"
+        )));
+        assert!(!is_avchd_index(Path::new("notes/INDEX.BDM")));
+    }
+
+    #[test]
+    fn sniffs_riff_and_other_signatures() {
+        assert_eq!(sniff(b"RIFF\0\0\0\0WAVEfmt "), Some(MediaKind::Audio));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some(MediaKind::Photo));
+        assert_eq!(sniff(b"fLaC\0\0\0\x22"), Some(MediaKind::Audio));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n"), Some(MediaKind::Photo));
+        assert_eq!(sniff(b"plain text"), None);
+    }
+
+    #[test]
+    fn sniffs_mpeg_audio_frames() {
+        // MPEG-1 layer III at 128 kbit/s and 44.1 kHz, whose frames are 417 bytes long
+        let frame = [0xFF, 0xFB, 0x90, 0x64];
+        let mut mp3 = vec![0; 417 + 4];
+        mp3[..4].copy_from_slice(&frame);
+        mp3[417..].copy_from_slice(&frame);
+        assert_eq!(sniff(&mp3), Some(MediaKind::Audio));
+
+        assert_eq!(sniff(&mp3[..417]), None);
+        assert_eq!(sniff(&[0xFF, 0xE9, 0x90, 0x64]), None);
+        assert_eq!(sniff(&[0xFF, 0xF9, 0xF0, 0x64]), None);
+        assert_eq!(sniff(&[0xFF, 0xFB, 0x9C, 0x64]), None);
+    }
+
+    #[test]
+    fn utf16_text_is_not_mpeg_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        let text: Vec<u8> = std::iter::once(0xFEFF)
+            .chain("Hello, world! ".repeat(40).encode_utf16())
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        std::fs::write(&path, &text).unwrap();
+
+        assert_eq!(sniff(&text), None);
+        assert_eq!(detect(&path), MediaKind::Document);
+    }
+
+    #[test]
+    fn content_wins_over_misleading_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("mp4_backups/notes.txt");
+        let backup = dir.path().join("photo.jpg.bak");
+        let renamed = dir.path().join("IMG_0001.txt");
+        std::fs::create_dir_all(notes.parent().unwrap()).unwrap();
+        std::fs::write(&notes, b"hello").unwrap();
+        std::fs::write(&backup, b"hello").unwrap();
+        std::fs::write(&renamed, [0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+
+        assert_eq!(detect(&notes), MediaKind::Document);
+        assert_eq!(detect(&backup), MediaKind::Unknown);
+        assert_eq!(detect(&renamed), MediaKind::Photo);
+    }
+
+    #[test]
+    fn extension_table_ignores_case() {
+        assert_eq!(kind_from_extension(Path::new("a.HEIC")), MediaKind::Photo);
+        assert_eq!(kind_from_extension(Path::new("a.Mov")), MediaKind::Video);
+        assert_eq!(kind_from_extension(Path::new("a.FLAC")), MediaKind::Audio);
+        assert_eq!(kind_from_extension(Path::new("a.RTF")), MediaKind::Document);
+        assert_eq!(kind_from_extension(Path::new("a")), MediaKind::Unknown);
+    }
+}
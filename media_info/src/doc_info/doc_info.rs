@@ -1,126 +1,163 @@
-use chrono::NaiveDate;
+use crate::error::MediaInfoError;
+use crate::media_date::{DatePrecision, DateSource, MediaDate};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
 use epub::doc::EpubDoc;
-use fs_metadata::file_created;
 use mobi::Mobi;
 use std::io::Read;
 use std::path::Path;
 
-fn parse_date_to_ymd(date_str: &str) -> Option<String> {
-    // Try ISO 8601: 2024-01-15T10:30:00Z or 2024-01-15
-    if let Ok(date) = NaiveDate::parse_from_str(&date_str[..10], "%Y-%m-%d") {
-        return Some(date.format("%Y-%m-%d").to_string());
+fn corrupt(message: &str, e: impl std::fmt::Display) -> MediaInfoError {
+    MediaInfoError::Corrupt(format!("{}: {}", message, e))
+}
+
+fn no_date(path: &Path) -> MediaInfoError {
+    MediaInfoError::NoMetadata(format!("No date in document metadata of {:?}", path))
+}
+
+/// Parses the date formats found in document metadata, from a full ISO 8601 timestamp down
+/// to a bare year.
+fn parse_doc_date(date_str: &str, source: DateSource) -> Option<MediaDate> {
+    let date_str = date_str.trim();
+
+    // Try ISO 8601 with an offset: 2024-01-15T10:30:00Z
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(date_str) {
+        return Some(MediaDate::from_fixed(date_time, source));
     }
-    // Try YYYY/MM/DD
-    if let Ok(date) = NaiveDate::parse_from_str(&date_str[..10], "%Y/%m/%d") {
-        return Some(date.format("%Y-%m-%d").to_string());
+    // Try ISO 8601 without an offset: 2024-01-15T10:30:00
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(MediaDate::new(date_time, DatePrecision::Second, source));
     }
-    // Try just a year like "2024"
-    if date_str.len() == 4 {
-        if let Ok(year) = date_str.parse::<i32>() {
-            if let Some(date) = NaiveDate::from_ymd_opt(year, 1, 1) {
-                return Some(date.format("%Y-%m-%d").to_string());
-            }
+    // Try YYYY-MM-DD and YYYY/MM/DD, ignoring anything after the date
+    let day = date_str.get(..10).unwrap_or(date_str);
+    for format in ["%Y-%m-%d", "%Y/%m/%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(day, format) {
+            return Some(MediaDate::from_date(date, source));
         }
     }
+    // Try just a year like "2024"
+    if date_str.len() == 4
+        && let Ok(year) = date_str.parse::<i32>()
+        && let Some(date) = NaiveDate::from_ymd_opt(year, 1, 1)
+    {
+        let mut date = MediaDate::from_date(date, source);
+        date.precision = DatePrecision::Year;
+        return Some(date);
+    }
     None
 }
 
-pub fn read_epub_date(path: &Path) -> Result<String, String> {
-    let doc = EpubDoc::new(path).map_err(|e| format!("Failed to open EPUB: {}", e))?;
+/// Converts a PDF date, whose UTC offset is stored as hours and minutes with a direction.
+fn pdf_date_to_date(date: &pdf::primitive::Date, source: DateSource) -> Option<MediaDate> {
+    let date_time = NaiveDate::from_ymd_opt(date.year as i32, date.month as u32, date.day as u32)?
+        .and_hms_opt(date.hour as u32, date.minute as u32, date.second as u32)?;
+    let offset_secs = (date.tz_hour as i32 * 60 + date.tz_minute as i32) * 60;
+    let offset = match date.rel {
+        pdf::primitive::TimeRel::Later => FixedOffset::east_opt(offset_secs),
+        pdf::primitive::TimeRel::Earlier => FixedOffset::west_opt(offset_secs),
+        pdf::primitive::TimeRel::Universal => FixedOffset::east_opt(0),
+    }?;
+
+    Some(MediaDate::new(date_time, DatePrecision::Second, source).with_offset(offset))
+}
+
+pub fn read_epub_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    let doc = EpubDoc::new(path).map_err(|e| corrupt("Failed to open EPUB", e))?;
 
     // Try multiple metadata keys in priority order
     let keys = ["dcterms:created", "dcterms:modified", "date"];
     for key in &keys {
         if let Some(value) = doc.mdata(key) {
-            if let Some(date) = parse_date_to_ymd(&value) {
+            if let Some(date) = parse_doc_date(&value, DateSource::EpubDate) {
                 return Ok(date);
             }
         }
     }
 
-    file_created(path)
+    Err(no_date(path))
 }
 
-pub fn read_mobi_date(path: &Path) -> Result<String, String> {
-    let mobi = Mobi::from_path(path).map_err(|e| format!("Failed to open MOBI: {}", e))?;
+pub fn read_mobi_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    let mobi = Mobi::from_path(path).map_err(|e| corrupt("Failed to open MOBI", e))?;
 
     if let Some(date_str) = mobi.publish_date() {
-        if let Some(date) = parse_date_to_ymd(&date_str) {
+        if let Some(date) = parse_doc_date(&date_str, DateSource::MobiPublishDate) {
             return Ok(date);
         }
     }
 
-    file_created(path)
+    Err(no_date(path))
 }
 
-pub fn read_pdf_date(path: &Path) -> Result<String, String> {
+pub fn read_pdf_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
     let file = pdf::file::FileOptions::cached()
         .open(path)
-        .map_err(|e| format!("Failed to open PDF: {}", e))?;
+        .map_err(|e| corrupt("Failed to open PDF", e))?;
 
     if let Some(ref info) = file.trailer.info_dict {
         // Try CreationDate first, then ModDate
-        let dates = [&info.creation_date, &info.mod_date];
-        for date_opt in &dates {
-            if let Some(date) = date_opt {
-                if let Some(naive) =
-                    NaiveDate::from_ymd_opt(date.year as i32, date.month as u32, date.day as u32)
-                {
-                    return Ok(naive.format("%Y-%m-%d").to_string());
-                }
+        let dates = [
+            (&info.creation_date, DateSource::PdfCreationDate),
+            (&info.mod_date, DateSource::PdfModDate),
+        ];
+        for (date_opt, source) in dates {
+            if let Some(date) = date_opt
+                && let Some(date) = pdf_date_to_date(date, source)
+            {
+                return Ok(date);
             }
         }
     }
 
-    file_created(path)
+    Err(no_date(path))
 }
 
-pub fn read_docx_date(path: &Path) -> Result<String, String> {
-    let file =
-        std::fs::File::open(path).map_err(|e| format!("Failed to open DOCX: {}", e))?;
+pub fn read_docx_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    let file = std::fs::File::open(path)?;
     let mut archive =
-        zip::ZipArchive::new(file).map_err(|e| format!("Failed to read DOCX as ZIP: {}", e))?;
+        zip::ZipArchive::new(file).map_err(|e| corrupt("Failed to read DOCX as ZIP", e))?;
 
     if let Ok(mut core_xml) = archive.by_name("docProps/core.xml") {
         let mut contents = String::new();
         core_xml
             .read_to_string(&mut contents)
-            .map_err(|e| format!("Failed to read core.xml: {}", e))?;
+            .map_err(|e| corrupt("Failed to read core.xml", e))?;
 
         // Look for dcterms:created or dcterms:modified
-        if let Some(date) = extract_xml_date(&contents, "dcterms:created")
-            .or_else(|| extract_xml_date(&contents, "dcterms:modified"))
+        if let Some(date) =
+            extract_xml_date(&contents, "dcterms:created", DateSource::OfficeCreated).or_else(
+                || extract_xml_date(&contents, "dcterms:modified", DateSource::OfficeModified),
+            )
         {
             return Ok(date);
         }
     }
 
-    file_created(path)
+    Err(no_date(path))
 }
 
-pub fn read_odt_date(path: &Path) -> Result<String, String> {
-    let file =
-        std::fs::File::open(path).map_err(|e| format!("Failed to open ODT: {}", e))?;
+pub fn read_odt_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    let file = std::fs::File::open(path)?;
     let mut archive =
-        zip::ZipArchive::new(file).map_err(|e| format!("Failed to read ODT as ZIP: {}", e))?;
+        zip::ZipArchive::new(file).map_err(|e| corrupt("Failed to read ODT as ZIP", e))?;
 
     if let Ok(mut meta_xml) = archive.by_name("meta.xml") {
         let mut contents = String::new();
         meta_xml
             .read_to_string(&mut contents)
-            .map_err(|e| format!("Failed to read meta.xml: {}", e))?;
+            .map_err(|e| corrupt("Failed to read meta.xml", e))?;
 
-        if let Some(date) = extract_xml_date(&contents, "meta:creation-date")
-            .or_else(|| extract_xml_date(&contents, "dc:date"))
+        if let Some(date) =
+            extract_xml_date(&contents, "meta:creation-date", DateSource::OfficeCreated)
+                .or_else(|| extract_xml_date(&contents, "dc:date", DateSource::OfficeModified))
         {
             return Ok(date);
         }
     }
 
-    file_created(path)
+    Err(no_date(path))
 }
 
-fn extract_xml_date(xml: &str, tag: &str) -> Option<String> {
+fn extract_xml_date(xml: &str, tag: &str, source: DateSource) -> Option<MediaDate> {
     let open_tag = format!("<{}", tag);
     let close_tag = format!("</{}>", tag);
 
@@ -130,5 +167,29 @@ fn extract_xml_date(xml: &str, tag: &str) -> Option<String> {
     let end = xml[content_start..].find(&close_tag)?;
     let value = &xml[content_start..content_start + end];
 
-    parse_date_to_ymd(value.trim())
+    parse_doc_date(value, source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_document_dates_with_their_precision() {
+        let date = parse_doc_date("2024-01-15T10:30:00Z", DateSource::OfficeCreated).unwrap();
+        assert_eq!(date.to_string(), "2024-01-15T10:30:00+00:00");
+
+        let date = parse_doc_date("2024/01/15", DateSource::EpubDate).unwrap();
+        assert_eq!(date.precision, DatePrecision::Day);
+
+        let date = parse_doc_date("2024", DateSource::MobiPublishDate).unwrap();
+        assert_eq!(date.to_string(), "2024");
+    }
+
+    #[test]
+    fn short_values_are_not_dates() {
+        // Slicing the first ten bytes used to panic on shorter values
+        assert!(parse_doc_date("Jan 2024", DateSource::EpubDate).is_none());
+        assert!(parse_doc_date("", DateSource::EpubDate).is_none());
+    }
 }
@@ -1,17 +1,22 @@
-use fs_metadata::file_created;
+use crate::error::MediaInfoError;
+use crate::media_date::MediaDate;
 use std::path::Path;
 
 mod doc_info;
 
-pub fn read_doc_creation_date(path: &Path) -> Result<String, String> {
+/// Reads the creation date stored in a document.
+///
+/// Plain text formats carry no metadata, so they have no date here; the file system dates
+/// are left to the caller to trust or not.
+pub fn read_doc_creation_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
     if !path.exists() {
-        return Err(format!("File does not exist: {:?}", path));
+        return Err(MediaInfoError::NotFound(path.to_path_buf()));
     }
 
     let ext = path
         .extension()
         .and_then(|e| e.to_str())
-        .ok_or_else(|| format!("File has no extension: {:?}", path))?;
+        .ok_or_else(|| MediaInfoError::Unsupported(format!("File has no extension: {:?}", path)))?;
 
     match ext.to_lowercase().as_str() {
         "epub" => doc_info::read_epub_date(path),
@@ -19,8 +24,14 @@ pub fn read_doc_creation_date(path: &Path) -> Result<String, String> {
         "pdf" => doc_info::read_pdf_date(path),
         "docx" | "doc" => doc_info::read_docx_date(path),
         "odt" => doc_info::read_odt_date(path),
-        "txt" | "md" | "rtf" => file_created(path),
-        _ => Err(format!("Unsupported file type: {:?}", path)),
+        "txt" | "md" | "rtf" => Err(MediaInfoError::NoMetadata(format!(
+            "Plain text documents hold no dates: {:?}",
+            path
+        ))),
+        _ => Err(MediaInfoError::Unsupported(format!(
+            "Unsupported file type: {:?}",
+            path
+        ))),
     }
 }
 
@@ -29,15 +40,15 @@ mod tests {
     use super::*;
 
     #[test]
-    fn can_read_docx_creation_date() {
+    fn docx_without_dates_has_no_metadata() {
+        // The test document has no docProps/core.xml, and readers no longer fall back to
+        // the file system date themselves
         let path = Path::new("../test-media/TESTDOCUMENT.docx");
         let result = read_doc_creation_date(path);
-        assert!(result.is_ok(), "Expected Ok, got {:?}", result);
-        let date = result.unwrap();
         assert!(
-            date.contains('-'),
-            "Expected YYYY-MM-DD format, got: {}",
-            date
+            matches!(result, Err(MediaInfoError::NoMetadata(_))),
+            "Expected NoMetadata, got {:?}",
+            result
         );
     }
 
@@ -46,7 +57,7 @@ mod tests {
         let path = Path::new("../test-media/TESTDOCUMENT.pdf");
         let result = read_doc_creation_date(path);
         assert!(result.is_ok(), "Expected Ok, got {:?}", result);
-        let date = result.unwrap();
+        let date = result.unwrap().to_string();
         assert!(
             date.contains('-'),
             "Expected YYYY-MM-DD format, got: {}",
@@ -59,20 +70,7 @@ mod tests {
         let path = Path::new("../test-media/TESTDOCUMENT.epub");
         let result = read_doc_creation_date(path);
         assert!(result.is_ok(), "Expected Ok, got {:?}", result);
-        let date = result.unwrap();
-        assert!(
-            date.contains('-'),
-            "Expected YYYY-MM-DD format, got: {}",
-            date
-        );
-    }
-
-    #[test]
-    fn can_read_txt_creation_date() {
-        let path = Path::new("../test-media/TESTDOCUMENT.txt");
-        let result = read_doc_creation_date(path);
-        assert!(result.is_ok(), "Expected Ok, got {:?}", result);
-        let date = result.unwrap();
+        let date = result.unwrap().to_string();
         assert!(
             date.contains('-'),
             "Expected YYYY-MM-DD format, got: {}",
@@ -81,41 +79,35 @@ mod tests {
     }
 
     #[test]
-    fn can_read_md_creation_date() {
-        let path = Path::new("../test-media/TESTDOCUMENT.md");
-        let result = read_doc_creation_date(path);
-        assert!(result.is_ok(), "Expected Ok, got {:?}", result);
-        let date = result.unwrap();
-        assert!(
-            date.contains('-'),
-            "Expected YYYY-MM-DD format, got: {}",
-            date
-        );
+    fn plain_text_has_no_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let rtf = dir.path().join("TESTDOCUMENT.rtf");
+        std::fs::write(&rtf, r"{\rtf1\ansi Hello}").unwrap();
+
+        for path in [
+            Path::new("../test-media/TESTDOCUMENT.txt"),
+            Path::new("../test-media/TESTDOCUMENT.md"),
+            &rtf,
+        ] {
+            let result = read_doc_creation_date(path);
+            assert!(
+                matches!(result, Err(MediaInfoError::NoMetadata(_))),
+                "Expected NoMetadata for {:?}, got {:?}",
+                path,
+                result
+            );
+        }
     }
 
     #[test]
-    fn can_read_odt_creation_date() {
+    fn odt_without_dates_has_no_metadata() {
+        // meta.xml of the test document only holds statistics
         let path = Path::new("../test-media/TESTDOCUMENT.odt");
         let result = read_doc_creation_date(path);
-        assert!(result.is_ok(), "Expected Ok, got {:?}", result);
-        let date = result.unwrap();
-        assert!(
-            date.contains('-'),
-            "Expected YYYY-MM-DD format, got: {}",
-            date
-        );
-    }
-
-    #[test]
-    fn can_read_rtf_creation_date() {
-        let path = Path::new("../test-media/TESTDOCUMENT.rtf");
-        let result = read_doc_creation_date(path);
-        assert!(result.is_ok(), "Expected Ok, got {:?}", result);
-        let date = result.unwrap();
         assert!(
-            date.contains('-'),
-            "Expected YYYY-MM-DD format, got: {}",
-            date
+            matches!(result, Err(MediaInfoError::NoMetadata(_))),
+            "Expected NoMetadata, got {:?}",
+            result
         );
     }
 
@@ -138,7 +130,7 @@ mod tests {
         let path = Path::new("../test-media");
         let result = read_doc_creation_date(path);
         assert!(result.is_err());
-        let err = result.unwrap_err();
+        let err = result.unwrap_err().to_string();
         assert!(
             err.contains("no extension") || err.contains("does not exist"),
             "Unexpected error: {}",
@@ -151,15 +143,24 @@ mod tests {
         // The function lowercases extensions, so DOCX should work via the .docx path
         let path = Path::new("../test-media/TESTDOCUMENT.docx");
         let result = read_doc_creation_date(path);
-        assert!(result.is_ok());
+        assert!(!matches!(result, Err(MediaInfoError::Unsupported(_))));
     }
 
     #[test]
     fn date_format_is_yyyy_mm_dd() {
         let path = Path::new("../test-media/TESTDOCUMENT.pdf");
-        let result = read_doc_creation_date(path).unwrap();
+        let result = read_doc_creation_date(path)
+            .unwrap()
+            .date()
+            .format("%Y-%m-%d")
+            .to_string();
         let parts: Vec<&str> = result.split('-').collect();
-        assert_eq!(parts.len(), 3, "Expected 3 parts in YYYY-MM-DD, got: {}", result);
+        assert_eq!(
+            parts.len(),
+            3,
+            "Expected 3 parts in YYYY-MM-DD, got: {}",
+            result
+        );
         assert_eq!(parts[0].len(), 4, "Year should be 4 digits");
         assert_eq!(parts[1].len(), 2, "Month should be 2 digits");
         assert_eq!(parts[2].len(), 2, "Day should be 2 digits");
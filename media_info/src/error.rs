@@ -0,0 +1,69 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Why metadata could not be read from a file.
+///
+/// `NoMetadata` means the file was read fine but does not carry the requested value, so a
+/// caller may fall back to another source. `Corrupt` and `Io` mean the file itself could
+/// not be read.
+#[derive(Debug)]
+pub enum MediaInfoError {
+    /// The file does not exist.
+    NotFound(PathBuf),
+    /// The file type is not handled by this reader.
+    Unsupported(String),
+    /// The file was read, but the requested metadata is not present.
+    NoMetadata(String),
+    /// The file could not be parsed.
+    Corrupt(String),
+    /// The file could not be opened or read.
+    Io(io::Error),
+}
+
+impl fmt::Display for MediaInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaInfoError::NotFound(path) => write!(f, "File does not exist: {:?}", path),
+            MediaInfoError::Unsupported(message) => write!(f, "Unsupported: {}", message),
+            MediaInfoError::NoMetadata(message) => write!(f, "No metadata: {}", message),
+            MediaInfoError::Corrupt(message) => write!(f, "Corrupt file: {}", message),
+            MediaInfoError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MediaInfoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MediaInfoError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MediaInfoError {
+    fn from(e: io::Error) -> Self {
+        MediaInfoError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn io_errors_keep_their_source() {
+        let error = MediaInfoError::from(io::Error::other("disk gone"));
+
+        assert_eq!(error.to_string(), "I/O error: disk gone");
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn displays_the_missing_path() {
+        let error = MediaInfoError::NotFound(PathBuf::from("a.jpg"));
+        assert_eq!(error.to_string(), "File does not exist: \"a.jpg\"");
+    }
+}
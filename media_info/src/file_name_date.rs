@@ -0,0 +1,279 @@
+use crate::error::MediaInfoError;
+use crate::media_date::{DatePrecision, DateSource, MediaDate};
+use chrono::NaiveDate;
+use std::path::Path;
+
+/// Patterns of cameras, phones and messaging apps, tried after any user patterns. Patterns
+/// with a time of day come first so the most precise match wins.
+const BUILTIN_PATTERNS: [&str; 8] = [
+    // IMG_20240101_123456.jpg, PXL_20231231_235959123.mp4
+    "%Y%m%d_%H%M%S",
+    // 20240101-123456.jpg
+    "%Y%m%d-%H%M%S",
+    // Screenshot_2024-02-03-10-11-12.png
+    "%Y-%m-%d-%H-%M-%S",
+    // 2019-07-04 18.30.00.jpg
+    "%Y-%m-%d %H.%M.%S",
+    // Screen Recording 2024-02-03 at 10.11.12.mov
+    "%Y-%m-%d at %H.%M.%S",
+    // signal-2024-02-03-101112.jpg
+    "%Y-%m-%d-%H%M%S",
+    // 2024-02-03.jpg
+    "%Y-%m-%d",
+    // VID-20240101-WA0003.mp4
+    "%Y%m%d",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Literal(char),
+}
+
+impl Token {
+    fn width(self) -> usize {
+        match self {
+            Token::Year => 4,
+            Token::Literal(_) => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// A date pattern to look for in file names, written with the `strftime` fields `%Y`,
+/// `%m`, `%d`, `%H`, `%M` and `%S`. Every other character must match literally, and `%%`
+/// matches a percent sign.
+///
+/// `%Y` is required. Without `%d` the date is read with month precision, and without `%m`
+/// with year precision.
+///
+/// The pattern can start anywhere in the file name, but not in the middle of a number, and
+/// it cannot end in the middle of a number unless it ends with seconds, which are often
+/// followed by milliseconds. `%Y` only matches years from 1900 to 2099, so counters such
+/// as `DSC_12340101` are not taken for dates.
+///
+/// # Example
+///
+/// ```rust
+/// use media_info::{FileNamePattern, read_file_name_date};
+/// use std::path::Path;
+///
+/// let pattern = FileNamePattern::new("scan %d.%m.%Y").unwrap();
+/// let date = read_file_name_date(Path::new("scan 04.07.1998.tif"), &[pattern]).unwrap();
+/// assert_eq!(date.to_string(), "1998-07-04");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileNamePattern {
+    tokens: Vec<Token>,
+}
+
+impl FileNamePattern {
+    pub fn new(format: &str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut chars = format.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                tokens.push(Token::Literal(c));
+                continue;
+            }
+            tokens.push(match chars.next() {
+                Some('Y') => Token::Year,
+                Some('m') => Token::Month,
+                Some('d') => Token::Day,
+                Some('H') => Token::Hour,
+                Some('M') => Token::Minute,
+                Some('S') => Token::Second,
+                Some('%') => Token::Literal('%'),
+                Some(other) => {
+                    return Err(format!(
+                        "Unsupported field %{} in file name pattern: {}",
+                        other, format
+                    ));
+                }
+                None => return Err(format!("Dangling % in file name pattern: {}", format)),
+            });
+        }
+
+        let has = |token: Token| tokens.contains(&token);
+        if !has(Token::Year) {
+            return Err(format!("File name pattern needs %Y: {}", format));
+        }
+        if has(Token::Day) && !has(Token::Month) {
+            return Err(format!("File name pattern with %d needs %m: {}", format));
+        }
+        if (has(Token::Hour) || has(Token::Minute) || has(Token::Second)) && !has(Token::Day) {
+            return Err(format!(
+                "File name pattern with a time needs %m and %d: {}",
+                format
+            ));
+        }
+
+        Ok(FileNamePattern { tokens })
+    }
+
+    fn precision(&self) -> DatePrecision {
+        if !self.tokens.contains(&Token::Month) {
+            DatePrecision::Year
+        } else if !self.tokens.contains(&Token::Day) {
+            DatePrecision::Month
+        } else if !self.tokens.contains(&Token::Hour) {
+            DatePrecision::Day
+        } else {
+            DatePrecision::Second
+        }
+    }
+
+    /// Matches the pattern at the start of `text`. Fields missing from the pattern are
+    /// filled with the first month, day or second.
+    pub(crate) fn match_at(&self, text: &[char], source: DateSource) -> Option<MediaDate> {
+        let mut fields = [0, 1, 1, 0, 0, 0];
+        let mut position = 0;
+
+        for token in &self.tokens {
+            let width = token.width();
+            let slice = text.get(position..position + width)?;
+            position += width;
+
+            let index = match token {
+                Token::Literal(c) => {
+                    if slice[0] != *c {
+                        return None;
+                    }
+                    continue;
+                }
+                Token::Year => 0,
+                Token::Month => 1,
+                Token::Day => 2,
+                Token::Hour => 3,
+                Token::Minute => 4,
+                Token::Second => 5,
+            };
+            fields[index] = slice.iter().try_fold(0, |number, c| {
+                c.to_digit(10).map(|digit| number * 10 + digit)
+            })?;
+        }
+
+        let ends_in_seconds = self.tokens.last() == Some(&Token::Second);
+        if !ends_in_seconds && text.get(position).is_some_and(char::is_ascii_digit) {
+            return None;
+        }
+
+        let [year, month, day, hour, minute, second] = fields;
+        if !(1900..=2099).contains(&year) {
+            return None;
+        }
+        let date_time =
+            NaiveDate::from_ymd_opt(year as i32, month, day)?.and_hms_opt(hour, minute, second)?;
+
+        Some(MediaDate::new(date_time, self.precision(), source))
+    }
+
+    /// Finds the pattern anywhere in `name` where it does not start inside a number.
+    pub(crate) fn find(&self, name: &[char], source: DateSource) -> Option<MediaDate> {
+        (0..name.len())
+            .filter(|&start| start == 0 || !name[start - 1].is_ascii_digit())
+            .find_map(|start| self.match_at(&name[start..], source))
+    }
+}
+
+/// Reads a date from the file name, trying `patterns` before the built-in patterns of
+/// common cameras, phones and messaging apps. File name dates carry no UTC offset.
+pub fn read_file_name_date(
+    path: &Path,
+    patterns: &[FileNamePattern],
+) -> Result<MediaDate, MediaInfoError> {
+    let name: Vec<char> = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .chars()
+        .collect();
+
+    let builtin = BUILTIN_PATTERNS
+        .iter()
+        .map(|format| FileNamePattern::new(format).expect("built-in patterns are valid"));
+
+    patterns
+        .iter()
+        .cloned()
+        .chain(builtin)
+        .find_map(|pattern| pattern.find(&name, DateSource::FileName))
+        .ok_or_else(|| MediaInfoError::NoMetadata(format!("No date in the name of {:?}", path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date_of(name: &str) -> Option<String> {
+        read_file_name_date(Path::new(name), &[])
+            .ok()
+            .map(|date| date.to_string())
+    }
+
+    #[test]
+    fn reads_camera_phone_and_app_names() {
+        let cases = [
+            ("IMG_20240101_123456.jpg", "2024-01-01T12:34:56"),
+            ("PXL_20231231_235959123.mp4", "2023-12-31T23:59:59"),
+            ("VID-20240101-WA0003.mp4", "2024-01-01"),
+            ("Screenshot_2024-02-03-10-11-12.png", "2024-02-03T10:11:12"),
+            ("2019-07-04 18.30.00.jpg", "2019-07-04T18:30:00"),
+            (
+                "Screen Recording 2024-02-03 at 10.11.12.mov",
+                "2024-02-03T10:11:12",
+            ),
+            ("holiday 2024-02-03.jpg", "2024-02-03"),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(date_of(name).as_deref(), Some(expected), "{}", name);
+        }
+    }
+
+    #[test]
+    fn ignores_counters_and_invalid_dates() {
+        assert_eq!(date_of("DSC_12340101.jpg"), None);
+        assert_eq!(date_of("IMG_0001.jpg"), None);
+        assert_eq!(date_of("20241399_000000.jpg"), None);
+        // Does not start a match in the middle of a longer number
+        assert_eq!(date_of("9920240101.jpg"), None);
+        // Nor end one there, unless milliseconds follow the seconds
+        assert_eq!(date_of("202401011.jpg"), None);
+    }
+
+    #[test]
+    fn user_patterns_come_first() {
+        let pattern = FileNamePattern::new("%d%m%Y").unwrap();
+        let date = read_file_name_date(Path::new("04072019_scan.tif"), &[pattern]).unwrap();
+
+        assert_eq!(date.to_string(), "2019-07-04");
+        assert_eq!(date.source, DateSource::FileName);
+        assert_eq!(date.offset, None);
+    }
+
+    #[test]
+    fn partial_patterns_read_partial_dates() {
+        let pattern = FileNamePattern::new("Roll %Y-%m").unwrap();
+        let date = read_file_name_date(Path::new("Roll 1987-06 #3.tif"), &[pattern]).unwrap();
+
+        assert_eq!(date.precision, DatePrecision::Month);
+        assert_eq!(date.to_string(), "1987-06");
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(FileNamePattern::new("%m-%d").is_err());
+        assert!(FileNamePattern::new("%Y-%d").is_err());
+        assert!(FileNamePattern::new("%Y-%m %H:%M").is_err());
+        assert!(FileNamePattern::new("%Y-%m-%d %I").is_err());
+        assert!(FileNamePattern::new("%Y-%m-%d%").is_err());
+        assert!(FileNamePattern::new("100%% %Y-%m-%d").is_ok());
+    }
+}
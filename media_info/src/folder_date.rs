@@ -0,0 +1,139 @@
+use crate::error::MediaInfoError;
+use crate::file_name_date::FileNamePattern;
+use crate::media_date::{DatePrecision, DateSource, MediaDate};
+use chrono::Datelike;
+use std::path::Path;
+
+/// Folder name patterns, from the most to the least precise.
+const FOLDER_PATTERNS: [&str; 8] = [
+    "%Y-%m-%d", "%Y_%m_%d", "%Y.%m.%d", "%Y%m%d", "%Y-%m", "%Y_%m", "%Y.%m", "%Y",
+];
+
+/// A date read from the name of a folder above a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderDate {
+    pub date: MediaDate,
+    /// The name of the folder the date was read from.
+    pub folder: String,
+}
+
+/// Reads a month from the start of a folder name such as `12 Christmas`, for folders that
+/// sit inside a year folder.
+fn leading_month(name: &[char]) -> Option<u32> {
+    let digits = name.iter().take_while(|c| c.is_ascii_digit()).count();
+    if !(1..=2).contains(&digits) {
+        return None;
+    }
+
+    let month: u32 = name[..digits].iter().collect::<String>().parse().ok()?;
+    (1..=12).contains(&month).then_some(month)
+}
+
+fn date_in_name(name: &[char], patterns: &[FileNamePattern]) -> Option<MediaDate> {
+    patterns
+        .iter()
+        .find_map(|pattern| pattern.find(name, DateSource::ParentFolder))
+}
+
+/// Reads a date from the folders above a file, such as `1998 - Summer Vacation/` or
+/// `2003/12 Christmas/`, starting with the nearest folder. Only the path as given is
+/// searched, so a relative path never reaches above the folder it is relative to.
+///
+/// The date has year, month or day precision depending on how much the folder names hold.
+///
+/// # Example
+///
+/// ```rust
+/// use media_info::{DatePrecision, read_folder_date};
+/// use std::path::Path;
+///
+/// let found = read_folder_date(Path::new("scans/2003/12 Christmas/scan_01.tif")).unwrap();
+/// assert_eq!(found.date.to_string(), "2003-12");
+/// assert_eq!(found.date.precision, DatePrecision::Month);
+/// assert_eq!(found.folder, "12 Christmas");
+/// ```
+pub fn read_folder_date(path: &Path) -> Result<FolderDate, MediaInfoError> {
+    let patterns: Vec<FileNamePattern> = FOLDER_PATTERNS
+        .iter()
+        .map(|format| FileNamePattern::new(format).expect("folder patterns are valid"))
+        .collect();
+    let folders: Vec<(String, Vec<char>)> = path
+        .ancestors()
+        .skip(1)
+        .filter_map(|folder| folder.file_name()?.to_str())
+        .map(|name| (name.to_string(), name.chars().collect()))
+        .collect();
+
+    for (index, (name, chars)) in folders.iter().enumerate() {
+        if let Some(date) = date_in_name(chars, &patterns) {
+            return Ok(FolderDate {
+                date,
+                folder: name.clone(),
+            });
+        }
+
+        if let Some(month) = leading_month(chars)
+            && let Some((_, parent)) = folders.get(index + 1)
+            && let Some(year) = date_in_name(parent, &patterns)
+            && year.precision == DatePrecision::Year
+            && let Some(date_time) = year.date_time.with_month(month)
+        {
+            return Ok(FolderDate {
+                date: MediaDate::new(date_time, DatePrecision::Month, DateSource::ParentFolder),
+                folder: name.clone(),
+            });
+        }
+    }
+
+    Err(MediaInfoError::NoMetadata(format!(
+        "No date in the folders of {:?}",
+        path
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date_of(path: &str) -> Option<String> {
+        read_folder_date(Path::new(path))
+            .ok()
+            .map(|found| found.date.to_string())
+    }
+
+    #[test]
+    fn reads_years_months_and_days_from_folders() {
+        assert_eq!(
+            date_of("scans/1998 - Summer Vacation/img001.tif").as_deref(),
+            Some("1998")
+        );
+        assert_eq!(
+            date_of("scans/2003/12 Christmas/img001.tif").as_deref(),
+            Some("2003-12")
+        );
+        assert_eq!(
+            date_of("scans/2003_12_24 Eve/img001.tif").as_deref(),
+            Some("2003-12-24")
+        );
+        assert_eq!(
+            date_of("scans/Family 2005-07/img001.tif").as_deref(),
+            Some("2005-07")
+        );
+    }
+
+    #[test]
+    fn nearest_folder_wins() {
+        let found = read_folder_date(Path::new("2020 backup/1998 - Summer/img.tif")).unwrap();
+
+        assert_eq!(found.date.to_string(), "1998");
+        assert_eq!(found.folder, "1998 - Summer");
+        assert_eq!(found.date.source, DateSource::ParentFolder);
+    }
+
+    #[test]
+    fn file_names_and_undated_folders_are_ignored() {
+        assert_eq!(date_of("scans/IMG_20240101_123456.jpg"), None);
+        assert_eq!(date_of("scans/Roll 12/img001.tif"), None);
+        assert_eq!(date_of("img001.tif"), None);
+    }
+}
@@ -1,17 +1,39 @@
 #[cfg(feature = "audio")]
 pub mod audio_info;
+#[cfg(any(feature = "audio", feature = "photo", feature = "video"))]
+mod bmff;
+pub mod date_validation;
+pub mod detect;
 #[cfg(feature = "doc")]
 pub mod doc_info;
+pub mod error;
+pub mod file_name_date;
+pub mod folder_date;
+pub mod media_date;
 #[cfg(feature = "photo")]
 pub mod photo_info;
+pub mod time_zone;
 #[cfg(feature = "video")]
 pub mod video_info;
+pub mod xmp_date;
 
 #[cfg(feature = "audio")]
 pub use audio_info::*;
+pub use date_validation::{DateProblem, DateValidation};
+pub use detect::*;
 #[cfg(feature = "doc")]
 pub use doc_info::*;
+pub use error::MediaInfoError;
+pub use file_name_date::{FileNamePattern, read_file_name_date};
+pub use folder_date::{FolderDate, read_folder_date};
+pub use fs_metadata::CreatedStrategy;
+pub use media_date::{
+    DatePrecision, DateSource, MediaDate, read_file_creation_date, read_file_creation_date_with,
+    read_file_modified_date,
+};
 #[cfg(feature = "photo")]
 pub use photo_info::*;
+pub use time_zone::{TimeZoneSetting, resolve_time_zone, time_zone_at};
 #[cfg(feature = "video")]
 pub use video_info::*;
+pub use xmp_date::read_xmp_date;
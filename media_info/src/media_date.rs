@@ -0,0 +1,326 @@
+use crate::error::MediaInfoError;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+use fs_metadata::{CreatedStrategy, FileTime, created_time, file_modified};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// How much of a [`MediaDate`] was actually stored in the file. Missing parts are filled
+/// with the first month, day or second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DatePrecision {
+    Year,
+    Month,
+    Day,
+    Second,
+}
+
+/// Where a [`MediaDate`] was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateSource {
+    ExifDateTime,
+    ExifDateTimeOriginal,
+    ExifDateTimeDigitized,
+    ExifGpsTime,
+    QuickTimeCreationDate,
+    QuickTimeDate,
+    FfmpegCreationTime,
+    StreamCreationTime,
+    MatroskaDateRecorded,
+    AviDateTimeOriginal,
+    AvchdRecordingDate,
+    Id3Recorded,
+    Mp4Day,
+    Mp4CreationTime,
+    VorbisDate,
+    BextOriginationDate,
+    RiffCreationDate,
+    AiffCommentTime,
+    EpubDate,
+    MobiPublishDate,
+    PdfCreationDate,
+    PdfModDate,
+    OfficeCreated,
+    OfficeModified,
+    FileBirthTime,
+    FileModifiedTime,
+    FileChangedTime,
+    FileName,
+    ParentFolder,
+    XmpCreateDate,
+    PhotoshopDateCreated,
+}
+
+impl DateSource {
+    /// A short, stable name for reports and logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DateSource::ExifDateTime => "exif_date_time",
+            DateSource::ExifDateTimeOriginal => "exif_date_time_original",
+            DateSource::ExifDateTimeDigitized => "exif_date_time_digitized",
+            DateSource::ExifGpsTime => "exif_gps_time",
+            DateSource::QuickTimeCreationDate => "quicktime_creation_date",
+            DateSource::QuickTimeDate => "quicktime_date",
+            DateSource::FfmpegCreationTime => "ffmpeg_creation_time",
+            DateSource::StreamCreationTime => "stream_creation_time",
+            DateSource::MatroskaDateRecorded => "matroska_date_recorded",
+            DateSource::AviDateTimeOriginal => "avi_date_time_original",
+            DateSource::AvchdRecordingDate => "avchd_recording_date",
+            DateSource::Id3Recorded => "id3_recorded",
+            DateSource::Mp4Day => "mp4_day",
+            DateSource::Mp4CreationTime => "mp4_creation_time",
+            DateSource::VorbisDate => "vorbis_date",
+            DateSource::BextOriginationDate => "bext_origination_date",
+            DateSource::RiffCreationDate => "riff_creation_date",
+            DateSource::AiffCommentTime => "aiff_comment_time",
+            DateSource::EpubDate => "epub_date",
+            DateSource::MobiPublishDate => "mobi_publish_date",
+            DateSource::PdfCreationDate => "pdf_creation_date",
+            DateSource::PdfModDate => "pdf_mod_date",
+            DateSource::OfficeCreated => "office_created",
+            DateSource::OfficeModified => "office_modified",
+            DateSource::FileBirthTime => "file_birth_time",
+            DateSource::FileModifiedTime => "file_modified_time",
+            DateSource::FileChangedTime => "file_changed_time",
+            DateSource::FileName => "file_name",
+            DateSource::ParentFolder => "parent_folder",
+            DateSource::XmpCreateDate => "xmp_create_date",
+            DateSource::PhotoshopDateCreated => "photoshop_date_created",
+        }
+    }
+}
+
+impl fmt::Display for DateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A date read from a media file, along with how precise it is and where it came from.
+///
+/// `date_time` is the local time as stored in the file. `offset` is only set when the file
+/// says which UTC offset that local time is in.
+///
+/// Some files, such as MP4 `creation_time`, store only the UTC instant and not the time zone
+/// it was recorded in. Their dates are `utc_only`, with `date_time` in UTC, until
+/// [`MediaDate::in_time_zone`] moves them into a local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediaDate {
+    pub date_time: NaiveDateTime,
+    pub offset: Option<FixedOffset>,
+    pub precision: DatePrecision,
+    pub source: DateSource,
+    pub utc_only: bool,
+}
+
+impl MediaDate {
+    pub fn new(date_time: NaiveDateTime, precision: DatePrecision, source: DateSource) -> Self {
+        MediaDate {
+            date_time,
+            offset: None,
+            precision,
+            source,
+            utc_only: false,
+        }
+    }
+
+    /// A date without a time of day.
+    pub fn from_date(date: NaiveDate, source: DateSource) -> Self {
+        MediaDate::new(
+            date.and_hms_opt(0, 0, 0).unwrap_or_default(),
+            DatePrecision::Day,
+            source,
+        )
+    }
+
+    /// A full timestamp that carries its own UTC offset.
+    pub fn from_fixed(date_time: DateTime<FixedOffset>, source: DateSource) -> Self {
+        MediaDate::new(date_time.naive_local(), DatePrecision::Second, source)
+            .with_offset(*date_time.offset())
+    }
+
+    /// A UTC instant stored without the time zone it was recorded in.
+    pub fn from_utc(date_time: NaiveDateTime, source: DateSource) -> Self {
+        MediaDate {
+            utc_only: true,
+            ..MediaDate::from_fixed(date_time.and_utc().fixed_offset(), source)
+        }
+    }
+
+    /// Moves a UTC-only date into the local time of `time_zone`. Dates recorded in local time
+    /// are returned as they are.
+    pub fn in_time_zone<Tz: TimeZone>(self, time_zone: &Tz) -> Self {
+        if !self.utc_only {
+            return self;
+        }
+
+        MediaDate {
+            precision: self.precision,
+            ..MediaDate::from_fixed(
+                time_zone.from_utc_datetime(&self.date_time).fixed_offset(),
+                self.source,
+            )
+        }
+    }
+
+    pub fn with_offset(mut self, offset: FixedOffset) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// The calendar date, in the time zone the file recorded it in.
+    pub fn date(&self) -> NaiveDate {
+        self.date_time.date()
+    }
+
+    /// The date as an instant, when the file recorded its UTC offset.
+    pub fn to_fixed(&self) -> Option<DateTime<FixedOffset>> {
+        self.offset?.from_local_datetime(&self.date_time).single()
+    }
+}
+
+impl fmt::Display for MediaDate {
+    /// Formats the date as ISO 8601, leaving out whatever the file did not store.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = match self.precision {
+            DatePrecision::Year => "%Y",
+            DatePrecision::Month => "%Y-%m",
+            DatePrecision::Day => "%Y-%m-%d",
+            DatePrecision::Second => "%Y-%m-%dT%H:%M:%S",
+        };
+        write!(f, "{}", self.date_time.format(format))?;
+
+        match self.offset {
+            Some(offset) if self.precision == DatePrecision::Second => write!(f, "{}", offset),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn file_system_date(
+    path: &Path,
+    read: fn(&Path) -> Result<String, String>,
+    source: DateSource,
+) -> Result<MediaDate, MediaInfoError> {
+    if !path.exists() {
+        return Err(MediaInfoError::NotFound(path.to_path_buf()));
+    }
+
+    let day = read(path).map_err(|e| MediaInfoError::Io(io::Error::other(e)))?;
+    let date = NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+        .map_err(|e| MediaInfoError::Corrupt(format!("Invalid file date {}: {}", day, e)))?;
+
+    Ok(MediaDate::from_date(date, source))
+}
+
+/// Reads the date the file was created on this file system. File systems that do not
+/// record birth times, such as NFS mounts and some FAT-formatted SD cards, fall back to the
+/// earlier of the modification and status change dates, and the source says which it was.
+pub fn read_file_creation_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    read_file_creation_date_with(path, CreatedStrategy::BirthOrEarliest)
+}
+
+/// Reads the date the file was created on this file system, using `strategy` where the file
+/// system does not record birth times.
+pub fn read_file_creation_date_with(
+    path: &Path,
+    strategy: CreatedStrategy,
+) -> Result<MediaDate, MediaInfoError> {
+    if !path.exists() {
+        return Err(MediaInfoError::NotFound(path.to_path_buf()));
+    }
+
+    let (time, file_time) = created_time(path, strategy)
+        .map_err(|e| MediaInfoError::Io(io::Error::other(e)))?
+        .ok_or_else(|| {
+            MediaInfoError::NoMetadata(format!("No file creation date for {:?}", path))
+        })?;
+    let source = match file_time {
+        FileTime::Birth => DateSource::FileBirthTime,
+        FileTime::Modified => DateSource::FileModifiedTime,
+        FileTime::StatusChanged => DateSource::FileChangedTime,
+    };
+
+    Ok(MediaDate::from_date(time.date_naive(), source))
+}
+
+/// Reads the date the file was last modified.
+pub fn read_file_modified_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    file_system_date(path, file_modified, DateSource::FileModifiedTime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noon() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 10, 22)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn displays_only_the_stored_precision() {
+        let date = MediaDate::new(noon(), DatePrecision::Month, DateSource::Id3Recorded);
+        assert_eq!(date.to_string(), "2024-10");
+
+        let date = MediaDate::new(noon(), DatePrecision::Second, DateSource::ExifDateTime);
+        assert_eq!(date.to_string(), "2024-10-22T12:00:00");
+
+        let date = date.with_offset(FixedOffset::east_opt(2 * 3600).unwrap());
+        assert_eq!(date.to_string(), "2024-10-22T12:00:00+02:00");
+    }
+
+    #[test]
+    fn fixed_dates_keep_their_local_time_and_offset() {
+        let parsed = DateTime::parse_from_rfc3339("2024-10-20T23:30:00-05:00").unwrap();
+        let date = MediaDate::from_fixed(parsed, DateSource::QuickTimeCreationDate);
+
+        assert_eq!(date.date(), NaiveDate::from_ymd_opt(2024, 10, 20).unwrap());
+        assert_eq!(date.to_fixed(), Some(parsed));
+        assert_eq!(date.source.to_string(), "quicktime_creation_date");
+    }
+
+    #[test]
+    fn utc_only_dates_move_into_a_time_zone() {
+        let date = MediaDate::from_utc(noon(), DateSource::FfmpegCreationTime);
+        assert!(date.utc_only);
+        assert_eq!(date.to_string(), "2024-10-22T12:00:00+00:00");
+
+        let denver = FixedOffset::west_opt(6 * 3600).unwrap();
+        let local = date.in_time_zone(&denver);
+        assert!(!local.utc_only);
+        assert_eq!(local.to_string(), "2024-10-22T06:00:00-06:00");
+        assert_eq!(local.to_fixed(), date.to_fixed());
+
+        // Local dates stay as the file recorded them
+        let exif = MediaDate::new(noon(), DatePrecision::Second, DateSource::ExifDateTime);
+        assert_eq!(exif.in_time_zone(&denver), exif);
+    }
+
+    #[test]
+    fn file_creation_date_is_a_day() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let date = read_file_creation_date(file.path()).unwrap();
+
+        assert_eq!(date.precision, DatePrecision::Day);
+        if fs_metadata::birth_time(file.path()).unwrap().is_some() {
+            assert_eq!(date.source, DateSource::FileBirthTime);
+        } else {
+            assert_ne!(date.source, DateSource::FileBirthTime);
+            assert!(matches!(
+                read_file_creation_date_with(file.path(), CreatedStrategy::Birth),
+                Err(MediaInfoError::NoMetadata(_))
+            ));
+        }
+        assert_eq!(
+            read_file_modified_date(file.path()).unwrap().source,
+            DateSource::FileModifiedTime
+        );
+        assert!(matches!(
+            read_file_creation_date(Path::new("nonexistent.jpg")),
+            Err(MediaInfoError::NotFound(_))
+        ));
+    }
+}
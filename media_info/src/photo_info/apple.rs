@@ -0,0 +1,70 @@
+use super::tiff::{ASCII, Tiff};
+
+/// What an Apple maker note starts with. The note's version and byte order follow, then
+/// its IFD at [`IFD`], and its offsets count from the start of the note.
+const SIGNATURE: &[u8] = b"Apple iOS\0";
+const BYTE_ORDER: usize = 12;
+const IFD: usize = 14;
+
+/// The tag of the identifier an iPhone gives both the photo and the video of a Live Photo.
+const CONTENT_IDENTIFIER: u16 = 0x11;
+
+/// Reads the content identifier of a Live Photo from an Apple maker note.
+pub(super) fn content_identifier(maker_note: &[u8]) -> Option<String> {
+    if !maker_note.starts_with(SIGNATURE) {
+        return None;
+    }
+
+    let tiff = Tiff::with_header_at(maker_note, BYTE_ORDER)?;
+    let entry = tiff
+        .entries(IFD)?
+        .into_iter()
+        .find(|entry| entry.tag == CONTENT_IDENTIFIER && entry.kind == ASCII)?;
+    let start = match entry.count {
+        0..=4 => entry.value,
+        _ => tiff.u32_at(entry.value)? as usize,
+    };
+
+    let identifier = std::str::from_utf8(maker_note.get(start..start + entry.count)?)
+        .ok()?
+        .trim_end_matches('\0');
+    (!identifier.is_empty()).then(|| identifier.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An Apple maker note with a run time and a content identifier, big endian like the
+    /// notes iPhones write.
+    fn maker_note(identifier: &str) -> Vec<u8> {
+        let mut note = SIGNATURE.to_vec();
+        note.extend_from_slice(&[0, 1]);
+        note.extend_from_slice(b"MM");
+        note.extend_from_slice(&2u16.to_be_bytes());
+        // A SLONG with the maker note version
+        note.extend_from_slice(&[0, 1, 0, 9, 0, 0, 0, 1, 0, 0, 0, 14]);
+
+        let value = (IFD + 2 + 2 * 12 + 4) as u32;
+        let text = format!("{}\0", identifier);
+        note.extend_from_slice(&CONTENT_IDENTIFIER.to_be_bytes());
+        note.extend_from_slice(&ASCII.to_be_bytes());
+        note.extend_from_slice(&(text.len() as u32).to_be_bytes());
+        note.extend_from_slice(&value.to_be_bytes());
+        note.extend_from_slice(&[0; 4]);
+        note.extend_from_slice(text.as_bytes());
+        note
+    }
+
+    #[test]
+    fn reads_the_content_identifier_of_a_live_photo() {
+        let identifier = "6C8CE2A2-5D5C-4C1F-9F4B-0E51B2DC33F8";
+
+        assert_eq!(
+            content_identifier(&maker_note(identifier)).as_deref(),
+            Some(identifier)
+        );
+        assert_eq!(content_identifier(&maker_note("")), None);
+        assert_eq!(content_identifier(b"Nikon\0\x02\x10\0\0MM\0*"), None);
+    }
+}
@@ -1,68 +1,236 @@
-use exif::{In, Reader, Tag as ExifTag};
-use fs_metadata::file_created;
+use crate::error::MediaInfoError;
+use crate::media_date::{DatePrecision, DateSource, MediaDate};
+use chrono::{FixedOffset, NaiveDate, TimeDelta};
+use exif::{Exif, Field, In, Reader, Tag as ExifTag, Value};
 use std::fs::File;
 use std::path::Path;
 
+mod apple;
+mod raw;
+mod rewrite_dates;
 pub mod struct_photo_info;
+mod tiff;
 
-pub fn read_photo_dimensions(path: &Path) -> Result<(u32, u32), String> {
-    if !path.exists() {
-        return Err(format!("File does not exist: {:?}", path));
-    }
+pub use rewrite_dates::{DateRewrite, photo_dates_rewritten, rewrite_photo_dates};
 
-    let file = File::open(path).expect("could not open photo");
-    let mut bufreader = std::io::BufReader::new(&file);
-    let exifreader = Reader::new();
-
-    match exifreader.read_from_container(&mut bufreader) {
-        Ok(reader) => {
-            let width: u32 = reader
-                .get_field(ExifTag::PixelXDimension, In::PRIMARY)
-                .and_then(|f| f.value.get_uint(0))
-                .unwrap_or(0);
-            let height: u32 = reader
-                .get_field(ExifTag::PixelYDimension, In::PRIMARY)
-                .and_then(|f| f.value.get_uint(0))
-                .unwrap_or(0);
-
-            if width > 0 && height > 0 {
-                Ok((width, height))
-            } else {
-                Err("Could not read photo dimensions from EXIF".to_string())
-            }
+/// Tells a photo without EXIF apart from a broken one.
+fn exif_error(path: &Path, e: exif::Error) -> MediaInfoError {
+    match e {
+        exif::Error::NotFound(_) => {
+            MediaInfoError::NoMetadata(format!("No EXIF data in {:?}", path))
         }
-        Err(_) => Err(format!("Could not read EXIF data from {:?}", path)),
+        exif::Error::Io(e) => MediaInfoError::Io(e),
+        e => MediaInfoError::Corrupt(format!("Could not read EXIF data from {:?}: {}", path, e)),
     }
 }
 
-pub fn read_photo_creation_date(path: &Path) -> Result<String, String> {
+/// Reads the EXIF block of a photo, along with the size of the image on the sensor for
+/// TIFF and camera RAW photos, whose EXIF often leaves it out.
+fn read_photo(path: &Path) -> Result<(Exif, Option<(u32, u32)>), MediaInfoError> {
     if !path.exists() {
-        return Err(format!("File does not exist: {:?}", path));
+        return Err(MediaInfoError::NotFound(path.to_path_buf()));
+    }
+    if let Some(raw) = raw::read_raw(path)? {
+        return Ok((raw.exif, raw.sensor_size));
     }
 
-    let file = File::open(path).expect("could not open photo");
+    let file = File::open(path)?;
     let mut bufreader = std::io::BufReader::new(&file);
-    let exifreader = Reader::new();
-
-    match exifreader.read_from_container(&mut bufreader) {
-        Ok(reader) => {
-            let date_data: String = match reader.get_field(ExifTag::DateTime, In::PRIMARY) {
-                Some(data) => data.value.display_as(data.tag).to_string(),
-                None => todo!(),
-            };
-            Ok(date_data)
-        }
-        Err(_) => {
-            println!("Error reading exif: {:?}", path);
-            println!("Falling back to file creation date");
-            // TODO: edit exif date with file creation date?
 
-            let formatted_date = file_created(path).unwrap();
-            Err(formatted_date)
-        }
+    let exif = Reader::new()
+        .read_from_container(&mut bufreader)
+        .map_err(|e| exif_error(path, e))?;
+    Ok((exif, None))
+}
+
+/// Reads the EXIF block of a photo, telling a photo without EXIF apart from a broken one.
+pub(crate) fn read_exif(path: &Path) -> Result<Exif, MediaInfoError> {
+    read_photo(path).map(|(exif, _)| exif)
+}
+
+/// Reads the width and height of a photo, from the sensor tags of TIFF and camera RAW
+/// photos, otherwise from the EXIF pixel dimensions.
+pub fn read_photo_dimensions(path: &Path) -> Result<(u32, u32), MediaInfoError> {
+    let (reader, sensor_size) = read_photo(path)?;
+    if let Some(size) = sensor_size {
+        return Ok(size);
+    }
+
+    let width: u32 = reader
+        .get_field(ExifTag::PixelXDimension, In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))
+        .unwrap_or(0);
+    let height: u32 = reader
+        .get_field(ExifTag::PixelYDimension, In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))
+        .unwrap_or(0);
+
+    if width > 0 && height > 0 {
+        Ok((width, height))
+    } else {
+        Err(MediaInfoError::NoMetadata(
+            "Could not read photo dimensions from EXIF".to_string(),
+        ))
+    }
+}
+
+/// The EXIF date tags in order of preference, each with the tags holding its UTC offset
+/// and fractional seconds. `DateTime` comes last because editors rewrite it on every save.
+const DATE_TAGS: [(ExifTag, ExifTag, ExifTag, DateSource); 3] = [
+    (
+        ExifTag::DateTimeOriginal,
+        ExifTag::OffsetTimeOriginal,
+        ExifTag::SubSecTimeOriginal,
+        DateSource::ExifDateTimeOriginal,
+    ),
+    (
+        ExifTag::DateTimeDigitized,
+        ExifTag::OffsetTimeDigitized,
+        ExifTag::SubSecTimeDigitized,
+        DateSource::ExifDateTimeDigitized,
+    ),
+    (
+        ExifTag::DateTime,
+        ExifTag::OffsetTime,
+        ExifTag::SubSecTime,
+        DateSource::ExifDateTime,
+    ),
+];
+
+fn ascii_value(field: &Field) -> Option<&[u8]> {
+    match field.value {
+        Value::Ascii(ref values) => values.first().map(Vec::as_slice),
+        _ => None,
+    }
+}
+
+/// Reads one EXIF `YYYY:MM:DD HH:MM:SS` date along with its offset and sub-second tags.
+/// Blank or out of range dates, which some cameras write, are treated as missing.
+fn exif_date(
+    exif: &Exif,
+    (date_tag, offset_tag, subsec_tag, source): (ExifTag, ExifTag, ExifTag, DateSource),
+) -> Option<MediaDate> {
+    let field = exif.get_field(date_tag, In::PRIMARY)?;
+    let mut date_time = exif::DateTime::from_ascii(ascii_value(field)?).ok()?;
+
+    if let Some(subsec) = exif
+        .get_field(subsec_tag, In::PRIMARY)
+        .and_then(ascii_value)
+    {
+        let _ = date_time.parse_subsec(subsec);
+    }
+    if let Some(offset) = exif
+        .get_field(offset_tag, In::PRIMARY)
+        .and_then(ascii_value)
+    {
+        let _ = date_time.parse_offset(offset);
+    }
+
+    let naive = NaiveDate::from_ymd_opt(
+        date_time.year as i32,
+        date_time.month as u32,
+        date_time.day as u32,
+    )?
+    .and_hms_nano_opt(
+        date_time.hour as u32,
+        date_time.minute as u32,
+        date_time.second as u32,
+        date_time.nanosecond.unwrap_or(0),
+    )?;
+    let date = MediaDate::new(naive, DatePrecision::Second, source);
+
+    match date_time
+        .offset
+        .and_then(|minutes| FixedOffset::east_opt(minutes as i32 * 60))
+    {
+        Some(offset) => Some(date.with_offset(offset)),
+        None => Some(date),
+    }
+}
+
+/// Adds up a GPS value stored as three rationals, such as degrees, minutes and seconds.
+fn sexagesimal(field: &Field, scales: [f64; 3]) -> Option<f64> {
+    match field.value {
+        Value::Rational(ref parts) if parts.len() == 3 => Some(
+            parts
+                .iter()
+                .zip(scales)
+                .map(|(part, scale)| part.to_f64() * scale)
+                .sum(),
+        )
+        .filter(|value: &f64| value.is_finite()),
+        _ => None,
     }
 }
 
+/// Reads the UTC time of the GPS fix, which has no local time zone of its own.
+fn gps_date(exif: &Exif) -> Option<MediaDate> {
+    let stamp = exif
+        .get_field(ExifTag::GPSDateStamp, In::PRIMARY)
+        .and_then(ascii_value)?;
+    let date = NaiveDate::parse_from_str(std::str::from_utf8(stamp).ok()?, "%Y:%m:%d").ok()?;
+    let seconds = sexagesimal(
+        exif.get_field(ExifTag::GPSTimeStamp, In::PRIMARY)?,
+        [3600.0, 60.0, 1.0],
+    )?;
+
+    let date_time =
+        date.and_hms_opt(0, 0, 0)? + TimeDelta::milliseconds((seconds * 1000.0).round() as i64);
+    Some(MediaDate::from_utc(date_time, DateSource::ExifGpsTime))
+}
+
+/// Reads when a photo was taken, preferring `DateTimeOriginal`, then `DateTimeDigitized`,
+/// then `DateTime`. Photos with none of those fall back to the UTC time of their GPS fix.
+pub fn read_photo_creation_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    let reader = read_exif(path)?;
+
+    DATE_TAGS
+        .into_iter()
+        .find_map(|tags| exif_date(&reader, tags))
+        .or_else(|| gps_date(&reader))
+        .ok_or_else(|| MediaInfoError::NoMetadata(format!("No EXIF date in {:?}", path)))
+}
+
+/// Reads one GPS coordinate in degrees, negative when its reference is `negative`.
+fn gps_coordinate(exif: &Exif, tag: ExifTag, reference: ExifTag, negative: &[u8]) -> Option<f64> {
+    let degrees = sexagesimal(
+        exif.get_field(tag, In::PRIMARY)?,
+        [1.0, 1.0 / 60.0, 1.0 / 3600.0],
+    )?;
+
+    match exif.get_field(reference, In::PRIMARY).and_then(ascii_value) {
+        Some(value) if value == negative => Some(-degrees),
+        _ => Some(degrees),
+    }
+}
+
+/// Reads where a photo was taken, as latitude and longitude in degrees.
+pub fn read_photo_location(path: &Path) -> Result<(f64, f64), MediaInfoError> {
+    let reader = read_exif(path)?;
+
+    gps_coordinate(&reader, ExifTag::GPSLatitude, ExifTag::GPSLatitudeRef, b"S")
+        .zip(gps_coordinate(
+            &reader,
+            ExifTag::GPSLongitude,
+            ExifTag::GPSLongitudeRef,
+            b"W",
+        ))
+        .ok_or_else(|| MediaInfoError::NoMetadata(format!("No GPS location in {:?}", path)))
+}
+
+/// Reads the identifier an iPhone gives both halves of a Live Photo from the Apple maker
+/// note of the photo.
+pub fn read_photo_content_identifier(path: &Path) -> Result<String, MediaInfoError> {
+    let exif = read_exif(path)?;
+
+    exif.get_field(ExifTag::MakerNote, In::PRIMARY)
+        .and_then(|field| match field.value {
+            Value::Undefined(ref maker_note, _) => apple::content_identifier(maker_note),
+            _ => None,
+        })
+        .ok_or_else(|| MediaInfoError::NoMetadata(format!("No content identifier in {:?}", path)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,9 +239,113 @@ mod tests {
     fn can_read_photo_creation_date() {
         let raw_path_str = "../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg";
         let path = Path::new(raw_path_str);
-        let creation_date = read_photo_creation_date(path);
+        let creation_date = read_photo_creation_date(path).unwrap();
+
+        assert_eq!(creation_date.to_string().contains("2024-10-22"), true);
+        assert_eq!(creation_date.source, DateSource::ExifDateTimeOriginal);
+    }
 
-        assert_eq!(creation_date.unwrap().contains("2024-10-22"), true);
+    fn ascii_field(tag: ExifTag, value: &str) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+        }
+    }
+
+    /// Writes a TIFF file that only holds the given EXIF fields.
+    fn write_tiff(dir: &Path, fields: &[Field]) -> std::path::PathBuf {
+        let mut writer = exif::experimental::Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+
+        let path = dir.join("photo.tif");
+        std::fs::write(&path, tiff.into_inner()).unwrap();
+        path
+    }
+
+    #[test]
+    fn prefers_the_original_date_over_the_edit_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_tiff(
+            dir.path(),
+            &[
+                ascii_field(ExifTag::DateTime, "2025:03:01 09:00:00"),
+                ascii_field(ExifTag::DateTimeOriginal, "2024:10:22 21:15:30"),
+                ascii_field(ExifTag::OffsetTimeOriginal, "-05:00"),
+                ascii_field(ExifTag::SubSecTimeOriginal, "25"),
+            ],
+        );
+
+        let date = read_photo_creation_date(&path).unwrap();
+        assert_eq!(date.source, DateSource::ExifDateTimeOriginal);
+        assert_eq!(date.to_string(), "2024-10-22T21:15:30-05:00");
+        assert_eq!(date.date_time.and_utc().timestamp_subsec_millis(), 250);
+    }
+
+    #[test]
+    fn blank_dates_fall_through_to_the_next_tag() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_tiff(
+            dir.path(),
+            &[
+                ascii_field(ExifTag::DateTimeOriginal, "    :  :     :  :  "),
+                ascii_field(ExifTag::DateTimeDigitized, "0000:00:00 00:00:00"),
+                ascii_field(ExifTag::DateTime, "2024:10:22 12:00:00"),
+            ],
+        );
+
+        let date = read_photo_creation_date(&path).unwrap();
+        assert_eq!(date.source, DateSource::ExifDateTime);
+        assert_eq!(date.offset, None);
+
+        let path = write_tiff(
+            dir.path(),
+            &[ascii_field(
+                ExifTag::DateTimeOriginal,
+                "    :  :     :  :  ",
+            )],
+        );
+        assert!(matches!(
+            read_photo_creation_date(&path),
+            Err(MediaInfoError::NoMetadata(_))
+        ));
+    }
+
+    fn rational_field(tag: ExifTag, values: [(u32, u32); 3]) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Rational(values.iter().map(|&value| value.into()).collect()),
+        }
+    }
+
+    #[test]
+    fn gps_fix_is_a_utc_only_fallback_and_a_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_tiff(
+            dir.path(),
+            &[
+                ascii_field(ExifTag::GPSDateStamp, "2024:10:23"),
+                rational_field(ExifTag::GPSTimeStamp, [(2, 1), (15, 1), (3050, 100)]),
+                ascii_field(ExifTag::GPSLatitudeRef, "N"),
+                rational_field(ExifTag::GPSLatitude, [(39, 1), (44, 1), (2112, 100)]),
+                ascii_field(ExifTag::GPSLongitudeRef, "W"),
+                rational_field(ExifTag::GPSLongitude, [(104, 1), (59, 1), (2508, 100)]),
+            ],
+        );
+
+        let date = read_photo_creation_date(&path).unwrap();
+        assert_eq!(date.source, DateSource::ExifGpsTime);
+        assert!(date.utc_only);
+        assert_eq!(date.to_string(), "2024-10-23T02:15:30+00:00");
+
+        let (latitude, longitude) = read_photo_location(&path).unwrap();
+        assert!((latitude - 39.7392).abs() < 1e-4, "{}", latitude);
+        assert!((longitude + 104.9903).abs() < 1e-4, "{}", longitude);
     }
 
     #[test]
@@ -90,4 +362,17 @@ mod tests {
         let path = Path::new("nonexistent.jpg");
         assert!(read_photo_dimensions(path).is_err());
     }
+
+    #[test]
+    fn photo_without_exif_has_no_metadata() {
+        let path = Path::new("../test-media/TESTDOCUMENT.txt");
+        assert!(matches!(
+            read_photo_creation_date(path),
+            Err(MediaInfoError::Corrupt(_)) | Err(MediaInfoError::NoMetadata(_))
+        ));
+        assert!(matches!(
+            read_photo_creation_date(Path::new("nonexistent.jpg")),
+            Err(MediaInfoError::NotFound(_))
+        ));
+    }
 }
@@ -0,0 +1,512 @@
+use super::exif_error;
+use super::tiff::Tiff;
+use crate::bmff::{boxes, read_moov};
+use crate::error::MediaInfoError;
+use exif::{Context, Exif, Field, In, Reader, Tag as ExifTag, Value};
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const NEW_SUBFILE_TYPE: u16 = 0x00FE;
+const IMAGE_WIDTH: u16 = 0x0100;
+const IMAGE_LENGTH: u16 = 0x0101;
+pub(super) const SUB_IFDS: u16 = 0x014A;
+
+/// The borders of the image area on the sensor, in the first IFD of Panasonic RW2 files.
+const RW2_TOP_BORDER: u16 = 0x0004;
+const RW2_LEFT_BORDER: u16 = 0x0005;
+const RW2_BOTTOM_BORDER: u16 = 0x0006;
+const RW2_RIGHT_BORDER: u16 = 0x0007;
+
+/// How much of a TIFF based RAW is read for its metadata. The IFDs and the values they
+/// point to come first, ahead of the tens of megabytes of image data.
+const TIFF_METADATA_LIMIT: u64 = 1 << 20;
+
+/// The `uuid` box in the `moov` box of CR3 files that holds Canon's metadata boxes.
+const CANON_UUID: [u8; 16] = [
+    0x85, 0xC0, 0xB6, 0x87, 0x82, 0x0F, 0x11, 0xE0, 0x81, 0x11, 0xF4, 0xCE, 0x46, 0x2B, 0x6A, 0x48,
+];
+
+/// The tags of the RAF metadata directory holding the sensor size, cropped and in full.
+const RAF_CROPPED_SIZE: u16 = 0x0111;
+const RAF_FULL_SIZE: u16 = 0x0100;
+
+/// How the metadata of a photo is laid out in its file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// TIFF and the RAWs built on it without changes: CR2, NEF, ARW and DNG.
+    Tiff,
+    /// Olympus ORF, a TIFF structure with its own magic number.
+    Orf,
+    /// Panasonic RW2, a TIFF structure with its own magic number.
+    Rw2,
+    /// Canon CR3, an ISO base media file with TIFF structures in boxes.
+    Cr3,
+    /// Fujifilm RAF, which embeds a JPEG holding the EXIF data.
+    Raf,
+}
+
+fn layout(header: &[u8]) -> Option<Layout> {
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, b"II*\0") || at(0, b"MM\0*") {
+        Some(Layout::Tiff)
+    } else if at(0, b"IIRO") || at(0, b"IIRS") || at(0, b"MMOR") {
+        Some(Layout::Orf)
+    } else if at(0, b"IIU\0") {
+        Some(Layout::Rw2)
+    } else if at(4, b"ftypcrx ") {
+        Some(Layout::Cr3)
+    } else if at(0, b"FUJIFILMCCD-RAW") {
+        Some(Layout::Raf)
+    } else {
+        None
+    }
+}
+
+/// The EXIF data of a TIFF or camera RAW photo, with the size of the image on its sensor.
+pub(super) struct RawPhoto {
+    pub exif: Exif,
+    pub sensor_size: Option<(u32, u32)>,
+}
+
+/// Reads a TIFF or camera RAW photo, or `None` for other files, which the EXIF reader
+/// handles on its own.
+pub(super) fn read_raw(path: &Path) -> Result<Option<RawPhoto>, MediaInfoError> {
+    let mut file = File::open(path)?;
+    let mut header = Vec::with_capacity(16);
+    file.by_ref().take(16).read_to_end(&mut header)?;
+
+    match layout(&header) {
+        None => Ok(None),
+        Some(Layout::Cr3) => read_cr3(path, &mut file).map(Some),
+        Some(Layout::Raf) => read_raf(path, &mut file).map(Some),
+        Some(layout) => read_tiff(path, &mut file, layout).map(Some),
+    }
+}
+
+/// Reads the size of the largest full resolution image in the first IFD and its sub-IFDs,
+/// which is where TIFF based RAWs keep the sensor data next to their previews.
+fn tiff_sensor_size(tiff: &Tiff) -> Option<(u32, u32)> {
+    let first = tiff.entries(tiff.first_ifd()?)?;
+    let sub_ifds = first
+        .iter()
+        .find(|entry| entry.tag == SUB_IFDS)
+        .map(|entry| tiff.longs(entry))
+        .unwrap_or_default();
+
+    std::iter::once(Some(first))
+        .chain(sub_ifds.into_iter().map(|ifd| tiff.entries(ifd as usize)))
+        .flatten()
+        .filter_map(|entries| {
+            let number = |tag| {
+                entries
+                    .iter()
+                    .find(|entry| entry.tag == tag)
+                    .and_then(|entry| tiff.number(entry))
+            };
+            if number(NEW_SUBFILE_TYPE).unwrap_or(0) != 0 {
+                return None;
+            }
+            Some((number(IMAGE_WIDTH)?, number(IMAGE_LENGTH)?))
+        })
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .max_by_key(|&(width, height)| u64::from(width) * u64::from(height))
+}
+
+/// Reads the size of the image area between the sensor borders of an RW2 file.
+fn rw2_sensor_size(tiff: &Tiff) -> Option<(u32, u32)> {
+    let first = tiff.entries(tiff.first_ifd()?)?;
+    let number = |tag| {
+        first
+            .iter()
+            .find(|entry| entry.tag == tag)
+            .and_then(|entry| tiff.number(entry))
+    };
+
+    let width = number(RW2_RIGHT_BORDER)?.checked_sub(number(RW2_LEFT_BORDER)?)?;
+    let height = number(RW2_BOTTOM_BORDER)?.checked_sub(number(RW2_TOP_BORDER)?)?;
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+/// Reads the EXIF data of a TIFF based RAW from the first `TIFF_METADATA_LIMIT` bytes of
+/// the file. Fields whose values lie past them are left out when the file is longer.
+fn read_tiff(path: &Path, file: &mut File, layout: Layout) -> Result<RawPhoto, MediaInfoError> {
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.by_ref()
+        .take(TIFF_METADATA_LIMIT)
+        .read_to_end(&mut bytes)?;
+    let truncated = file.metadata()?.len() > bytes.len() as u64;
+    let (sensor_size, little_endian) = match Tiff::new(&bytes) {
+        Some(tiff) if layout == Layout::Rw2 => (rw2_sensor_size(&tiff), tiff.little_endian()),
+        Some(tiff) => (tiff_sensor_size(&tiff), tiff.little_endian()),
+        None => (None, true),
+    };
+
+    // The EXIF reader only accepts the magic number of TIFF.
+    if layout != Layout::Tiff {
+        let magic = if little_endian { [42, 0] } else { [0, 42] };
+        bytes[2..4].copy_from_slice(&magic);
+    }
+
+    let exif = Reader::new()
+        .continue_on_error(truncated)
+        .read_raw(bytes)
+        .or_else(|e| e.distill_partial_result(|_| ()))
+        .map_err(|e| exif_error(path, e))?;
+    Ok(RawPhoto { exif, sensor_size })
+}
+
+/// Reads the EXIF data of a CR3 file. Its `CMT1` box holds the first IFD of a TIFF
+/// structure, `CMT2` the EXIF IFD and `CMT4` the GPS IFD, each as the first IFD of a TIFF
+/// structure of its own, so they are put back together into one.
+fn read_cr3(path: &Path, file: &mut File) -> Result<RawPhoto, MediaInfoError> {
+    let missing = || MediaInfoError::NoMetadata(format!("No EXIF data in {:?}", path));
+    let moov = read_moov(file)?.ok_or_else(missing)?;
+    let canon = boxes(&moov)
+        .find(|(kind, body)| kind == b"uuid" && body.starts_with(&CANON_UUID))
+        .map(|(_, body)| &body[CANON_UUID.len()..])
+        .ok_or_else(missing)?;
+
+    let mut fields = Vec::new();
+    let mut sensor_size = None;
+    for (kind, body) in boxes(canon) {
+        let context = match &kind {
+            b"CMT1" => Context::Tiff,
+            b"CMT2" => Context::Exif,
+            b"CMT4" => Context::Gps,
+            _ => continue,
+        };
+        let exif = Reader::new()
+            .read_raw(body.to_vec())
+            .map_err(|e| exif_error(path, e))?;
+
+        if context == Context::Tiff {
+            let number = |tag| {
+                exif.get_field(tag, In::PRIMARY)
+                    .and_then(|field| field.value.get_uint(0))
+            };
+            sensor_size = number(ExifTag::ImageWidth).zip(number(ExifTag::ImageLength));
+        }
+        fields.extend(
+            exif.fields()
+                .filter(|field| field.ifd_num == In::PRIMARY)
+                .filter(|field| !matches!(field.value, Value::Unknown(..)))
+                .map(|field| Field {
+                    tag: match field.tag.context() {
+                        Context::Tiff => ExifTag(context, field.tag.number()),
+                        _ => field.tag,
+                    },
+                    ifd_num: In::PRIMARY,
+                    value: field.value.clone(),
+                }),
+        );
+    }
+    if fields.is_empty() {
+        return Err(missing());
+    }
+
+    let mut writer = exif::experimental::Writer::new();
+    for field in &fields {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer
+        .write(&mut tiff, true)
+        .map_err(|e| exif_error(path, e))?;
+
+    let exif = Reader::new()
+        .read_raw(tiff.into_inner())
+        .map_err(|e| exif_error(path, e))?;
+    Ok(RawPhoto { exif, sensor_size })
+}
+
+/// Reads `length` bytes at `offset`, or fewer when the file ends first.
+fn read_range(file: &mut File, offset: u32, length: u32) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(u64::from(offset)))?;
+    file.take(u64::from(length)).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Reads the sensor size from the RAF metadata directory, a count of records followed by
+/// the records, each a tag, a length and the data, all big endian. Sizes are stored as
+/// height, then width.
+fn raf_sensor_size(directory: &[u8]) -> Option<(u32, u32)> {
+    let count = u32::from_be_bytes(directory.get(..4)?.try_into().ok()?);
+    let mut position = 4;
+    let mut full = None;
+    let mut cropped = None;
+
+    for _ in 0..count {
+        let record = directory.get(position..position + 4)?;
+        let tag = u16::from_be_bytes([record[0], record[1]]);
+        let length = u16::from_be_bytes([record[2], record[3]]) as usize;
+        let data = directory.get(position + 4..position + 4 + length)?;
+        if let [h1, h2, w1, w2, ..] = *data {
+            let size = (
+                u32::from(u16::from_be_bytes([w1, w2])),
+                u32::from(u16::from_be_bytes([h1, h2])),
+            );
+            match tag {
+                RAF_FULL_SIZE => full = Some(size),
+                RAF_CROPPED_SIZE => cropped = Some(size),
+                _ => {}
+            }
+        }
+        position += 4 + length;
+    }
+
+    cropped
+        .or(full)
+        .filter(|&(width, height)| width > 0 && height > 0)
+}
+
+/// Reads the EXIF data of a RAF file from its embedded JPEG. The header holds the offset
+/// and length of the JPEG at byte 84 and of the metadata directory at byte 92.
+fn read_raf(path: &Path, file: &mut File) -> Result<RawPhoto, MediaInfoError> {
+    let mut header = [0; 100];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)
+        .map_err(|_| MediaInfoError::Corrupt(format!("Truncated RAF header in {:?}", path)))?;
+    let at = |offset: usize| {
+        u32::from_be_bytes([
+            header[offset],
+            header[offset + 1],
+            header[offset + 2],
+            header[offset + 3],
+        ])
+    };
+
+    let jpeg = read_range(file, at(84), at(88))?;
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(jpeg))
+        .map_err(|e| exif_error(path, e))?;
+    let directory = read_range(file, at(92), at(96))?;
+
+    Ok(RawPhoto {
+        exif,
+        sensor_size: raf_sensor_size(&directory),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media_date::DateSource;
+    use crate::photo_info::read_photo_creation_date;
+    use crate::photo_info::read_photo_dimensions;
+    use crate::photo_info::struct_photo_info::PhotoInfo;
+    use std::fs;
+
+    fn ascii(tag: ExifTag, value: &str) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+        }
+    }
+
+    fn short(tag: ExifTag, value: u16) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Short(vec![value]),
+        }
+    }
+
+    fn tiff(fields: &[Field], little_endian: bool) -> Vec<u8> {
+        let mut writer = exif::experimental::Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, little_endian).unwrap();
+        tiff.into_inner()
+    }
+
+    fn iso_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut bytes = (body.len() as u32 + 8).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    #[test]
+    fn recognizes_raw_layouts() {
+        assert_eq!(layout(b"II*\0\x10\0\0\0CR\x02\0"), Some(Layout::Tiff));
+        assert_eq!(layout(b"IIRO\x08\0\0\0"), Some(Layout::Orf));
+        assert_eq!(layout(b"IIU\0\x08\0\0\0"), Some(Layout::Rw2));
+        assert_eq!(layout(b"\0\0\0\x18ftypcrx \0\0\0\x01"), Some(Layout::Cr3));
+        assert_eq!(layout(b"FUJIFILMCCD-RAW 0201"), Some(Layout::Raf));
+        assert_eq!(layout(&[0xFF, 0xD8, 0xFF, 0xE1]), None);
+    }
+
+    #[test]
+    fn reads_the_sensor_size_of_raf_directories() {
+        let mut directory = 2u32.to_be_bytes().to_vec();
+        for (tag, height, width) in [(RAF_FULL_SIZE, 4032u16, 6160u16), (0x0130, 0, 0)] {
+            directory.extend_from_slice(&tag.to_be_bytes());
+            directory.extend_from_slice(&4u16.to_be_bytes());
+            directory.extend_from_slice(&height.to_be_bytes());
+            directory.extend_from_slice(&width.to_be_bytes());
+        }
+
+        assert_eq!(raf_sensor_size(&directory), Some((6160, 4032)));
+        assert_eq!(raf_sensor_size(&directory[..10]), None);
+    }
+
+    #[test]
+    fn reads_orf_and_rw2_despite_their_magic_numbers() {
+        let dir = tempfile::tempdir().unwrap();
+        let orf = dir.path().join("P1010001.ORF");
+        let mut bytes = tiff(
+            &[
+                ascii(ExifTag::Make, "OLYMPUS IMAGING CORP."),
+                ascii(ExifTag::DateTimeOriginal, "2023:05:14 10:20:30"),
+                short(ExifTag::ImageWidth, 4640),
+                short(ExifTag::ImageLength, 3472),
+            ],
+            false,
+        );
+        bytes[2..4].copy_from_slice(b"OR");
+        fs::write(&orf, bytes).unwrap();
+
+        let date = read_photo_creation_date(&orf).unwrap();
+        assert_eq!(date.source, DateSource::ExifDateTimeOriginal);
+        assert_eq!(date.to_string(), "2023-05-14T10:20:30");
+        assert_eq!(read_photo_dimensions(&orf).unwrap(), (4640, 3472));
+
+        let rw2 = dir.path().join("P1000001.RW2");
+        let border = |tag, value| short(ExifTag(Context::Tiff, tag), value);
+        let mut bytes = tiff(
+            &[
+                ascii(ExifTag::Model, "DC-G9"),
+                border(RW2_TOP_BORDER, 8),
+                border(RW2_LEFT_BORDER, 16),
+                border(RW2_BOTTOM_BORDER, 3896),
+                border(RW2_RIGHT_BORDER, 5216),
+            ],
+            true,
+        );
+        bytes[2..4].copy_from_slice(&[0x55, 0]);
+        fs::write(&rw2, bytes).unwrap();
+
+        assert_eq!(PhotoInfo::new(&rw2).unwrap().model, "\"DC-G9\"");
+        assert_eq!(read_photo_dimensions(&rw2).unwrap(), (5200, 3888));
+    }
+
+    #[test]
+    fn reads_only_the_start_of_large_tiff_raws() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DSC_0001.NEF");
+        let past_limit = TIFF_METADATA_LIMIT as u32 + 16;
+
+        // A first IFD with the date next to it and a description after the image data
+        let mut bytes = b"II*\0\x08\0\0\0\x02\0".to_vec();
+        for (tag, count, offset) in [(0x0132u16, 20u32, 38u32), (0x010E, 12, past_limit)] {
+            bytes.extend_from_slice(&tag.to_le_bytes());
+            bytes.extend_from_slice(&2u16.to_le_bytes());
+            bytes.extend_from_slice(&count.to_le_bytes());
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(b"2022:08:09 07:06:05\0");
+        bytes.resize(past_limit as usize, 0);
+        bytes.extend_from_slice(b"Description\0");
+        fs::write(&path, bytes).unwrap();
+
+        let date = read_photo_creation_date(&path).unwrap();
+        assert_eq!(date.source, DateSource::ExifDateTime);
+        assert_eq!(date.to_string(), "2022-08-09T07:06:05");
+        let exif = read_raw(&path).unwrap().unwrap().exif;
+        assert!(exif.get_field(ExifTag::ImageDescription, In::PRIMARY).is_none());
+    }
+
+    #[test]
+    fn reads_the_canon_boxes_of_cr3_files() {
+        let cmt1 = tiff(
+            &[
+                ascii(ExifTag::Make, "Canon"),
+                ascii(ExifTag::Model, "Canon EOS R5"),
+                short(ExifTag::ImageWidth, 8192),
+                short(ExifTag::ImageLength, 5464),
+            ],
+            true,
+        );
+        // The EXIF IFD is stored as the first IFD of its own TIFF structure.
+        let cmt2 = tiff(
+            &[
+                ascii(
+                    ExifTag(Context::Tiff, ExifTag::DateTimeOriginal.number()),
+                    "2024:08:02 07:45:10",
+                ),
+                ascii(
+                    ExifTag(Context::Tiff, ExifTag::LensModel.number()),
+                    "RF24-105mm F4 L IS USM",
+                ),
+            ],
+            true,
+        );
+        let mut canon = CANON_UUID.to_vec();
+        canon.extend(iso_box(b"CNCV", b"CanonCR3_001/00.09.00/00.00.00"));
+        canon.extend(iso_box(b"CMT1", &cmt1));
+        canon.extend(iso_box(b"CMT2", &cmt2));
+
+        let mut bytes = iso_box(b"ftyp", b"crx \0\0\0\x01crx isom");
+        bytes.extend(iso_box(b"moov", &iso_box(b"uuid", &canon)));
+        bytes.extend(iso_box(b"mdat", &[0; 64]));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("IMG_0001.CR3");
+        fs::write(&path, bytes).unwrap();
+
+        let date = read_photo_creation_date(&path).unwrap();
+        assert_eq!(date.source, DateSource::ExifDateTimeOriginal);
+        assert_eq!(date.to_string(), "2024-08-02T07:45:10");
+
+        let info = PhotoInfo::new(&path).unwrap();
+        assert_eq!(info.make, "\"Canon\"");
+        assert_eq!(info.lens_model, "\"RF24-105mm F4 L IS USM\"");
+        assert_eq!(info.pixel_x_dimension, "8192");
+        assert_eq!(info.pixel_y_dimension, "5464");
+    }
+
+    #[test]
+    fn reads_the_embedded_jpeg_of_raf_files() {
+        let exif = tiff(
+            &[
+                ascii(ExifTag::Make, "FUJIFILM"),
+                ascii(ExifTag::DateTimeOriginal, "2022:12:24 18:00:05"),
+            ],
+            false,
+        );
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&(exif.len() as u16 + 8).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend(exif);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+
+        let mut directory = 1u32.to_be_bytes().to_vec();
+        directory.extend_from_slice(&RAF_CROPPED_SIZE.to_be_bytes());
+        directory.extend_from_slice(&4u16.to_be_bytes());
+        directory.extend_from_slice(&4000u16.to_be_bytes());
+        directory.extend_from_slice(&6000u16.to_be_bytes());
+
+        let mut bytes = b"FUJIFILMCCD-RAW 0201FF383501".to_vec();
+        bytes.resize(84, 0);
+        let jpeg_offset = 100 + directory.len() as u32;
+        for value in [jpeg_offset, jpeg.len() as u32, 100, directory.len() as u32] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes.extend(directory);
+        bytes.extend(jpeg);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DSCF0001.RAF");
+        fs::write(&path, bytes).unwrap();
+
+        let date = read_photo_creation_date(&path).unwrap();
+        assert_eq!(date.to_string(), "2022-12-24T18:00:05");
+        assert_eq!(read_photo_dimensions(&path).unwrap(), (6000, 4000));
+    }
+}
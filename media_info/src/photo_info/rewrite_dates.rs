@@ -0,0 +1,583 @@
+use super::raw::SUB_IFDS;
+use super::tiff::{ASCII, Entry, Tiff};
+use super::{DATE_TAGS, ascii_value, exif_date, read_exif};
+use crate::detect::is_raw;
+use crate::error::MediaInfoError;
+use crate::media_date::MediaDate;
+use exif::{Context, In, Tag as ExifTag};
+use std::collections::HashMap;
+use std::fs::{self, File, FileTimes};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The EXIF pointer from the first IFD to the EXIF IFD.
+const EXIF_IFD_POINTER: u16 = 0x8769;
+/// The TIFF/EP tag listing the changes made to an image since it was taken.
+const IMAGE_HISTORY: u16 = 0x9213;
+/// The tag giving the version of the DNG specification a DNG file follows.
+const DNG_VERSION: u16 = 0xC612;
+/// What [`rewrite_photo_dates`] adds to the `ImageHistory` of the photos it changes.
+const REWRITTEN: &str = "EXIF dates rewritten by media_organizer";
+
+/// One EXIF date tag changed by [`rewrite_photo_dates`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRewrite {
+    pub before: MediaDate,
+    pub after: MediaDate,
+}
+
+/// Where the TIFF structure holding the EXIF data is in a file.
+struct TiffLocation {
+    start: usize,
+    end: usize,
+    /// Where a JPEG stores the length of its `Exif` segment.
+    segment_length: Option<usize>,
+}
+
+/// Whether a TIFF structure is a camera RAW built on TIFF: a CR2, marked by `CR` after
+/// its header, or a NEF, ARW, PEF or DNG, whose first IFD points to the sensor data in
+/// sub-IFDs or gives a DNG version.
+fn is_raw_tiff(bytes: &[u8]) -> bool {
+    if bytes.get(8..10) == Some(b"CR") {
+        return true;
+    }
+    Tiff::new(bytes)
+        .and_then(|tiff| tiff.entries(tiff.first_ifd()?))
+        .is_some_and(|entries| {
+            entries
+                .iter()
+                .any(|entry| entry.tag == SUB_IFDS || entry.tag == DNG_VERSION)
+        })
+}
+
+/// Finds the TIFF structure holding the EXIF data: the whole file for TIFF photos, or the
+/// `Exif` APP1 segment of a JPEG. Camera RAWs built on TIFF are refused, since their
+/// originals are not to be changed.
+fn locate_tiff(bytes: &[u8]) -> Option<TiffLocation> {
+    if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        if is_raw_tiff(bytes) {
+            return None;
+        }
+        return Some(TiffLocation {
+            start: 0,
+            end: bytes.len(),
+            segment_length: None,
+        });
+    }
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut position = 2;
+    while position + 4 <= bytes.len() && bytes[position] == 0xFF {
+        let marker = bytes[position + 1];
+        let length = u16::from_be_bytes([bytes[position + 2], bytes[position + 3]]) as usize;
+        let body = position + 4;
+        if marker == 0xDA || length < 2 {
+            return None;
+        }
+        if marker == 0xE1 && bytes[body..].starts_with(b"Exif\0\0") {
+            return Some(TiffLocation {
+                start: body + 6,
+                end: (body + length - 2).min(bytes.len()),
+                segment_length: Some(position + 2),
+            })
+            .filter(|location| location.start <= location.end);
+        }
+        position = body + length - 2;
+    }
+    None
+}
+
+/// Collects where the ASCII values of an IFD's entries are stored, by tag number, and
+/// where its EXIF IFD starts when it points to one.
+fn ascii_values(
+    tiff: &Tiff,
+    ifd: usize,
+    values: &mut HashMap<u16, (usize, usize)>,
+) -> Option<Option<usize>> {
+    let mut exif_ifd = None;
+    for entry in tiff.entries(ifd)? {
+        if entry.tag == EXIF_IFD_POINTER {
+            exif_ifd = tiff.number(&entry).map(|offset| offset as usize);
+        } else if entry.kind == ASCII && entry.count > 4 {
+            values.insert(entry.tag, (tiff.u32_at(entry.value)? as usize, entry.count));
+        }
+    }
+    Some(exif_ifd)
+}
+
+/// Maps the tag numbers of the first and EXIF IFDs' ASCII values to their position and
+/// length in `tiff`.
+fn ascii_locations(tiff: &[u8]) -> Option<HashMap<u16, (usize, usize)>> {
+    let tiff = Tiff::new(tiff)?;
+
+    let mut values = HashMap::new();
+    let exif_ifd = ascii_values(&tiff, tiff.first_ifd()?, &mut values)?;
+    if let Some(exif_ifd) = exif_ifd {
+        ascii_values(&tiff, exif_ifd, &mut values)?;
+    }
+    Some(values)
+}
+
+/// Overwrites the start of an ASCII value in place, keeping its length.
+fn overwrite(tiff: &mut [u8], location: Option<&(usize, usize)>, text: &str) -> Option<()> {
+    let &(position, count) = location?;
+    if text.len() >= count {
+        return None;
+    }
+    tiff.get_mut(position..position + text.len())?
+        .copy_from_slice(text.as_bytes());
+    Some(())
+}
+
+/// Reads the ASCII value of an entry, without its trailing NULs.
+fn ascii_text(tiff: &Tiff, bytes: &[u8], entry: &Entry) -> Option<String> {
+    let position = match entry.count {
+        0..=4 => entry.value,
+        _ => tiff.u32_at(entry.value)? as usize,
+    };
+    let text = bytes.get(position..position.checked_add(entry.count)?)?;
+    Some(
+        String::from_utf8_lossy(text)
+            .trim_end_matches('\0')
+            .to_string(),
+    )
+}
+
+/// Adds [`REWRITTEN`] to the `ImageHistory` of the first IFD. The IFD is copied to the end
+/// of the TIFF structure with the new entry, since it has no room to grow where it is; its
+/// other entries keep pointing to their values, which stay where they were.
+fn mark_rewritten(bytes: &mut Vec<u8>, location: &TiffLocation) -> Option<()> {
+    let (start, end) = (location.start, location.end);
+    let tiff = Tiff::new(&bytes[start..end])?;
+    let little_endian = tiff.little_endian();
+    let first_ifd = tiff.first_ifd()?;
+    let entries = tiff.entries(first_ifd)?;
+    let next_ifd = tiff.u32_at(first_ifd + 2 + entries.len() * 12)?;
+
+    let history = entries
+        .iter()
+        .find(|entry| entry.tag == IMAGE_HISTORY && entry.kind == ASCII)
+        .and_then(|entry| ascii_text(&tiff, &bytes[start..end], entry))
+        .unwrap_or_default();
+    if history.contains(REWRITTEN) {
+        return Some(());
+    }
+    let history = match history.is_empty() {
+        true => REWRITTEN.to_string(),
+        false => format!("{}; {}", history, REWRITTEN),
+    };
+
+    let u16_bytes = |n: u16| match little_endian {
+        true => n.to_le_bytes(),
+        false => n.to_be_bytes(),
+    };
+    let u32_bytes = |n: u32| match little_endian {
+        true => n.to_le_bytes(),
+        false => n.to_be_bytes(),
+    };
+
+    // IFDs start on a word boundary
+    let ifd = (end - start + 1) & !1;
+    let kept: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| entry.tag != IMAGE_HISTORY)
+        .collect();
+    let text_at = u32::try_from(ifd + 2 + (kept.len() + 1) * 12 + 4).ok()?;
+
+    let mut addition = vec![0; ifd - (end - start)];
+    addition.extend_from_slice(&u16_bytes(u16::try_from(kept.len() + 1).ok()?));
+    let mut history_entry = Vec::with_capacity(12);
+    history_entry.extend_from_slice(&u16_bytes(IMAGE_HISTORY));
+    history_entry.extend_from_slice(&u16_bytes(ASCII));
+    history_entry.extend_from_slice(&u32_bytes(history.len() as u32 + 1));
+    history_entry.extend_from_slice(&u32_bytes(text_at));
+    let mut history_entry = Some(history_entry);
+    for entry in kept {
+        if entry.tag > IMAGE_HISTORY
+            && let Some(history_entry) = history_entry.take()
+        {
+            addition.extend(history_entry);
+        }
+        addition.extend_from_slice(&bytes[start + entry.value - 8..start + entry.value + 4]);
+    }
+    addition.extend(history_entry.unwrap_or_default());
+    addition.extend_from_slice(&u32_bytes(next_ifd));
+    addition.extend_from_slice(history.as_bytes());
+    addition.push(0);
+
+    if let Some(segment_length) = location.segment_length {
+        let length = u16::from_be_bytes([bytes[segment_length], bytes[segment_length + 1]]);
+        let length = u16::try_from(length as usize + addition.len()).ok()?;
+        bytes[segment_length..segment_length + 2].copy_from_slice(&length.to_be_bytes());
+    }
+    bytes[start + 4..start + 8].copy_from_slice(&u32_bytes(u32::try_from(ifd).ok()?));
+    bytes.splice(end..end, addition);
+    Some(())
+}
+
+/// Whether [`rewrite_photo_dates`] already changed the dates of a photo, going by the
+/// `ImageHistory` it leaves behind.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use media_info::photo_dates_rewritten;
+/// use std::path::Path;
+///
+/// if photo_dates_rewritten(Path::new("IMG_0001.jpg")) {
+///     println!("Already corrected");
+/// }
+/// ```
+pub fn photo_dates_rewritten(path: &Path) -> bool {
+    read_exif(path)
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(ExifTag(Context::Tiff, IMAGE_HISTORY), In::PRIMARY)
+                .and_then(ascii_value)
+                .map(|history| String::from_utf8_lossy(history).contains(REWRITTEN))
+        })
+        .unwrap_or(false)
+}
+
+fn write_temporary(temporary: &Path, bytes: &[u8], original: &fs::Metadata) -> io::Result<()> {
+    let mut file = File::create(temporary)?;
+    file.write_all(bytes)?;
+    file.set_times(
+        FileTimes::new()
+            .set_accessed(original.accessed()?)
+            .set_modified(original.modified()?),
+    )?;
+    file.set_permissions(original.permissions())?;
+    file.sync_all()
+}
+
+/// Replaces the contents of a file through a temporary file next to it, which is synced
+/// and given the original's permissions and modification time before it is renamed over
+/// the original. The original is never left half written.
+fn replace_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let original = fs::metadata(path)?;
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".rewrite");
+    let temporary = PathBuf::from(temporary);
+
+    let replaced =
+        write_temporary(&temporary, bytes, &original).and_then(|_| fs::rename(&temporary, path));
+    if replaced.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    replaced
+}
+
+/// Corrects the `DateTimeOriginal`, `DateTimeDigitized` and `DateTime` EXIF tags of a JPEG
+/// or TIFF photo with `correct`, returning each date that changed. When a date has an
+/// offset tag and the correction moves it into another offset, the offset tag is rewritten
+/// as well.
+///
+/// The dates are overwritten in place, at the same length, and the change is noted in the
+/// photo's `ImageHistory` so [`photo_dates_rewritten`] can tell it was already corrected.
+/// The file is replaced as a whole, keeping its permissions and modification time. With
+/// `dry_run`, the file is left untouched and only the changes are returned.
+///
+/// # Errors
+///
+/// Returns `Unsupported` for photos other than JPEG and TIFF, camera RAWs included, or
+/// when a JPEG's `Exif` segment has no room left for the history, and `NoMetadata` when a
+/// date cannot be found in the file's EXIF structure.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use chrono::TimeDelta;
+/// use media_info::rewrite_photo_dates;
+/// use std::path::Path;
+///
+/// let changes = rewrite_photo_dates(
+///     Path::new("IMG_0001.jpg"),
+///     |mut date| {
+///         date.date_time += TimeDelta::minutes(72);
+///         date
+///     },
+///     false,
+/// )
+/// .unwrap();
+/// ```
+pub fn rewrite_photo_dates(
+    path: &Path,
+    correct: impl Fn(MediaDate) -> MediaDate,
+    dry_run: bool,
+) -> Result<Vec<DateRewrite>, MediaInfoError> {
+    let unsupported = || {
+        MediaInfoError::Unsupported(format!(
+            "Only JPEG and TIFF dates can be rewritten: {:?}",
+            path
+        ))
+    };
+    if is_raw(path) {
+        return Err(unsupported());
+    }
+    let exif = read_exif(path)?;
+    let mut bytes = fs::read(path)?;
+    let location = locate_tiff(&bytes).ok_or_else(unsupported)?;
+    let tiff = &mut bytes[location.start..location.end];
+    let locations = ascii_locations(tiff).ok_or_else(|| {
+        MediaInfoError::Corrupt(format!("Could not read EXIF data from {:?}", path))
+    })?;
+    let missing = || MediaInfoError::NoMetadata(format!("EXIF date not found in {:?}", path));
+
+    let mut rewrites = Vec::new();
+    for tags in DATE_TAGS {
+        let Some(before) = exif_date(&exif, tags) else {
+            continue;
+        };
+        let after = correct(before);
+        if after.date_time == before.date_time && after.offset == before.offset {
+            continue;
+        }
+
+        let (date_tag, offset_tag, _, _) = tags;
+        overwrite(
+            tiff,
+            locations.get(&date_tag.number()),
+            &after.date_time.format("%Y:%m:%d %H:%M:%S").to_string(),
+        )
+        .ok_or_else(missing)?;
+        if before.offset.is_some()
+            && let Some(offset) = after.offset
+            && after.offset != before.offset
+        {
+            overwrite(
+                tiff,
+                locations.get(&offset_tag.number()),
+                &offset.to_string(),
+            )
+            .ok_or_else(missing)?;
+        }
+        rewrites.push(DateRewrite { before, after });
+    }
+
+    if !dry_run && !rewrites.is_empty() {
+        mark_rewritten(&mut bytes, &location).ok_or_else(|| {
+            MediaInfoError::Unsupported(format!(
+                "No room to mark the dates of {:?} as rewritten",
+                path
+            ))
+        })?;
+        replace_file(path, &bytes)?;
+    }
+
+    Ok(rewrites)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media_date::DateSource;
+    use crate::photo_info::read_photo_creation_date;
+    use chrono::TimeDelta;
+    use exif::{Field, Value};
+
+    /// Writes a JPEG whose `Exif` segment holds the given ASCII fields.
+    fn write_jpeg(dir: &Path, fields: &[(ExifTag, &str)]) -> std::path::PathBuf {
+        let fields: Vec<Field> = fields
+            .iter()
+            .map(|(tag, value)| Field {
+                tag: *tag,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+            })
+            .collect();
+        let mut writer = exif::experimental::Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&(tiff.len() as u16 + 8).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+
+        let path = dir.join("photo.jpg");
+        fs::write(&path, jpeg).unwrap();
+        path
+    }
+
+    fn shift(mut date: MediaDate) -> MediaDate {
+        date.date_time += TimeDelta::minutes(72);
+        date
+    }
+
+    #[test]
+    fn rewrites_every_date_tag_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_jpeg(
+            dir.path(),
+            &[
+                (ExifTag::DateTime, "2024:10:22 21:15:30"),
+                (ExifTag::DateTimeOriginal, "2024:10:22 21:15:30"),
+                (ExifTag::DateTimeDigitized, "2024:10:22 23:00:00"),
+            ],
+        );
+        let before = fs::read(&path).unwrap();
+
+        let rewrites = rewrite_photo_dates(&path, shift, false).unwrap();
+        assert_eq!(rewrites.len(), 3);
+        assert_eq!(rewrites[1].before.source, DateSource::ExifDateTimeDigitized);
+        assert_eq!(rewrites[1].after.to_string(), "2024-10-23T00:12:00");
+
+        let date = read_photo_creation_date(&path).unwrap();
+        assert_eq!(date.to_string(), "2024-10-22T22:27:30");
+        // The history goes after the EXIF data that was there, which stays where it was
+        let after = fs::read(&path).unwrap();
+        let end = before.len() - 2;
+        assert_eq!(after[20..80], before[20..80]);
+        assert!(after[end..].ends_with(&[REWRITTEN.as_bytes(), &[0, 0xFF, 0xD9]].concat()));
+    }
+
+    #[test]
+    fn rewritten_photos_are_marked_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_jpeg(
+            dir.path(),
+            &[
+                (ExifTag::Make, "Canon"),
+                (ExifTag::DateTimeOriginal, "2024:10:22 21:15:30"),
+                (ExifTag(Context::Tiff, IMAGE_HISTORY), "Cropped"),
+            ],
+        );
+        assert!(!photo_dates_rewritten(&path));
+
+        rewrite_photo_dates(&path, shift, false).unwrap();
+        rewrite_photo_dates(&path, shift, false).unwrap();
+        assert!(photo_dates_rewritten(&path));
+
+        let exif = read_exif(&path).unwrap();
+        let history = exif
+            .get_field(ExifTag(Context::Tiff, IMAGE_HISTORY), In::PRIMARY)
+            .and_then(ascii_value)
+            .unwrap();
+        assert_eq!(history, format!("Cropped; {}", REWRITTEN).as_bytes());
+        assert_eq!(
+            exif.get_field(ExifTag::Make, In::PRIMARY)
+                .and_then(ascii_value),
+            Some(&b"Canon"[..])
+        );
+        assert_eq!(
+            read_photo_creation_date(&path).unwrap().to_string(),
+            "2024-10-22T23:39:30"
+        );
+    }
+
+    #[test]
+    fn keeps_permissions_and_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_jpeg(
+            dir.path(),
+            &[(ExifTag::DateTimeOriginal, "2024:10:22 21:15:30")],
+        );
+        let modified =
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(modified).unwrap();
+        let mut permissions = file.metadata().unwrap().permissions();
+        permissions.set_readonly(true);
+        file.set_permissions(permissions).unwrap();
+        drop(file);
+
+        rewrite_photo_dates(&path, shift, false).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        assert!(metadata.permissions().readonly());
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert!(!dir.path().join("photo.jpg.rewrite").exists());
+    }
+
+    #[test]
+    fn rewrites_offsets_that_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_jpeg(
+            dir.path(),
+            &[
+                (ExifTag::DateTimeOriginal, "2024:06:03 08:00:00"),
+                (ExifTag::OffsetTimeOriginal, "-06:00"),
+            ],
+        );
+        let tokyo = |date: MediaDate| {
+            let instant = date.to_fixed().unwrap();
+            MediaDate::from_fixed(
+                instant.with_timezone(&chrono::FixedOffset::east_opt(9 * 3600).unwrap()),
+                date.source,
+            )
+        };
+
+        rewrite_photo_dates(&path, tokyo, false).unwrap();
+        let date = read_photo_creation_date(&path).unwrap();
+        assert_eq!(date.to_string(), "2024-06-03T23:00:00+09:00");
+    }
+
+    /// Builds a little endian TIFF header with one first IFD holding `entries`, as tag and
+    /// SHORT value, starting at `first_ifd`.
+    fn tiff_header(first_ifd: u32, entries: &[(u16, u16)]) -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&first_ifd.to_le_bytes());
+        tiff.resize(first_ifd as usize, 0);
+        tiff.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for &(tag, value) in entries {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&3u16.to_le_bytes());
+            tiff.extend_from_slice(&1u32.to_le_bytes());
+            tiff.extend_from_slice(&u32::from(value).to_le_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff
+    }
+
+    #[test]
+    fn leaves_camera_raws_alone() {
+        let mut cr2 = tiff_header(16, &[]);
+        cr2[8..10].copy_from_slice(b"CR");
+        assert!(locate_tiff(&cr2).is_none());
+        assert!(locate_tiff(&tiff_header(8, &[(SUB_IFDS, 0)])).is_none());
+        assert!(locate_tiff(&tiff_header(8, &[(DNG_VERSION, 1)])).is_none());
+        assert!(locate_tiff(&tiff_header(8, &[(0x0100, 1)])).is_some());
+
+        let dir = tempfile::tempdir().unwrap();
+        let jpeg = write_jpeg(
+            dir.path(),
+            &[(ExifTag::DateTimeOriginal, "2024:10:22 21:15:30")],
+        );
+        for name in ["DSC_0001.NEF", "IMG_0001.CR2"] {
+            let path = dir.path().join(name);
+            fs::copy(&jpeg, &path).unwrap();
+            let before = fs::read(&path).unwrap();
+
+            assert!(matches!(
+                rewrite_photo_dates(&path, shift, false),
+                Err(MediaInfoError::Unsupported(_))
+            ));
+            assert_eq!(fs::read(&path).unwrap(), before);
+        }
+    }
+
+    #[test]
+    fn dry_runs_leave_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_jpeg(
+            dir.path(),
+            &[(ExifTag::DateTimeOriginal, "2024:10:22 21:15:30")],
+        );
+        let before = fs::read(&path).unwrap();
+
+        assert_eq!(rewrite_photo_dates(&path, shift, true).unwrap().len(), 1);
+        assert_eq!(fs::read(&path).unwrap(), before);
+        assert!(
+            rewrite_photo_dates(&path, |date| date, false)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
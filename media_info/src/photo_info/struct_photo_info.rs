@@ -1,10 +1,11 @@
-use exif::Reader;
-use std::fs::File;
+use super::read_photo;
+use crate::error::MediaInfoError;
 use std::path::Path;
 
 pub struct PhotoInfo {
     pub make: String,
     pub model: String,
+    pub body_serial_number: String,
     pub date_time: String,
     pub exposure_time: String,
     pub f_number: String,
@@ -37,6 +38,7 @@ pub struct PhotoInfo {
 }
 
 /// Creates a new `PhotoInfo` instance by extracting EXIF metadata from the specified file path.
+/// For TIFF and camera RAW photos, the pixel dimensions come from the sensor tags.
 ///
 /// # Arguments
 ///
@@ -45,12 +47,12 @@ pub struct PhotoInfo {
 /// # Returns
 ///
 /// * `Ok(PhotoInfo)` containing the extracted metadata if successful.
-/// * `Err(String)` with an error message if the file does not exist or cannot be read.
+/// * `Err(MediaInfoError)` if the file does not exist or cannot be read.
 ///
 /// # Errors
 ///
-/// Returns an error if the file at the given path does not exist. Panics if the file cannot be opened
-/// or if EXIF data cannot be read from the file.
+/// Returns `NotFound` if the file does not exist, `NoMetadata` if it has no EXIF data, and
+/// `Corrupt` or `Io` if it cannot be read.
 ///
 /// # Example
 ///
@@ -60,15 +62,8 @@ pub struct PhotoInfo {
 /// let photo_info = PhotoInfo::new(Path::new("../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg")).unwrap();
 /// ```
 impl PhotoInfo {
-    pub fn new(path: &Path) -> Result<Self, String> {
-        if !path.exists() {
-            return Err(format!("File does not exist: {:?}", path));
-        }
-
-        let file = File::open(path).expect("could not open photo");
-        let mut bufreader = std::io::BufReader::new(&file);
-        let exifreader = Reader::new();
-        let exif = exifreader.read_from_container(&mut bufreader).unwrap();
+    pub fn new(path: &Path) -> Result<Self, MediaInfoError> {
+        let (exif, sensor_size) = read_photo(path)?;
 
         macro_rules! get_exif_field {
             ($tag:expr) => {
@@ -82,6 +77,7 @@ impl PhotoInfo {
         Ok(PhotoInfo {
             make: get_exif_field!(exif::Tag::Make),
             model: get_exif_field!(exif::Tag::Model),
+            body_serial_number: get_exif_field!(exif::Tag::BodySerialNumber),
             date_time: get_exif_field!(exif::Tag::DateTime),
             exposure_time: get_exif_field!(exif::Tag::ExposureTime),
             f_number: get_exif_field!(exif::Tag::FNumber),
@@ -92,8 +88,14 @@ impl PhotoInfo {
             exposure_bias_value: get_exif_field!(exif::Tag::ExposureBiasValue),
             focal_length: get_exif_field!(exif::Tag::FocalLength),
             color_space: get_exif_field!(exif::Tag::ColorSpace),
-            pixel_x_dimension: get_exif_field!(exif::Tag::PixelXDimension),
-            pixel_y_dimension: get_exif_field!(exif::Tag::PixelYDimension),
+            pixel_x_dimension: sensor_size.map_or_else(
+                || get_exif_field!(exif::Tag::PixelXDimension),
+                |(width, _)| width.to_string(),
+            ),
+            pixel_y_dimension: sensor_size.map_or_else(
+                || get_exif_field!(exif::Tag::PixelYDimension),
+                |(_, height)| height.to_string(),
+            ),
             lens_make: get_exif_field!(exif::Tag::LensMake),
             lens_model: get_exif_field!(exif::Tag::LensModel),
             gps_latitude_ref: get_exif_field!(exif::Tag::GPSLatitudeRef),
@@ -0,0 +1,112 @@
+/// The TIFF field type of ASCII strings.
+pub(super) const ASCII: u16 = 2;
+/// The TIFF field type of 16 bit unsigned integers.
+pub(super) const SHORT: u16 = 3;
+/// The TIFF field type of 32 bit unsigned integers.
+pub(super) const LONG: u16 = 4;
+
+/// One 12 byte IFD entry.
+pub(super) struct Entry {
+    pub tag: u16,
+    pub kind: u16,
+    pub count: usize,
+    /// Where the value is stored, or the offset of a value longer than four bytes.
+    pub value: usize,
+}
+
+/// Reads the integers and IFD entries of a TIFF structure in its byte order.
+pub(super) struct Tiff<'a> {
+    bytes: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    /// Reads the byte order of a TIFF header. The magic number after it is not checked,
+    /// since RAW formats such as ORF and RW2 replace the usual 42.
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        Self::with_header_at(bytes, 0)
+    }
+
+    /// Reads the byte order from `header` on, for structures such as maker notes whose
+    /// offsets count from before their byte order.
+    pub fn with_header_at(bytes: &'a [u8], header: usize) -> Option<Self> {
+        let little_endian = match bytes.get(header..header + 2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(Tiff {
+            bytes,
+            little_endian,
+        })
+    }
+
+    pub fn little_endian(&self) -> bool {
+        self.little_endian
+    }
+
+    pub fn u16_at(&self, position: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.bytes.get(position..position + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    pub fn u32_at(&self, position: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.bytes.get(position..position + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// Where the first IFD starts.
+    pub fn first_ifd(&self) -> Option<usize> {
+        self.u32_at(4).map(|offset| offset as usize)
+    }
+
+    /// Reads the entries of the IFD starting at `ifd`.
+    pub fn entries(&self, ifd: usize) -> Option<Vec<Entry>> {
+        (0..self.u16_at(ifd)? as usize)
+            .map(|entry| {
+                let entry = ifd + 2 + entry * 12;
+                Some(Entry {
+                    tag: self.u16_at(entry)?,
+                    kind: self.u16_at(entry + 2)?,
+                    count: self.u32_at(entry + 4)? as usize,
+                    value: entry + 8,
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the first number of a SHORT or LONG entry.
+    pub fn number(&self, entry: &Entry) -> Option<u32> {
+        match entry.kind {
+            SHORT => self.u16_at(entry.value).map(u32::from),
+            LONG => self.u32_at(entry.value),
+            _ => None,
+        }
+    }
+
+    /// Reads every number of a LONG entry, such as the offsets of sub-IFDs.
+    pub fn longs(&self, entry: &Entry) -> Vec<u32> {
+        if entry.kind != LONG {
+            return Vec::new();
+        }
+        let start = match entry.count {
+            0..=1 => entry.value,
+            _ => match self.u32_at(entry.value) {
+                Some(offset) => offset as usize,
+                None => return Vec::new(),
+            },
+        };
+
+        (0..entry.count)
+            .map_while(|index| self.u32_at(start + index * 4))
+            .collect()
+    }
+}
@@ -0,0 +1,193 @@
+use crate::media_date::MediaDate;
+use chrono::{FixedOffset, Local, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+use std::sync::OnceLock;
+use tzf_rs::DefaultFinder;
+
+/// The time zone UTC-only dates are placed in when a file does not say where it was
+/// recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeZoneSetting {
+    /// This machine's time zone.
+    #[default]
+    Local,
+    /// An IANA time zone, whose offset follows daylight saving time.
+    Named(Tz),
+    /// A fixed UTC offset.
+    Fixed(FixedOffset),
+}
+
+impl TimeZoneSetting {
+    /// Parses `local`, an IANA name such as `America/Denver`, `UTC`, or a fixed offset such
+    /// as `+02:00`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("local") {
+            return Ok(TimeZoneSetting::Local);
+        }
+        if let Ok(time_zone) = value.parse::<Tz>() {
+            return Ok(TimeZoneSetting::Named(time_zone));
+        }
+
+        value.parse::<FixedOffset>().map(TimeZoneSetting::Fixed).map_err(|_| {
+            format!(
+                "Unknown time zone: {} (expected local, an IANA name such as America/Denver, or an offset such as +02:00)",
+                value
+            )
+        })
+    }
+
+    /// Moves a UTC-only date into this time zone.
+    pub fn localize(&self, date: MediaDate) -> MediaDate {
+        match self {
+            TimeZoneSetting::Local => date.in_time_zone(&Local),
+            TimeZoneSetting::Named(time_zone) => date.in_time_zone(time_zone),
+            TimeZoneSetting::Fixed(offset) => date.in_time_zone(offset),
+        }
+    }
+
+    /// The UTC offset of a local time in this time zone. A time repeated when clocks go
+    /// back takes the earlier offset; a time skipped when they go forward has none.
+    pub fn offset_at(&self, local: &NaiveDateTime) -> Option<FixedOffset> {
+        match self {
+            TimeZoneSetting::Local => Local.offset_from_local_datetime(local).earliest(),
+            TimeZoneSetting::Named(time_zone) => time_zone
+                .offset_from_local_datetime(local)
+                .earliest()
+                .map(|offset| offset.fix()),
+            TimeZoneSetting::Fixed(offset) => Some(*offset),
+        }
+    }
+}
+
+/// Looks up the time zone at a location in the offline time zone boundary dataset.
+/// Returns `None` at sea and wherever no time zone is found.
+pub fn time_zone_at(latitude: f64, longitude: f64) -> Option<Tz> {
+    static FINDER: OnceLock<DefaultFinder> = OnceLock::new();
+
+    FINDER
+        .get_or_init(DefaultFinder::new)
+        .get_tz_name(longitude, latitude)
+        .parse()
+        .ok()
+}
+
+/// Moves a UTC-only date into the local time it was recorded in: the time zone at
+/// `location` when the file has one, otherwise `fallback`. Dates that carry their own offset
+/// or are already in local time are returned as they are.
+///
+/// # Example
+///
+/// ```rust
+/// use media_info::{DateSource, MediaDate, TimeZoneSetting, resolve_time_zone};
+///
+/// let utc = "2024-10-21T01:30:00".parse().unwrap();
+/// let date = MediaDate::from_utc(utc, DateSource::FfmpegCreationTime);
+/// let fallback = TimeZoneSetting::parse("-06:00").unwrap();
+///
+/// let local = resolve_time_zone(date, None, &fallback);
+/// assert_eq!(local.to_string(), "2024-10-20T19:30:00-06:00");
+/// ```
+pub fn resolve_time_zone(
+    date: MediaDate,
+    location: Option<(f64, f64)>,
+    fallback: &TimeZoneSetting,
+) -> MediaDate {
+    if !date.utc_only {
+        return date;
+    }
+
+    match location.and_then(|(latitude, longitude)| time_zone_at(latitude, longitude)) {
+        Some(time_zone) => date.in_time_zone(&time_zone),
+        None => fallback.localize(date),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media_date::DateSource;
+
+    fn evening_in_utc() -> MediaDate {
+        MediaDate::from_utc(
+            "2024-10-21T01:30:00".parse().unwrap(),
+            DateSource::FfmpegCreationTime,
+        )
+    }
+
+    #[test]
+    fn parses_local_named_and_fixed_zones() {
+        assert_eq!(TimeZoneSetting::parse("local"), Ok(TimeZoneSetting::Local));
+        assert_eq!(
+            TimeZoneSetting::parse("America/Denver"),
+            Ok(TimeZoneSetting::Named(chrono_tz::America::Denver))
+        );
+        assert_eq!(
+            TimeZoneSetting::parse("+05:30"),
+            Ok(TimeZoneSetting::Fixed(
+                FixedOffset::east_opt(5 * 3600 + 1800).unwrap()
+            ))
+        );
+        assert!(TimeZoneSetting::parse("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn named_zones_follow_daylight_saving_time() {
+        let denver = TimeZoneSetting::parse("America/Denver").unwrap();
+
+        assert_eq!(
+            denver.localize(evening_in_utc()).to_string(),
+            "2024-10-20T19:30:00-06:00"
+        );
+        let winter = MediaDate::from_utc(
+            "2024-12-21T01:30:00".parse().unwrap(),
+            DateSource::FfmpegCreationTime,
+        );
+        assert_eq!(
+            denver.localize(winter).to_string(),
+            "2024-12-20T18:30:00-07:00"
+        );
+    }
+
+    #[test]
+    fn offsets_of_local_times_follow_daylight_saving_time() {
+        let denver = TimeZoneSetting::parse("America/Denver").unwrap();
+        let offset = |local: &str| denver.offset_at(&local.parse().unwrap());
+
+        assert_eq!(
+            offset("2024-07-01T12:00:00"),
+            FixedOffset::west_opt(6 * 3600)
+        );
+        assert_eq!(
+            offset("2024-12-01T12:00:00"),
+            FixedOffset::west_opt(7 * 3600)
+        );
+        assert_eq!(offset("2024-03-10T02:30:00"), None);
+    }
+
+    #[test]
+    fn gps_location_comes_before_the_fallback() {
+        let fallback = TimeZoneSetting::parse("UTC").unwrap();
+
+        let date = resolve_time_zone(evening_in_utc(), Some((39.7392, -104.9903)), &fallback);
+        assert_eq!(date.to_string(), "2024-10-20T19:30:00-06:00");
+        assert!(!date.utc_only);
+
+        let date = resolve_time_zone(evening_in_utc(), None, &fallback);
+        assert_eq!(date.to_string(), "2024-10-21T01:30:00+00:00");
+    }
+
+    #[test]
+    fn embedded_offsets_are_kept() {
+        let recorded = MediaDate::from_fixed(
+            "2024-10-20T19:30:00-06:00".parse().unwrap(),
+            DateSource::QuickTimeCreationDate,
+        );
+        let fallback = TimeZoneSetting::parse("Asia/Tokyo").unwrap();
+
+        assert_eq!(
+            resolve_time_zone(recorded, Some((35.68, 139.69)), &fallback),
+            recorded
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use crate::media_date::{DatePrecision, DateSource, MediaDate};
+use chrono::{FixedOffset, NaiveDate};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// How much of a clip is searched for the MDPM block, which camcorders write with the
+/// first frames.
+const SCAN_LEN: u64 = 4 * 1024 * 1024;
+
+/// The sync byte every transport stream packet starts with.
+const SYNC: u8 = 0x47;
+const PACKET_LEN: usize = 188;
+
+/// The UUID of the H.264 user data SEI message AVCHD camcorders keep their recording
+/// details in, followed by `MDPM`.
+const MDPM: [u8; 20] = [
+    0x17, 0xee, 0x8c, 0x60, 0xf8, 0x4d, 0x11, 0xd9, 0x8c, 0xd6, 0x08, 0x00, 0x20, 0x0c, 0x9a, 0x66,
+    b'M', b'D', b'P', b'M',
+];
+
+/// The MDPM tag with the time zone, year and month of the recording.
+const DATE: u8 = 0x18;
+/// The MDPM tag with the day and time of the recording.
+const TIME: u8 = 0x19;
+
+/// The size of the packets of a transport stream: 188 bytes, or 192 in the `.MTS` and
+/// `.M2TS` clips of AVCHD, which put a 4 byte time stamp before each packet.
+fn packet_size(bytes: &[u8]) -> Option<usize> {
+    [PACKET_LEN, PACKET_LEN + 4].into_iter().find(|&size| {
+        (0..3).all(|packet| bytes.get(size - PACKET_LEN + packet * size) == Some(&SYNC))
+    })
+}
+
+/// Joins the payloads of the packets of each stream, so a message split across packets
+/// reads as one.
+fn payloads(bytes: &[u8], size: usize) -> HashMap<u16, Vec<u8>> {
+    let mut payloads: HashMap<u16, Vec<u8>> = HashMap::new();
+
+    for packet in bytes.chunks_exact(size) {
+        let packet = &packet[size - PACKET_LEN..];
+        if packet[0] != SYNC || packet[3] & 0x10 == 0 {
+            continue;
+        }
+        let pid = u16::from_be_bytes([packet[1] & 0x1f, packet[2]]);
+        let start = match packet[3] & 0x20 {
+            0 => 4,
+            _ => 5 + packet[4] as usize,
+        };
+        if let Some(payload) = packet.get(start..) {
+            payloads.entry(pid).or_default().extend_from_slice(payload);
+        }
+    }
+
+    payloads
+}
+
+/// Drops the emulation prevention bytes H.264 puts after every two zero bytes.
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+
+    for &byte in data {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        unescaped.push(byte);
+    }
+
+    unescaped
+}
+
+fn bcd(byte: u8) -> Option<u32> {
+    let (tens, ones) = (byte >> 4, byte & 0x0f);
+    (tens < 10 && ones < 10).then_some(tens as u32 * 10 + ones as u32)
+}
+
+/// Reads the UTC offset in the first byte of the MDPM date. A set top bit means no time
+/// zone was set; then come a daylight saving flag, the sign, the hours and a half hour
+/// flag. Daylight saving time is an hour ahead of the time zone.
+fn time_zone(byte: u8) -> Option<FixedOffset> {
+    if byte & 0x80 != 0 {
+        return None;
+    }
+
+    let mut minutes = ((byte >> 1) & 0x0f) as i32 * 60 + (byte & 0x01) as i32 * 30;
+    if byte & 0x20 != 0 {
+        minutes = -minutes;
+    }
+    if byte & 0x40 != 0 {
+        minutes += 60;
+    }
+    FixedOffset::east_opt(minutes * 60)
+}
+
+/// Reads the recording date of an MDPM block: a count, then that many entries of a tag
+/// and four bytes, with the date and time in BCD.
+fn mdpm_date(block: &[u8]) -> Option<MediaDate> {
+    let count = *block.first()? as usize;
+    let entries: HashMap<u8, &[u8]> = block
+        .get(1..1 + count * 5)?
+        .chunks_exact(5)
+        .map(|entry| (entry[0], &entry[1..]))
+        .collect();
+    let (date, time) = (entries.get(&DATE)?, entries.get(&TIME)?);
+
+    let naive = NaiveDate::from_ymd_opt(
+        (bcd(date[1])? * 100 + bcd(date[2])?) as i32,
+        bcd(date[3])?,
+        bcd(time[0])?,
+    )?
+    .and_hms_opt(bcd(time[1])?, bcd(time[2])?, bcd(time[3])?)?;
+    let recorded = MediaDate::new(naive, DatePrecision::Second, DateSource::AvchdRecordingDate);
+
+    match time_zone(date[0]) {
+        Some(offset) => Some(recorded.with_offset(offset)),
+        None => Some(recorded),
+    }
+}
+
+/// Reads the date AVCHD camcorders write into the MDPM block of the H.264 stream of a
+/// transport stream clip, in the local time of the recording and with the camcorder's time
+/// zone when one was set.
+pub(super) fn read_mdpm_date(path: &Path) -> Option<MediaDate> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(SCAN_LEN)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    let size = packet_size(&bytes)?;
+
+    payloads(&bytes, size).into_values().find_map(|payload| {
+        let payload = unescape(&payload);
+        let start = payload
+            .windows(MDPM.len())
+            .position(|window| window == MDPM)?;
+        mdpm_date(&payload[start + MDPM.len()..])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An MDPM block recorded on 2009-05-17 at 14:32:05, with time zone byte `zone`.
+    fn mdpm_block(zone: u8) -> Vec<u8> {
+        let mut block = MDPM.to_vec();
+        block.extend_from_slice(&[3, 0xe0, 0x01, 0x03, 0x00, 0x00]);
+        block.extend_from_slice(&[DATE, zone, 0x20, 0x09, 0x05]);
+        block.extend_from_slice(&[TIME, 0x17, 0x14, 0x32, 0x05]);
+        block
+    }
+
+    /// An M2TS clip whose video stream carries `data`, split over packets of 40 bytes so it
+    /// has to be joined, after a packet of another stream.
+    fn m2ts(data: &[u8]) -> Vec<u8> {
+        let packet = |pid: u16, payload: &[u8]| {
+            let mut packet = vec![0, 0, 0, 0, SYNC, 0x40 | (pid >> 8) as u8, pid as u8, 0x30];
+            // Adaptation field stuffing fills the packet up to 188 bytes
+            let stuffing = PACKET_LEN - 5 - payload.len();
+            packet.push(stuffing as u8);
+            packet.extend(std::iter::repeat_n(0xff, stuffing));
+            packet.extend_from_slice(payload);
+            packet
+        };
+
+        let mut clip = packet(0x100, b"audio");
+        for chunk in data.chunks(40) {
+            clip.extend(packet(0x1011, chunk));
+        }
+        clip
+    }
+
+    #[test]
+    fn reads_the_mdpm_date_of_an_avchd_clip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("00000.MTS");
+        let mut sei = vec![0, 0, 1, 6, 5, 0x40];
+        sei.extend(mdpm_block(0x12));
+        std::fs::write(&path, m2ts(&sei)).unwrap();
+
+        let date = read_mdpm_date(&path).unwrap();
+        assert_eq!(date.to_string(), "2009-05-17T14:32:05+09:00");
+        assert_eq!(date.source, DateSource::AvchdRecordingDate);
+
+        std::fs::write(&path, m2ts(&[0; 100])).unwrap();
+        assert!(read_mdpm_date(&path).is_none());
+    }
+
+    #[test]
+    fn mdpm_time_zones() {
+        let offset = |zone| mdpm_date(&mdpm_block(zone)[MDPM.len()..]).unwrap().offset;
+
+        assert_eq!(offset(0x80), None);
+        assert_eq!(offset(0x2c), FixedOffset::west_opt(6 * 3600));
+        assert_eq!(offset(0x6e), FixedOffset::west_opt(6 * 3600));
+        assert_eq!(offset(0x0b), FixedOffset::east_opt(5 * 3600 + 1800));
+    }
+
+    #[test]
+    fn drops_emulation_prevention_bytes() {
+        assert_eq!(
+            unescape(&[1, 0, 0, 3, 1, 0, 0, 3, 0, 3]),
+            [1, 0, 0, 1, 0, 0, 0, 3]
+        );
+    }
+}
@@ -1,63 +1,327 @@
+use crate::error::MediaInfoError;
+use crate::media_date::{DatePrecision, DateSource, MediaDate};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
 use ffmpeg_next as ffmpeg;
-use fs_metadata::file_created;
 use std::path::Path;
 
+mod avchd;
 mod struct_video_info;
+mod tmcd;
 
 pub use struct_video_info::VideoInfo;
 
-pub fn read_video_dimensions(path: &Path) -> Result<(u32, u32), String> {
+/// Opens a video with FFmpeg.
+///
+/// # Panics
+///
+/// Panics if FFmpeg fails to initialize.
+pub(crate) fn open_video(path: &Path) -> Result<ffmpeg::format::context::Input, MediaInfoError> {
     if !path.exists() {
-        return Err(format!("File does not exist: {:?}", path));
+        return Err(MediaInfoError::NotFound(path.to_path_buf()));
     }
 
     ffmpeg::init().expect("could not initialize ffmpeg");
 
-    match ffmpeg::format::input(path) {
-        Ok(context) => {
-            for stream in context.streams() {
-                let codec_params = stream.parameters();
-                if let Ok(decoder) = ffmpeg::codec::context::Context::from_parameters(codec_params)
-                    && let Ok(video) = decoder.decoder().video()
-                {
-                    return Ok((video.width(), video.height()));
-                }
-            }
-            Err("No video stream found".to_string())
+    ffmpeg::format::input(path)
+        .map_err(|e| MediaInfoError::Corrupt(format!("Error reading video {:?}: {}", path, e)))
+}
+
+pub fn read_video_dimensions(path: &Path) -> Result<(u32, u32), MediaInfoError> {
+    let context = open_video(path)?;
+
+    for stream in context.streams() {
+        let codec_params = stream.parameters();
+        if let Ok(decoder) = ffmpeg::codec::context::Context::from_parameters(codec_params)
+            && let Ok(video) = decoder.decoder().video()
+        {
+            return Ok((video.width(), video.height()));
         }
-        Err(e) => Err(format!("Error reading video: {}", e)),
     }
+
+    Err(MediaInfoError::NoMetadata(
+        "No video stream found".to_string(),
+    ))
 }
 
-pub fn read_video_creation_date(path: &Path) -> Result<String, String> {
-    if !path.exists() {
-        return Err(format!("File does not exist: {:?}", path));
+/// QuickTime and vendor keys that hold the local time of the recording, in order of
+/// preference.
+const LOCAL_DATE_KEYS: [(&str, DateSource); 2] = [
+    (
+        "com.apple.quicktime.creationdate",
+        DateSource::QuickTimeCreationDate,
+    ),
+    // The `©day` user data atom written by many cameras
+    ("date", DateSource::QuickTimeDate),
+];
+
+/// Matroska and WebM tags that hold the local time of the recording.
+const MATROSKA_DATE_KEYS: [(&str, DateSource); 1] =
+    [("DATE_RECORDED", DateSource::MatroskaDateRecorded)];
+
+/// The containers whose dates are read by rules of their own, told apart by the FFmpeg
+/// demuxer that opened the video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    /// MP4, MOV and 3GP, and any container without rules of its own
+    QuickTime,
+    /// Matroska and WebM
+    Matroska,
+    Avi,
+    /// MPEG transport streams, such as the `.MTS` and `.M2TS` clips of AVCHD camcorders
+    MpegTs,
+}
+
+impl Container {
+    fn of(context: &ffmpeg::format::context::Input) -> Self {
+        match context.format().name().split(',').next() {
+            Some("matroska") => Container::Matroska,
+            Some("avi") => Container::Avi,
+            Some("mpegts") => Container::MpegTs,
+            _ => Container::QuickTime,
+        }
     }
 
-    ffmpeg::init().expect("could not initialize ffmpeg");
+    fn local_date_keys(self) -> &'static [(&'static str, DateSource)] {
+        match self {
+            Container::Matroska => &MATROSKA_DATE_KEYS,
+            _ => &LOCAL_DATE_KEYS,
+        }
+    }
+}
+
+/// Parses a local recording date such as `2024-10-20T18:04:11-0600`, keeping its UTC
+/// offset when it has one.
+fn parse_local_date(value: &str, source: DateSource) -> Option<MediaDate> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z"))
+    {
+        return Some(MediaDate::from_fixed(date_time, source));
+    }
 
-    match ffmpeg::format::input(path) {
-        Ok(context) => {
-            let video_metadata = context.metadata().to_owned();
-            let creation_date: String = video_metadata
-                .get("creation_time")
-                .unwrap_or(&"".to_string())
-                .to_string();
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f"))
+    {
+        return Some(MediaDate::new(date_time, DatePrecision::Second, source));
+    }
 
-            Ok(creation_date)
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .map(|date| MediaDate::from_date(date, source))
+}
+
+/// Parses an FFmpeg `creation_time`, which is ISO 8601 in UTC for MP4 and QuickTime and
+/// says nothing of the time zone the video was recorded in.
+fn parse_creation_time(value: &str, source: DateSource) -> Option<MediaDate> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Some(MediaDate::from_utc(date_time.naive_utc(), source));
+    }
+
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|date_time| MediaDate::from_utc(date_time, source))
+}
+
+/// Whether a date is the zero of the QuickTime (1904) or Unix (1970) epoch, which cameras
+/// without a set clock write instead of leaving the date out.
+fn is_epoch_zero(date: &MediaDate) -> bool {
+    let utc = date
+        .to_fixed()
+        .map_or(date.date_time, |date_time| date_time.naive_utc());
+    matches!((utc.year(), utc.ordinal()), (1904, 1) | (1970, 1))
+}
+
+/// Whether an offset is one a time zone uses: between -12:00 and +14:00, on the hour, the
+/// half hour or, as in Nepal and the Chatham Islands, the three quarter hour.
+fn is_zone_offset(seconds: i64) -> bool {
+    (-12 * 3600..=14 * 3600).contains(&seconds) && matches!(seconds.abs() % 3600, 0 | 1800 | 2700)
+}
+
+/// Works out the UTC offset of the camera clock from a `tmcd` timecode that runs on the
+/// time of day, such as `18:04:11:12`, by comparing it with the UTC creation time. Only
+/// timecodes whose track says they run on the time of day are passed in. Timecodes that
+/// start at zero, or that do not sit within two minutes of a time zone's offset from the
+/// creation time, are ignored.
+fn timecode_offset(timecode: &str, utc: NaiveDateTime) -> Option<FixedOffset> {
+    let time = NaiveTime::parse_from_str(timecode.get(..8)?, "%H:%M:%S").ok()?;
+    if time == NaiveTime::MIN {
+        return None;
+    }
+
+    const DAY: i64 = 24 * 3600;
+    const QUARTER_HOUR: i64 = 900;
+    let difference = ((time - utc.time()).num_seconds() + DAY / 2).rem_euclid(DAY) - DAY / 2;
+    let quarters = (difference + QUARTER_HOUR / 2).div_euclid(QUARTER_HOUR);
+    if (difference - quarters * QUARTER_HOUR).abs() > 120
+        || !is_zone_offset(quarters * QUARTER_HOUR)
+    {
+        return None;
+    }
+
+    FixedOffset::east_opt((quarters * QUARTER_HOUR) as i32)
+}
+
+/// The date tags of a video's container and streams.
+#[derive(Debug, Default)]
+struct VideoDateTags {
+    /// The MDPM date of an AVCHD clip, which is read from the video stream itself.
+    recorded: Option<MediaDate>,
+    /// Values of the container's local date keys, in the same order, after the `IDIT`
+    /// date of an AVI.
+    local: Vec<(String, DateSource)>,
+    /// The container `creation_time`, then each stream's, all in UTC.
+    utc: Vec<(String, DateSource)>,
+    /// The first `timecode` of a `tmcd` track, `HH:MM:SS:FF`, when the track runs on the
+    /// time of day.
+    timecode: Option<String>,
+}
+
+impl VideoDateTags {
+    fn read(path: &Path, context: &ffmpeg::format::context::Input) -> Self {
+        let container = Container::of(context);
+        let metadata = context.metadata();
+        let mut tags = VideoDateTags::default();
+
+        if container == Container::MpegTs {
+            tags.recorded = avchd::read_mdpm_date(path);
+        }
+        for &(key, source) in container.local_date_keys() {
+            if let Some(value) = metadata.get(key).filter(|value| !value.is_empty()) {
+                tags.local.push((value.to_string(), source));
+            }
+        }
+        if let Some(value) = metadata.get("creation_time") {
+            match container {
+                // FFmpeg reads the `IDIT` date of an AVI, which cameras write in local time
+                Container::Avi => tags
+                    .local
+                    .insert(0, (value.to_string(), DateSource::AviDateTimeOriginal)),
+                _ => tags
+                    .utc
+                    .push((value.to_string(), DateSource::FfmpegCreationTime)),
+            }
+        }
+        // Free-run and record-run timecodes can start anywhere, so they say nothing about
+        // the time zone
+        let time_of_day = container == Container::QuickTime && tmcd::runs_on_time_of_day(path);
+        if time_of_day {
+            tags.timecode = metadata.get("timecode").map(str::to_string);
         }
-        Err(_) => {
-            println!("Error reading video creation date: {:?}", path);
-            println!("Falling back to file creation date");
-            // TODO: edit creation date with file creation date?
 
-            let formatted_date = file_created(path).unwrap();
+        for stream in context.streams() {
+            let stream_metadata = stream.metadata();
+            if let Some(value) = stream_metadata.get("creation_time") {
+                tags.utc
+                    .push((value.to_string(), DateSource::StreamCreationTime));
+            }
+            if time_of_day && tags.timecode.is_none() {
+                tags.timecode = stream_metadata.get("timecode").map(str::to_string);
+            }
+        }
+
+        tags
+    }
 
-            Ok(formatted_date)
+    /// The date of the recording: an AVCHD MDPM date, then a QuickTime, Matroska, AVI or
+    /// vendor date, which cameras write in local time, else the first UTC creation time,
+    /// shifted into the time zone of the camera's timecode when it has one. Epoch-zero
+    /// dates are skipped.
+    fn media_date(&self) -> Option<MediaDate> {
+        let local = self
+            .recorded
+            .into_iter()
+            .chain(
+                self.local
+                    .iter()
+                    .filter_map(|(value, source)| parse_local_date(value, *source)),
+            )
+            .find(|date| !is_epoch_zero(date));
+        if local.is_some() {
+            return local;
+        }
+
+        let created = self
+            .utc
+            .iter()
+            .filter_map(|(value, source)| parse_creation_time(value, *source))
+            .find(|date| !is_epoch_zero(date))?;
+        match self
+            .timecode
+            .as_deref()
+            .and_then(|timecode| timecode_offset(timecode, created.date_time))
+        {
+            Some(offset) => Some(created.in_time_zone(&offset)),
+            None => Some(created),
         }
     }
 }
 
+/// Reads the date a video was recorded. The MDPM date of AVCHD clips, QuickTime
+/// `creationdate` and vendor `©day` dates, Matroska `DATE_RECORDED` and AVI `IDIT` dates,
+/// which are in the local time of the recording, are preferred, then the container and
+/// stream `creation_time`. Those are UTC and are shifted into the camera's time zone when a
+/// time-of-day `tmcd` timecode gives it away, or else returned `utc_only` for
+/// [`resolve_time_zone`](crate::resolve_time_zone) to place.
+pub fn read_video_creation_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    let context = open_video(path)?;
+
+    VideoDateTags::read(path, &context)
+        .media_date()
+        .ok_or_else(|| MediaInfoError::NoMetadata(format!("No creation date in {:?}", path)))
+}
+
+/// The keys phones and cameras store a video's ISO 6709 location under.
+const LOCATION_KEYS: [&str; 2] = ["com.apple.quicktime.location.ISO6709", "location"];
+
+/// Reads where a video was recorded, as latitude and longitude in degrees.
+pub fn read_video_location(path: &Path) -> Result<(f64, f64), MediaInfoError> {
+    let context = open_video(path)?;
+    let metadata = context.metadata();
+
+    LOCATION_KEYS
+        .iter()
+        .filter_map(|key| metadata.get(key))
+        .find_map(parse_iso6709)
+        .ok_or_else(|| MediaInfoError::NoMetadata(format!("No location in {:?}", path)))
+}
+
+/// The key iPhones store the identifier shared by both halves of a Live Photo under.
+const CONTENT_IDENTIFIER_KEY: &str = "com.apple.quicktime.content.identifier";
+
+/// Reads the identifier an iPhone gives both halves of a Live Photo from the video.
+pub fn read_video_content_identifier(path: &Path) -> Result<String, MediaInfoError> {
+    let context = open_video(path)?;
+
+    context
+        .metadata()
+        .get(CONTENT_IDENTIFIER_KEY)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| MediaInfoError::NoMetadata(format!("No content identifier in {:?}", path)))
+}
+
+/// Parses the latitude and longitude of an ISO 6709 location in decimal degrees, such as
+/// `+39.7392-104.9903+1609.000/`.
+fn parse_iso6709(value: &str) -> Option<(f64, f64)> {
+    let signs: Vec<usize> = value
+        .match_indices(['+', '-'])
+        .map(|(index, _)| index)
+        .collect();
+    let latitude: f64 = value.get(*signs.first()?..*signs.get(1)?)?.parse().ok()?;
+    let longitude: f64 = value
+        .get(
+            signs[1]
+                ..signs
+                    .get(2)
+                    .copied()
+                    .unwrap_or(value.trim_end_matches('/').len()),
+        )?
+        .parse()
+        .ok()?;
+
+    ((-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude))
+        .then_some((latitude, longitude))
+}
+
 #[cfg(test)]
 pub mod video_date_read {
     use super::*;
@@ -68,7 +332,168 @@ pub mod video_date_read {
         let path = Path::new(raw_path_str);
         let creation_date = read_video_creation_date(path);
 
-        assert_eq!(creation_date.unwrap().contains("2024-10-20"), true);
+        assert_eq!(
+            creation_date.unwrap().to_string().contains("2024-10-20"),
+            true
+        );
+    }
+
+    #[test]
+    fn creation_time_keeps_its_utc_offset() {
+        let date = parse_creation_time(
+            "2024-10-20T18:04:11.000000Z",
+            DateSource::FfmpegCreationTime,
+        )
+        .unwrap();
+
+        assert_eq!(date.to_string(), "2024-10-20T18:04:11+00:00");
+        assert!(date.utc_only);
+        assert!(parse_creation_time("yesterday", DateSource::FfmpegCreationTime).is_none());
+    }
+
+    fn tags(local: &[&str], utc: &[&str], timecode: Option<&str>) -> VideoDateTags {
+        VideoDateTags {
+            recorded: None,
+            local: local
+                .iter()
+                .map(|value| (value.to_string(), DateSource::QuickTimeCreationDate))
+                .collect(),
+            utc: utc
+                .iter()
+                .map(|value| (value.to_string(), DateSource::StreamCreationTime))
+                .collect(),
+            timecode: timecode.map(str::to_string),
+        }
+    }
+
+    fn mountain_time() -> FixedOffset {
+        FixedOffset::west_opt(6 * 3600).unwrap()
+    }
+
+    #[test]
+    fn quicktime_creation_date_is_preferred_in_local_time() {
+        let date = tags(
+            &["2024-10-20T18:04:11-0600"],
+            &["2024-10-21T00:04:11.000000Z"],
+            None,
+        )
+        .media_date()
+        .unwrap();
+
+        assert_eq!(date.to_string(), "2024-10-20T18:04:11-06:00");
+        assert_eq!(date.source, DateSource::QuickTimeCreationDate);
+        assert!(!date.utc_only);
+    }
+
+    #[test]
+    fn creation_times_stay_in_utc_until_placed_in_a_time_zone() {
+        let date = tags(&[], &["2024-10-21T01:30:00.000000Z"], None)
+            .media_date()
+            .unwrap();
+        assert!(date.utc_only);
+        assert_eq!(date.to_string(), "2024-10-21T01:30:00+00:00");
+
+        let local = date.in_time_zone(&mountain_time());
+        assert_eq!(local.to_string(), "2024-10-20T19:30:00-06:00");
+    }
+
+    #[test]
+    fn epoch_zero_dates_are_skipped() {
+        let date = tags(
+            &["1904-01-01T00:00:00Z"],
+            &["1970-01-01T00:00:00.000000Z", "2024-10-20T18:04:11Z"],
+            None,
+        )
+        .media_date()
+        .unwrap();
+
+        assert_eq!(date.to_string(), "2024-10-20T18:04:11+00:00");
+        assert!(
+            tags(&[], &["1904-01-01 00:00:00"], None)
+                .media_date()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn matroska_and_avi_dates_are_in_local_time() {
+        for (value, source) in [
+            ("2024-10-20 18:04:11.250", DateSource::MatroskaDateRecorded),
+            ("2008-03-03 09:44:56", DateSource::AviDateTimeOriginal),
+        ] {
+            let date = VideoDateTags {
+                local: vec![(value.to_string(), source)],
+                utc: vec![(
+                    "2024-10-21T01:30:00Z".to_string(),
+                    DateSource::FfmpegCreationTime,
+                )],
+                ..Default::default()
+            }
+            .media_date()
+            .unwrap();
+
+            assert_eq!(date.date_time.format("%F %T").to_string(), value[..19]);
+            assert_eq!(date.source, source);
+            assert!(!date.utc_only);
+        }
+    }
+
+    #[test]
+    fn avchd_recording_date_comes_first() {
+        let recorded = MediaDate::new(
+            NaiveDate::from_ymd_opt(2009, 5, 17)
+                .unwrap()
+                .and_hms_opt(14, 32, 5)
+                .unwrap(),
+            DatePrecision::Second,
+            DateSource::AvchdRecordingDate,
+        );
+        let mut clip = tags(&["2024-10-20T18:04:11-0600"], &[], None);
+        clip.recorded = Some(recorded);
+
+        assert_eq!(clip.media_date(), Some(recorded));
+    }
+
+    #[test]
+    fn time_of_day_timecode_gives_the_camera_time_zone() {
+        let date = tags(&[], &["2024-10-21T01:30:05Z"], Some("17:30:00:12"))
+            .media_date()
+            .unwrap();
+        assert_eq!(date.to_string(), "2024-10-20T17:30:05-08:00");
+        assert!(!date.utc_only);
+
+        // Record-run timecodes say nothing about the time of day, and an offset no time zone
+        // uses, such as -01:15, is taken for chance
+        for timecode in ["00:00:00:00", "01:12:44:03", "00:15:00:00"] {
+            let date = tags(&[], &["2024-10-21T01:30:05Z"], Some(timecode))
+                .media_date()
+                .unwrap();
+            assert!(date.utc_only, "{}", timecode);
+        }
+    }
+
+    #[test]
+    fn zone_offsets_are_real_ones() {
+        assert!(is_zone_offset(-8 * 3600));
+        assert!(is_zone_offset(5 * 3600 + 2700));
+        assert!(is_zone_offset(-(3 * 3600 + 1800)));
+        assert!(is_zone_offset(14 * 3600));
+        assert!(!is_zone_offset(-(3600 + 900)));
+        assert!(!is_zone_offset(-13 * 3600));
+    }
+
+    #[test]
+    fn parses_iso6709_locations() {
+        assert_eq!(
+            parse_iso6709("+39.7392-104.9903+1609.000/"),
+            Some((39.7392, -104.9903))
+        );
+        assert_eq!(
+            parse_iso6709("-33.8688+151.2093/"),
+            Some((-33.8688, 151.2093))
+        );
+        assert_eq!(parse_iso6709("+99.0000+000.0000/"), None);
+        assert_eq!(parse_iso6709(""), None);
     }
 
     #[test]
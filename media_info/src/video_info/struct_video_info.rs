@@ -1,9 +1,12 @@
-use ffmpeg_next::{self as ffmpeg};
+use super::{VideoDateTags, open_video, parse_iso6709};
+use crate::error::MediaInfoError;
+use crate::time_zone::{TimeZoneSetting, resolve_time_zone};
 use std::path::Path;
 
 #[derive(Debug)]
 pub struct VideoInfo {
     pub creation_date: String,
+    pub container: String,
     pub major_brand: String,
     pub minor_version: String,
     pub compatible_brands: String,
@@ -27,22 +30,28 @@ pub struct VideoInfo {
 /// # Returns
 ///
 /// * `Ok(VideoInfo)` containing the extracted metadata if successful.
-/// * `Err(String)` with an error message if the file does not exist or if metadata extraction fails.
+/// * `Err(MediaInfoError)` if the file does not exist or if metadata extraction fails.
 ///
 /// # Errors
 ///
-/// Returns an error if the file does not exist at the specified path or if FFmpeg fails to initialize.
+/// Returns `NotFound` if the file does not exist at the specified path, or `Corrupt` if FFmpeg
+/// cannot read it.
 ///
 /// # Metadata Extraction
 ///
+/// `creation_date` is the recording date [`read_video_creation_date`] reads, as ISO 8601,
+/// with UTC-only dates placed in the time zone of the video's location or of this machine.
+///
+/// [`read_video_creation_date`]: super::read_video_creation_date
+///
 /// Attempts to extract the following metadata fields, preferring QuickTime-specific tags when available:
-/// - `creation_date`
 /// - `location`
 /// - `make`
 /// - `model`
 /// - `software`
 ///
 /// Also extracts:
+/// - `container`, the FFmpeg demuxer that read the video, such as `matroska,webm`
 /// - `major_brand`
 /// - `minor_version`
 /// - `compatible_brands`
@@ -55,14 +64,8 @@ pub struct VideoInfo {
 ///
 /// Panics if FFmpeg fails to initialize.
 impl VideoInfo {
-    pub fn new(path: &Path) -> Result<Self, String> {
-        if !path.exists() {
-            return Err(format!("File does not exist: {:?}", path));
-        }
-
-        ffmpeg::init().expect("could not initialize ffmpeg");
-
-        let input_context = ffmpeg::format::input(path).unwrap();
+    pub fn new(path: &Path) -> Result<Self, MediaInfoError> {
+        let input_context = open_video(path)?;
         let video_metadata = input_context.metadata().to_owned();
 
         macro_rules! get_video_metadata {
@@ -74,12 +77,6 @@ impl VideoInfo {
             };
         }
 
-        let creation_date = if get_video_metadata!("com.apple.quicktime.creationdate").len() > 0
-        {
-            get_video_metadata!("com.apple.quicktime.creationdate")
-        } else {
-            get_video_metadata!("creation_time")
-        };
         let location = if get_video_metadata!("com.apple.quicktime.location").len() > 0 {
             get_video_metadata!("com.apple.quicktime.location")
         } else {
@@ -101,8 +98,15 @@ impl VideoInfo {
             get_video_metadata!("software")
         };
 
+        let creation_date = VideoDateTags::read(path, &input_context)
+            .media_date()
+            .map(|date| resolve_time_zone(date, parse_iso6709(&location), &TimeZoneSetting::Local))
+            .map(|date| date.to_string())
+            .unwrap_or_default();
+
         Ok(VideoInfo {
             creation_date: creation_date,
+            container: input_context.format().name().to_string(),
             major_brand: get_video_metadata!("major_brand"),
             minor_version: get_video_metadata!("minor_version"),
             compatible_brands: get_video_metadata!("compatible_brands"),
@@ -0,0 +1,86 @@
+use crate::bmff::{boxes, read_moov};
+use std::fs::File;
+use std::path::Path;
+
+/// The `tmcd` flag of a timecode that wraps at 24 hours, the way the time of day does.
+const TWENTY_FOUR_HOUR_MAX: u32 = 0x2;
+/// The `tmcd` flag of a timecode that is a plain counter rather than a time.
+const COUNTER: u32 = 0x8;
+
+/// The boxes on the way from a track to its sample descriptions.
+const STSD_PATH: [&[u8; 4]; 4] = [b"mdia", b"minf", b"stbl", b"stsd"];
+
+/// Reads the flags of the first `tmcd` sample description in the tracks of a `moov` box.
+/// A description holds the usual reserved bytes and data reference index, four more
+/// reserved bytes, then the flags.
+fn tmcd_flags(moov: &[u8]) -> Option<u32> {
+    boxes(moov)
+        .filter(|(name, _)| name == b"trak")
+        .find_map(|(_, track)| {
+            let stsd = STSD_PATH.iter().try_fold(track, |parent, name| {
+                boxes(parent)
+                    .find(|(child, _)| child == *name)
+                    .map(|(_, contents)| contents)
+            })?;
+            // The version, flags and entry count come before the descriptions
+            boxes(stsd.get(8..)?)
+                .find(|(format, _)| format == b"tmcd")
+                .and_then(|(_, description)| description.get(12..16))
+                .map(|flags| u32::from_be_bytes([flags[0], flags[1], flags[2], flags[3]]))
+        })
+}
+
+/// Whether the timecode track of a QuickTime or MP4 video runs on the time of day: its
+/// `tmcd` description wraps at 24 hours and is not a plain counter. Record-run and free-run
+/// timecodes without that flag can start at any value.
+pub(super) fn runs_on_time_of_day(path: &Path) -> bool {
+    File::open(path)
+        .ok()
+        .and_then(|mut file| read_moov(&mut file).ok().flatten())
+        .and_then(|moov| tmcd_flags(&moov))
+        .is_some_and(|flags| flags & TWENTY_FOUR_HOUR_MAX != 0 && flags & COUNTER == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(name: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+        let mut bytes = ((contents.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(name);
+        bytes.extend_from_slice(contents);
+        bytes
+    }
+
+    /// A movie with a video track and a timecode track whose `tmcd` description has `flags`.
+    fn movie(flags: u32) -> Vec<u8> {
+        let mut tmcd = vec![0; 12];
+        tmcd.extend_from_slice(&flags.to_be_bytes());
+        tmcd.extend_from_slice(&[0, 0, 0x75, 0x30, 0, 0, 0x03, 0xe9, 30, 0]);
+        let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        stsd.extend(mp4_box(b"tmcd", &tmcd));
+
+        let track = STSD_PATH
+            .iter()
+            .rev()
+            .fold(stsd, |contents, name| mp4_box(name, &contents));
+        let mut moov = mp4_box(b"trak", &mp4_box(b"tkhd", &[0; 20]));
+        moov.extend(mp4_box(b"trak", &track));
+
+        let mut file = mp4_box(b"ftyp", b"qt  \0\0\0\0qt  ");
+        file.extend(mp4_box(b"moov", &moov));
+        file.extend(mp4_box(b"mdat", &[0; 64]));
+        file
+    }
+
+    #[test]
+    fn only_time_of_day_timecodes_are_trusted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.mov");
+
+        for (flags, time_of_day) in [(0x2, true), (0x3, true), (0x0, false), (0xa, false)] {
+            std::fs::write(&path, movie(flags)).unwrap();
+            assert_eq!(runs_on_time_of_day(&path), time_of_day, "{:#x}", flags);
+        }
+    }
+}
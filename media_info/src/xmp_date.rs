@@ -0,0 +1,202 @@
+use crate::error::MediaInfoError;
+use crate::media_date::{DatePrecision, DateSource, MediaDate};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The XMP properties a sidecar is dated by, in order of preference. Like EXIF's original
+/// date before its digitized one, `photoshop:DateCreated` is when the photo was taken and
+/// `xmp:CreateDate` when it was digitized, such as the day a print was scanned.
+const XMP_DATES: [(&[u8], DateSource); 2] = [
+    (b"photoshop:DateCreated", DateSource::PhotoshopDateCreated),
+    (b"xmp:CreateDate", DateSource::XmpCreateDate),
+];
+
+/// Parses an XMP date, which is ISO 8601 from a bare year down to fractional seconds,
+/// with or without a UTC offset.
+fn parse_xmp_date(value: &str, source: DateSource) -> Option<MediaDate> {
+    let value = value.trim();
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Some(MediaDate::from_fixed(date_time, source));
+    }
+    // Times without seconds: 2024-10-22T21:15-06:00
+    let with_offset = match value.strip_suffix('Z') {
+        Some(value) => format!("{}+00:00", value),
+        None => value.to_string(),
+    };
+    if let Ok(date_time) = DateTime::parse_from_str(&with_offset, "%Y-%m-%dT%H:%M%:z") {
+        return Some(MediaDate::from_fixed(date_time, source));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"] {
+        if let Ok(date_time) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(MediaDate::new(date_time, DatePrecision::Second, source));
+        }
+    }
+
+    let (padded, precision) = match value.len() {
+        4 => (format!("{}-01-01", value), DatePrecision::Year),
+        7 => (format!("{}-01", value), DatePrecision::Month),
+        _ => (value.to_string(), DatePrecision::Day),
+    };
+    let mut date =
+        MediaDate::from_date(NaiveDate::parse_from_str(&padded, "%Y-%m-%d").ok()?, source);
+    date.precision = precision;
+    Some(date)
+}
+
+/// Collects the date properties written as attributes of an element, the way Lightroom and
+/// darktable write them on `rdf:Description`.
+fn date_attributes(element: &BytesStart, found: &mut HashMap<&'static [u8], String>) {
+    for attribute in element.attributes().flatten() {
+        if let Some((name, _)) = XMP_DATES
+            .iter()
+            .find(|(name, _)| *name == attribute.key.as_ref())
+            && let Ok(value) = attribute.unescape_value()
+        {
+            found.entry(*name).or_insert_with(|| value.to_string());
+        }
+    }
+}
+
+/// Reads the date properties of an XMP packet, whether they are written as attributes or
+/// as elements.
+fn xmp_properties(xml: &str) -> Result<HashMap<&'static [u8], String>, quick_xml::Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut found = HashMap::new();
+    let mut open: Option<&'static [u8]> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                date_attributes(&element, &mut found);
+                open = XMP_DATES
+                    .iter()
+                    .find(|(name, _)| *name == element.name().as_ref())
+                    .map(|(name, _)| *name);
+            }
+            Event::Empty(element) => date_attributes(&element, &mut found),
+            Event::Text(text) => {
+                if let Some(name) = open {
+                    found.entry(name).or_insert(text.unescape()?.to_string());
+                }
+            }
+            Event::End(_) => open = None,
+            Event::Eof => return Ok(found),
+            _ => {}
+        }
+    }
+}
+
+/// Reads the date an XMP sidecar, such as the `.xmp` files Lightroom, darktable and
+/// Capture One write next to a photo, gives the photo: its `photoshop:DateCreated`, then
+/// its `xmp:CreateDate`.
+///
+/// The date has year, month, day or second precision depending on how much the property
+/// holds, and keeps its UTC offset when it has one.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use media_info::read_xmp_date;
+/// use std::path::Path;
+///
+/// let date = read_xmp_date(Path::new("DSC_1234.xmp")).unwrap();
+/// println!("{} from {}", date, date.source);
+/// ```
+pub fn read_xmp_date(path: &Path) -> Result<MediaDate, MediaInfoError> {
+    if !path.exists() {
+        return Err(MediaInfoError::NotFound(path.to_path_buf()));
+    }
+    let xml = fs::read_to_string(path)?;
+    let properties = xmp_properties(&xml).map_err(|e| {
+        MediaInfoError::Corrupt(format!("Could not read XMP from {:?}: {}", path, e))
+    })?;
+
+    XMP_DATES
+        .iter()
+        .find_map(|(name, source)| parse_xmp_date(properties.get(name)?, *source))
+        .ok_or_else(|| MediaInfoError::NoMetadata(format!("No XMP date in {:?}", path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_xmp(xml: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DSC_1234.xmp");
+        fs::write(&path, xml).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn reads_dates_written_as_attributes() {
+        let (_dir, path) = write_xmp(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:photoshop="http://ns.adobe.com/photoshop/1.0/"
+   photoshop:DateCreated="2024-10-22T21:15:30.25-06:00"
+   xmp:CreateDate="2024-11-02"/>
+ </rdf:RDF>
+</x:xmpmeta>"#,
+        );
+
+        let date = read_xmp_date(&path).unwrap();
+        assert_eq!(date.source, DateSource::PhotoshopDateCreated);
+        assert_eq!(date.to_string(), "2024-10-22T21:15:30-06:00");
+    }
+
+    #[test]
+    fn falls_back_to_the_create_date_element() {
+        let (_dir, path) = write_xmp(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/">
+   <xmp:CreateDate>1998-07</xmp:CreateDate>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#,
+        );
+
+        let date = read_xmp_date(&path).unwrap();
+        assert_eq!(date.source, DateSource::XmpCreateDate);
+        assert_eq!(date.precision, DatePrecision::Month);
+        assert_eq!(date.to_string(), "1998-07");
+    }
+
+    #[test]
+    fn parses_xmp_dates_with_their_precision() {
+        let parse = |value| parse_xmp_date(value, DateSource::XmpCreateDate).map(|d| d.to_string());
+
+        assert_eq!(
+            parse("2024-10-22T21:15Z").as_deref(),
+            Some("2024-10-22T21:15:00+00:00")
+        );
+        assert_eq!(
+            parse("2024-10-22T21:15:30").as_deref(),
+            Some("2024-10-22T21:15:30")
+        );
+        assert_eq!(parse("2024").as_deref(), Some("2024"));
+        assert_eq!(parse("Tuesday"), None);
+    }
+
+    #[test]
+    fn sidecars_without_dates_have_no_metadata() {
+        let (_dir, path) = write_xmp(r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#);
+        assert!(matches!(
+            read_xmp_date(&path),
+            Err(MediaInfoError::NoMetadata(_))
+        ));
+
+        assert!(matches!(
+            read_xmp_date(&path.with_extension("XMP.missing")),
+            Err(MediaInfoError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,27 @@
+//! Organize media into one folder with a date-centric folder structure.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use media_organizer::{Organizer, OrganizerConfig};
+//! use std::path::Path;
+//!
+//! let config = OrganizerConfig {
+//!     destination: "sorted_media".into(),
+//!     dry_run: true,
+//!     ..Default::default()
+//! };
+//! let report = Organizer::new(config).run(Path::new("DCIM"));
+//!
+//! for entry in &report.entries {
+//!     println!("{} -> {}", entry.source, entry.destination);
+//! }
+//! ```
+
+mod organizer;
+
+pub use organizer::{
+    Adjustment, Camera, CollisionPolicy, DateChains, DateStrategy, Failure, Layout, Organizer,
+    OrganizerConfig, Report, ReportEntry, TimeCorrection, TimeCorrections, UndoReport, fix_times,
+    undo,
+};
@@ -1,13 +1,18 @@
-mod organizer;
-
-use clap::Parser;
-use organizer::{handle_path, organize_dir};
-use std::env;
+use chrono::{NaiveDate, TimeDelta};
+use clap::{Parser, Subcommand};
+use media_info::{DateValidation, FileNamePattern, TimeZoneSetting};
+use media_organizer::{
+    CollisionPolicy, DateChains, Layout, Organizer, OrganizerConfig, Report, TimeCorrections,
+    fix_times, undo,
+};
 use std::path::Path;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[clap(
         short,
         long,
@@ -15,14 +20,15 @@ pub struct Args {
         help = "The target media to sort.",
         required = true
     )]
-    pub target: String,
+    pub target: Option<String>,
 
     #[clap(
         short,
         long,
         value_name = "DESTINATION_FOLDER",
         default_value = "sorted_media",
-        help = "The destination folder of sorted media."
+        help = "The destination folder of sorted media.",
+        global = true
     )]
     destination: String,
 
@@ -51,34 +57,272 @@ pub struct Args {
         default_value = "false"
     )]
     dimensions: bool,
+
+    #[clap(
+        long,
+        value_name = "DRY_RUN",
+        help = "Print the planned moves without creating directories or touching files.",
+        default_value = "false",
+        global = true
+    )]
+    dry_run: bool,
+
+    #[clap(
+        long,
+        value_name = "PLAN_FORMAT",
+        help = "The output format of the dry-run plan.",
+        default_value = "table",
+        value_parser = ["table", "json"]
+    )]
+    plan_format: String,
+
+    #[clap(
+        long,
+        value_name = "POLICY",
+        help = "What to do when a file with the same name already exists at the destination: skip, rename-with-suffix, overwrite or keep-if-identical.",
+        default_value = "rename-with-suffix",
+        value_parser = CollisionPolicy::parse
+    )]
+    on_collision: CollisionPolicy,
+
+    #[clap(
+        long,
+        value_name = "LAYOUT",
+        help = "The destination folder template. Fields: {year}, {month}, {month_name}, {day}, {type}, {camera_make}, {camera_model}, {artist}, {album}, {ext}, {source_dir}. Numeric fields accept a zero-padded width, e.g. {month:02}.",
+        default_value = "{year}/{month:02}/{day:02}",
+        value_parser = Layout::parse
+    )]
+    layout: Layout,
+
+    #[clap(
+        short,
+        long,
+        value_name = "JOBS",
+        help = "How many files to read and transfer at once. 0 uses one thread per CPU.",
+        default_value = "0"
+    )]
+    jobs: usize,
+
+    #[clap(
+        long,
+        value_name = "KIND=SOURCES",
+        help = "Where to take dates from, in order, for photo, video, audio, document or all kinds, e.g. photo=metadata,modified. Sources: metadata, xmp, filename, folder, created, modified. Files without a date from any source go to an undated folder. May be repeated. [default: all=metadata,filename,created]",
+        value_parser = date_sources_spec
+    )]
+    date_sources: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "PATTERN",
+        help = "A file name date pattern tried before the built-in camera and phone patterns, using %Y, %m, %d, %H, %M and %S, e.g. \"scan %d.%m.%Y\". May be repeated.",
+        value_parser = FileNamePattern::new
+    )]
+    file_name_pattern: Vec<FileNamePattern>,
+
+    #[clap(
+        long,
+        value_name = "KEEP_FOLDER_NAME",
+        help = "Append the name of the folder a date was read from to the date folder, e.g. 1998/01/01 1998 - Summer Vacation.",
+        default_value = "false"
+    )]
+    keep_folder_name: bool,
+
+    #[clap(
+        long,
+        value_name = "TIMEZONE",
+        help = "The time zone of dates stored only in UTC, such as video creation times, for files without a GPS location: local, an IANA name such as America/Denver, or an offset such as +02:00.",
+        default_value = "local",
+        value_parser = TimeZoneSetting::parse
+    )]
+    timezone: TimeZoneSetting,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "A JSON file of corrections for cameras whose clocks were wrong, each with an optional make, model, serial, from and to day, and either a shift such as \"+1h12m\" or an actual_zone with the camera_zone the clock was set to.",
+        value_parser = TimeCorrections::load,
+        global = true
+    )]
+    time_corrections: Option<TimeCorrections>,
+
+    #[clap(
+        long,
+        value_name = "YYYY-MM-DD",
+        help = "Dates before this day are not trusted, and the next date source is tried instead.",
+        default_value = "1826-01-01"
+    )]
+    earliest_date: NaiveDate,
+
+    #[clap(
+        long,
+        value_name = "YYYY-MM-DD",
+        help = "Dates after this day are not trusted, and the next date source is tried instead. [default: tomorrow]"
+    )]
+    latest_date: Option<NaiveDate>,
+
+    #[clap(
+        long,
+        value_name = "DAYS",
+        help = "Flag a file for review when its date is more than this many days from the date another of its date sources gives. File system dates are not compared."
+    )]
+    max_date_disagreement: Option<u32>,
+
+    #[clap(
+        long,
+        value_name = "RAW_FOLDER",
+        help = "Put camera RAWs in a RAW folder inside their date folder. RAWs and the JPEGs shot with them are always dated and moved together.",
+        default_value = "false"
+    )]
+    raw_folder: bool,
+
+    #[clap(
+        long,
+        value_name = "LIVE_FOLDER",
+        help = "Put the videos of iPhone Live Photos in a live folder inside their date folder. Live Photos are always dated by their photo and moved together.",
+        default_value = "false"
+    )]
+    live_folder: bool,
 }
 
-fn set_env(matches: &Args) {
-    unsafe {
-        env::set_var("DEST_FOLDER", &matches.destination);
-        env::set_var("FILE_TYPE", &matches.file_type);
-        env::set_var("COPY", matches.copy.to_string());
-        env::set_var("DIMENSIONS", matches.dimensions.to_string());
+/// Validates a `--date-sources` spec up front, so clap reports a bad one like any other
+/// invalid argument.
+fn date_sources_spec(spec: &str) -> Result<String, String> {
+    DateChains::default()
+        .set_from(spec)
+        .map(|_| spec.to_string())
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Undo an organizer run by replaying its journal in reverse.
+    Undo {
+        #[clap(
+            value_name = "RUN_ID",
+            help = "The run id printed at the end of the run."
+        )]
+        run_id: String,
+    },
+    /// Write the dates given by --time-corrections back into the EXIF dates of JPEG and TIFF photos.
+    FixTimes {
+        #[clap(
+            value_name = "TARGET_MEDIA",
+            help = "The photo, or folder of photos, to fix."
+        )]
+        target: String,
+    },
+}
+
+fn print_report(report: &Report, plan_format: &str) {
+    for failure in &report.failures {
+        eprintln!("Error: {}: {}", failure.source, failure.reason);
+    }
+    for entry in &report.entries {
+        for review in &entry.review {
+            eprintln!("Review: {}: {}", entry.source, review);
+        }
+    }
+
+    if report.dry_run {
+        if plan_format == "json" {
+            match serde_json::to_string_pretty(&report.entries) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Could not serialize plan: {}", e),
+            }
+        } else {
+            println!("{}", report.format_table());
+        }
+        return;
+    }
+
+    for entry in &report.entries {
+        if let Some(note) = &entry.note {
+            println!("{}: {}", entry.source, note);
+        }
     }
 }
 
 fn main() {
     let matches: Args = Args::parse();
+    let time_corrections = matches.time_corrections.clone().unwrap_or_default();
 
-    set_env(&matches);
-
-    let path = Path::new(&matches.target);
+    match &matches.command {
+        Some(Command::Undo { run_id }) => {
+            let report = match undo(Path::new(&matches.destination), run_id) {
+                Ok(report) => report,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            for failure in &report.failures {
+                eprintln!("Could not undo {}: {}", failure.source, failure.reason);
+            }
+            println!(
+                "Undid run {}: {} files restored, {} directories removed, {} entries could not be undone.",
+                run_id,
+                report.restored,
+                report.removed_dirs,
+                report.failures.len()
+            );
+            if !report.failures.is_empty() {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::FixTimes { target }) => {
+            if let Err(e) = fix_times(Path::new(target), &time_corrections, matches.dry_run) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
 
-    if !path.exists() {
-        println!("Path does not exist: {}", &matches.target);
-        return;
+    let mut date_sources = DateChains::default();
+    for spec in &matches.date_sources {
+        date_sources
+            .set_from(spec)
+            .expect("date sources are validated while parsing arguments");
     }
 
-    if path.is_dir() {
-        organize_dir(&matches.target);
-    } else if path.is_file() {
-        handle_path(&matches.target);
-    } else {
-        println!("Path is not a file or directory: {}", &matches.target);
+    let config = OrganizerConfig {
+        destination: matches.destination.clone().into(),
+        file_type: matches.file_type.clone(),
+        copy: matches.copy,
+        dimensions: matches.dimensions,
+        dry_run: matches.dry_run,
+        on_collision: matches.on_collision,
+        layout: matches.layout.clone(),
+        jobs: matches.jobs,
+        date_sources,
+        file_name_patterns: matches.file_name_pattern.clone(),
+        keep_folder_name: matches.keep_folder_name,
+        time_zone: matches.timezone,
+        time_corrections,
+        date_validation: DateValidation {
+            earliest: matches.earliest_date,
+            latest: matches.latest_date,
+            max_disagreement: matches
+                .max_date_disagreement
+                .map(|days| TimeDelta::days(days.into())),
+            ..Default::default()
+        },
+        raw_folder: matches.raw_folder,
+        live_folder: matches.live_folder,
+    };
+    let organizer = Organizer::new(config);
+    let target = matches.target.as_deref().unwrap_or_default();
+    let report = organizer.run(Path::new(target));
+
+    print_report(&report, &matches.plan_format);
+
+    if report.journaled {
+        println!(
+            "Run {} recorded. Undo it with: media_organizer --destination {} undo {}",
+            organizer.run_id(),
+            matches.destination,
+            organizer.run_id()
+        );
     }
 }
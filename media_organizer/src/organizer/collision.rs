@@ -0,0 +1,292 @@
+use super::file_hash::file_hash;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// What to do when a file with the same name already exists at the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    Skip,
+    RenameWithSuffix,
+    Overwrite,
+    KeepIfIdentical,
+}
+
+impl CollisionPolicy {
+    pub fn parse(policy: &str) -> Result<Self, String> {
+        match policy {
+            "skip" => Ok(CollisionPolicy::Skip),
+            "rename-with-suffix" => Ok(CollisionPolicy::RenameWithSuffix),
+            "overwrite" => Ok(CollisionPolicy::Overwrite),
+            "keep-if-identical" => Ok(CollisionPolicy::KeepIfIdentical),
+            _ => Err(format!("Unknown collision policy: {}", policy)),
+        }
+    }
+}
+
+/// The outcome of checking a destination path against the collision policy.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// Nothing exists at the destination yet.
+    Free(PathBuf),
+    /// The destination is taken, so the file is written under a suffixed name instead.
+    Renamed(PathBuf),
+    /// The existing destination file is replaced.
+    Overwritten(PathBuf),
+    /// The destination is taken and the file is left where it is.
+    Skipped(PathBuf),
+    /// The destination already holds the same content, so the file is left where it is.
+    Identical(PathBuf),
+}
+
+impl Resolution {
+    /// The path the decision was made about.
+    pub fn path(&self) -> &Path {
+        match self {
+            Resolution::Free(path)
+            | Resolution::Renamed(path)
+            | Resolution::Overwritten(path)
+            | Resolution::Skipped(path)
+            | Resolution::Identical(path) => path,
+        }
+    }
+
+    /// The path to write to, or `None` when the file should not be written.
+    pub fn destination(&self) -> Option<&Path> {
+        match self {
+            Resolution::Free(path) | Resolution::Renamed(path) | Resolution::Overwritten(path) => {
+                Some(path)
+            }
+            Resolution::Skipped(_) | Resolution::Identical(_) => None,
+        }
+    }
+
+    /// A short description of the decision, or `None` if there was no collision.
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            Resolution::Free(_) => None,
+            Resolution::Renamed(path) => Some(format!("renamed to {}", path.display())),
+            Resolution::Overwritten(path) => Some(format!("overwrote {}", path.display())),
+            Resolution::Skipped(path) => Some(format!("skipped, {} exists", path.display())),
+            Resolution::Identical(path) => {
+                Some(format!("skipped, identical to {}", path.display()))
+            }
+        }
+    }
+}
+
+/// Destinations claimed earlier in the same run, mapped to the file headed there.
+///
+/// Files are only written after every collision of a run has been resolved, so a claimed
+/// destination counts as taken even though nothing exists there yet.
+#[derive(Debug, Default)]
+pub struct Reservations {
+    claimed: HashMap<PathBuf, PathBuf>,
+}
+
+impl Reservations {
+    fn is_taken(&self, path: &Path) -> bool {
+        self.claimed.contains_key(path) || path.exists()
+    }
+
+    fn claim(&mut self, destination: &Path, source: &Path) {
+        self.claimed
+            .insert(destination.to_path_buf(), source.to_path_buf());
+    }
+}
+
+/// Returns `stem_<n>.ext` for the first `n` that is not taken yet.
+fn suffixed_path(destination: &Path, reservations: &Reservations) -> PathBuf {
+    let parent = destination.parent().unwrap_or(Path::new(""));
+    let stem = destination
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let ext = destination.extension().and_then(|e| e.to_str());
+
+    let mut counter: u32 = 1;
+    loop {
+        let file_name = match ext {
+            Some(e) => format!("{}_{}.{}", stem, counter, e),
+            None => format!("{}_{}", stem, counter),
+        };
+        let candidate = parent.join(file_name);
+
+        if !reservations.is_taken(&candidate) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+fn is_identical(source: &Path, destination: &Path) -> bool {
+    match (file_hash(source), file_hash(destination)) {
+        (Ok(source_hash), Ok(destination_hash)) => source_hash == destination_hash,
+        _ => false,
+    }
+}
+
+/// Decides where `source` should be written given that it is headed for `destination`,
+/// and claims the chosen path in `reservations`.
+///
+/// `keep-if-identical` falls back to renaming when the contents differ, so that a
+/// different file with the same name is never lost. `overwrite` only replaces files that
+/// existed before the run; two files of the same run never overwrite each other.
+pub fn resolve_collision(
+    source: &Path,
+    destination: &Path,
+    policy: CollisionPolicy,
+    reservations: &mut Reservations,
+) -> Resolution {
+    let claimed_by = reservations.claimed.get(destination).cloned();
+
+    let resolution = if !reservations.is_taken(destination) {
+        Resolution::Free(destination.to_path_buf())
+    } else {
+        match policy {
+            CollisionPolicy::Skip => Resolution::Skipped(destination.to_path_buf()),
+            CollisionPolicy::Overwrite if claimed_by.is_none() => {
+                Resolution::Overwritten(destination.to_path_buf())
+            }
+            CollisionPolicy::Overwrite | CollisionPolicy::RenameWithSuffix => {
+                Resolution::Renamed(suffixed_path(destination, reservations))
+            }
+            CollisionPolicy::KeepIfIdentical => {
+                let existing = claimed_by.as_deref().unwrap_or(destination);
+                if is_identical(source, existing) {
+                    Resolution::Identical(destination.to_path_buf())
+                } else {
+                    Resolution::Renamed(suffixed_path(destination, reservations))
+                }
+            }
+        }
+    };
+
+    if let Some(path) = resolution.destination() {
+        reservations.claim(path, source);
+    }
+
+    resolution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.jpg");
+        let destination = dir.path().join("IMG_0001.JPG");
+        fs::write(&source, b"new photo").unwrap();
+        fs::write(&destination, b"old photo").unwrap();
+        (dir, source, destination)
+    }
+
+    fn resolve(source: &Path, destination: &Path, policy: CollisionPolicy) -> Resolution {
+        resolve_collision(source, destination, policy, &mut Reservations::default())
+    }
+
+    #[test]
+    fn free_destination_is_used_as_is() {
+        let (dir, source, _) = setup();
+        let free = dir.path().join("IMG_0002.JPG");
+        let resolution = resolve(&source, &free, CollisionPolicy::Skip);
+
+        assert_eq!(resolution, Resolution::Free(free.clone()));
+        assert_eq!(resolution.destination(), Some(free.as_path()));
+        assert_eq!(resolution.describe(), None);
+    }
+
+    #[test]
+    fn skip_policy_does_not_write() {
+        let (_dir, source, destination) = setup();
+        let resolution = resolve(&source, &destination, CollisionPolicy::Skip);
+
+        assert_eq!(resolution, Resolution::Skipped(destination));
+        assert_eq!(resolution.destination(), None);
+    }
+
+    #[test]
+    fn overwrite_policy_keeps_destination() {
+        let (_dir, source, destination) = setup();
+        let resolution = resolve(&source, &destination, CollisionPolicy::Overwrite);
+
+        assert_eq!(resolution, Resolution::Overwritten(destination));
+    }
+
+    #[test]
+    fn rename_policy_picks_next_free_suffix() {
+        let (dir, source, destination) = setup();
+        fs::write(dir.path().join("IMG_0001_1.JPG"), b"taken").unwrap();
+        let resolution = resolve(&source, &destination, CollisionPolicy::RenameWithSuffix);
+
+        assert_eq!(
+            resolution,
+            Resolution::Renamed(dir.path().join("IMG_0001_2.JPG"))
+        );
+    }
+
+    #[test]
+    fn keep_if_identical_skips_same_content() {
+        let (_dir, source, destination) = setup();
+        fs::write(&source, b"old photo").unwrap();
+        let resolution = resolve(&source, &destination, CollisionPolicy::KeepIfIdentical);
+
+        assert_eq!(resolution, Resolution::Identical(destination));
+        assert_eq!(resolution.destination(), None);
+    }
+
+    #[test]
+    fn keep_if_identical_renames_different_content() {
+        let (dir, source, destination) = setup();
+        let resolution = resolve(&source, &destination, CollisionPolicy::KeepIfIdentical);
+
+        assert_eq!(
+            resolution,
+            Resolution::Renamed(dir.path().join("IMG_0001_1.JPG"))
+        );
+    }
+
+    #[test]
+    fn claimed_destinations_count_as_taken() {
+        let (dir, source, _) = setup();
+        let other = dir.path().join("other.jpg");
+        let free = dir.path().join("IMG_0002.JPG");
+        fs::write(&other, b"other photo").unwrap();
+        let mut reservations = Reservations::default();
+
+        let policy = CollisionPolicy::Overwrite;
+        let first = resolve_collision(&source, &free, policy, &mut reservations);
+        let second = resolve_collision(&other, &free, policy, &mut reservations);
+
+        assert_eq!(first, Resolution::Free(free.clone()));
+        assert_eq!(
+            second,
+            Resolution::Renamed(dir.path().join("IMG_0002_1.JPG"))
+        );
+    }
+
+    #[test]
+    fn keep_if_identical_compares_against_claiming_file() {
+        let (dir, source, _) = setup();
+        let copy = dir.path().join("copy.jpg");
+        let free = dir.path().join("IMG_0002.JPG");
+        fs::write(&copy, b"new photo").unwrap();
+        let mut reservations = Reservations::default();
+
+        let policy = CollisionPolicy::KeepIfIdentical;
+        resolve_collision(&source, &free, policy, &mut reservations);
+        let resolution = resolve_collision(&copy, &free, policy, &mut reservations);
+
+        assert_eq!(resolution, Resolution::Identical(free));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_policy() {
+        assert_eq!(
+            CollisionPolicy::parse("keep-if-identical"),
+            Ok(CollisionPolicy::KeepIfIdentical)
+        );
+        assert!(CollisionPolicy::parse("clobber").is_err());
+    }
+}
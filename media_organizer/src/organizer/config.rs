@@ -0,0 +1,79 @@
+use super::collision::CollisionPolicy;
+use super::make_file_destination::date_chain::DateChains;
+use super::make_file_destination::layout::{DEFAULT_LAYOUT, Layout};
+use super::make_file_destination::time_correction::TimeCorrections;
+use media_info::{DateValidation, FileNamePattern, TimeZoneSetting};
+use std::path::PathBuf;
+
+/// Options for an organizer run.
+///
+/// # Example
+///
+/// ```rust
+/// use media_organizer::OrganizerConfig;
+///
+/// let config = OrganizerConfig {
+///     destination: "sorted_media".into(),
+///     copy: true,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct OrganizerConfig {
+    /// The folder the date folders are created in.
+    pub destination: PathBuf,
+    /// The file extension to sort when organizing a directory, `*` for all files.
+    pub file_type: String,
+    /// Copy the files instead of moving them.
+    pub copy: bool,
+    /// Append width x height dimensions to image and video filenames.
+    pub dimensions: bool,
+    /// Only plan the run, without creating directories or touching files.
+    pub dry_run: bool,
+    /// What to do when a file with the same name already exists at the destination.
+    pub on_collision: CollisionPolicy,
+    /// The folder structure below the destination.
+    pub layout: Layout,
+    /// How many files are read and transferred at once. `0` uses one thread per CPU.
+    pub jobs: usize,
+    /// Where each media kind's date is taken from, in order of preference.
+    pub date_sources: DateChains,
+    /// File name date patterns tried before the built-in ones.
+    pub file_name_patterns: Vec<FileNamePattern>,
+    /// Append the name of the folder a date was read from to the date folder.
+    pub keep_folder_name: bool,
+    /// The time zone of dates stored only in UTC, for files without a GPS location.
+    pub time_zone: TimeZoneSetting,
+    /// Corrections for cameras whose clocks were wrong, applied before the date folder is
+    /// chosen.
+    pub time_corrections: TimeCorrections,
+    /// The checks a date has to pass before a file is filed under it.
+    pub date_validation: DateValidation,
+    /// Route camera RAWs into a `RAW` sub-folder of their date folder.
+    pub raw_folder: bool,
+    /// Route the videos of Live Photos into a `live` sub-folder of their date folder.
+    pub live_folder: bool,
+}
+
+impl Default for OrganizerConfig {
+    fn default() -> Self {
+        OrganizerConfig {
+            destination: PathBuf::from("sorted_media"),
+            file_type: "*".to_string(),
+            copy: false,
+            dimensions: false,
+            dry_run: false,
+            on_collision: CollisionPolicy::RenameWithSuffix,
+            layout: Layout::parse(DEFAULT_LAYOUT).expect("default layout is valid"),
+            jobs: 0,
+            date_sources: DateChains::default(),
+            file_name_patterns: Vec::new(),
+            keep_folder_name: false,
+            time_zone: TimeZoneSetting::Local,
+            time_corrections: TimeCorrections::default(),
+            date_validation: DateValidation::default(),
+            raw_folder: false,
+            live_folder: false,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Returns the hex encoded SHA-256 digest of the file contents.
+pub fn file_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_file_contents() {
+        let hash = file_hash(Path::new("../test-media/TESTDOCUMENT.txt")).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(
+            hash,
+            file_hash(Path::new("../test-media/TESTDOCUMENT.txt")).unwrap()
+        );
+    }
+
+    #[test]
+    fn different_files_have_different_hashes() {
+        let txt = file_hash(Path::new("../test-media/TESTDOCUMENT.txt")).unwrap();
+        let md = file_hash(Path::new("../test-media/TESTDOCUMENT.md")).unwrap();
+        assert_ne!(txt, md);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        assert!(file_hash(Path::new("nonexistent.jpg")).is_err());
+    }
+}
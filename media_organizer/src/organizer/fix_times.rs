@@ -0,0 +1,102 @@
+use super::make_file_destination::time_correction::{Camera, TimeCorrections};
+use glob::glob;
+use media_info::{MediaInfoError, MediaKind, detect, is_raw, rewrite_photo_dates};
+use std::path::{Path, PathBuf};
+
+/// Lists a single file, or every file below a directory.
+fn target_files(target: &Path) -> Result<Vec<PathBuf>, String> {
+    if target.is_file() {
+        return Ok(vec![target.to_path_buf()]);
+    }
+    if !target.is_dir() {
+        return Err(format!("Path does not exist: {}", target.display()));
+    }
+
+    let pattern = format!("{}/**/*", target.display());
+    let paths = glob(&pattern).map_err(|e| format!("Invalid glob pattern: {}", e))?;
+    Ok(paths.flatten().filter(|path| path.is_file()).collect())
+}
+
+/// Writes the dates `corrections` give the photos below `target` back into their EXIF
+/// date tags, printing each change. With `dry_run`, the changes are only printed.
+///
+/// Only JPEG and TIFF photos can be rewritten; other files are skipped, camera RAWs
+/// included, as are photos this already fixed.
+pub fn fix_times(
+    target: &Path,
+    corrections: &TimeCorrections,
+    dry_run: bool,
+) -> Result<(), String> {
+    if corrections.is_empty() {
+        return Err("No time corrections given. Pass them with --time-corrections.".to_string());
+    }
+
+    let mut fixed: u32 = 0;
+    let mut skipped: u32 = 0;
+    for path in target_files(target)? {
+        if detect(&path) != MediaKind::Photo || is_raw(&path) {
+            continue;
+        }
+
+        let camera = Camera::read(MediaKind::Photo, &path);
+        match rewrite_photo_dates(&path, |date| corrections.correct(&camera, date), dry_run) {
+            Ok(rewrites) if rewrites.is_empty() => {}
+            Ok(rewrites) => {
+                for rewrite in rewrites {
+                    println!(
+                        "{}: {} {} -> {}",
+                        path.display(),
+                        rewrite.before.source,
+                        rewrite.before,
+                        rewrite.after
+                    );
+                }
+                fixed += 1;
+            }
+            Err(MediaInfoError::NoMetadata(_)) => {}
+            Err(e) => {
+                println!("Could not fix {}: {}", path.display(), e);
+                skipped += 1;
+            }
+        }
+    }
+
+    let verb = if dry_run { "Would fix" } else { "Fixed" };
+    println!(
+        "{} the dates of {} photos, {} photos could not be fixed.",
+        verb, fixed, skipped
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_corrections_and_an_existing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let corrections = TimeCorrections::parse(r#"[{ "shift": "+1h" }]"#).unwrap();
+
+        assert!(fix_times(dir.path(), &TimeCorrections::default(), true).is_err());
+        assert!(fix_times(&dir.path().join("missing"), &corrections, true).is_err());
+        assert!(fix_times(dir.path(), &corrections, true).is_ok());
+    }
+
+    #[test]
+    fn leaves_camera_raws_byte_identical() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = Path::new("../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg");
+        let jpeg = dir.path().join("IMG_0001.jpg");
+        let nef = dir.path().join("DSC_0001.NEF");
+        std::fs::copy(photo, &jpeg).unwrap();
+        std::fs::copy(photo, &nef).unwrap();
+        let corrections = TimeCorrections::parse(r#"[{ "shift": "+1h" }]"#).unwrap();
+
+        fix_times(dir.path(), &corrections, false).unwrap();
+        let original = std::fs::read(photo).unwrap();
+        assert_eq!(std::fs::read(&nef).unwrap(), original);
+        assert_ne!(std::fs::read(&jpeg).unwrap(), original);
+    }
+}
@@ -1,43 +1,46 @@
+use super::collision::{Reservations, Resolution, resolve_collision};
+use super::config::OrganizerConfig;
+use super::journal::Journal;
+use super::make_file_destination::{DateFolder, sort_and_make};
+use super::report::{Failure, ReportEntry};
+use super::transfer::{MoveError, move_file};
 use faccess::{AccessMode, PathExt};
-use media_info::{read_photo_dimensions, read_video_dimensions};
-use std::env;
-use std::fs::{copy, rename};
+use media_info::{MediaKind, detect, read_photo_dimensions, read_video_dimensions};
+use std::fs::copy;
 use std::path::Path;
 use std::path::PathBuf;
 
-fn handle_if_removable(file: &str) {
+fn removable_hint(file: &str) -> String {
     let file_path = Path::new(file);
 
     if !file_path.exists() {
-        println!("The {} file does not exist.", file);
-        return;
+        return format!("The {} file does not exist.", file);
     }
 
     match file_path.parent().unwrap().access(AccessMode::WRITE) {
-        Ok(_) => println!("The file is likely deletable."),
-        Err(e) => println!("The file might not be deletable. Error: {}", e),
+        Ok(_) => "The file is likely deletable.".to_string(),
+        Err(e) => format!("The file might not be deletable. Error: {}", e),
     }
 }
 
 fn get_dimensions_suffix(original_file: &str) -> Option<String> {
     let path = Path::new(original_file);
-    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-    let ext_lower = ext.to_lowercase();
 
-    let dims = match ext_lower.as_str() {
-        "jpg" | "jpeg" | "png" | "tiff" | "heif" | "heic" | "avif" | "webp" => {
-            read_photo_dimensions(path).ok()
-        }
-        "mp4" | "mov" => read_video_dimensions(path).ok(),
+    let dims = match detect(path) {
+        MediaKind::Photo => read_photo_dimensions(path).ok(),
+        MediaKind::Video => read_video_dimensions(path).ok(),
         _ => None,
     };
 
     dims.map(|(w, h)| format!("_{}x{}", w, h))
 }
 
-fn apply_dimensions_to_filename(file_name: &str, original_file: &str) -> String {
-    let dimensions_env = env::var("DIMENSIONS").unwrap_or("false".to_string());
-    if dimensions_env != "true" {
+fn apply_dimensions_to_filename(
+    file_name: &str,
+    original_file: &str,
+    config: &OrganizerConfig,
+) -> String {
+    if !config.dimensions {
         return file_name.to_string();
     }
 
@@ -55,76 +58,229 @@ fn apply_dimensions_to_filename(file_name: &str, original_file: &str) -> String
     }
 }
 
-fn media_action(file_name: &str, dest_dir: &str, original_file: &str) {
-    let mut owned_dest_string: String = dest_dir.to_owned();
-    let copy_env = env::var("COPY").expect("COPY not set");
-    owned_dest_string.push('/');
-    owned_dest_string.push_str(file_name);
-
-    if copy_env == "true" {
-        match copy(original_file, owned_dest_string) {
-            Ok(_e) => (),
-            Err(_) => handle_if_removable(original_file),
-        };
+fn action_name(config: &OrganizerConfig) -> &'static str {
+    if config.copy { "copy" } else { "move" }
+}
+
+fn describe_action(resolution: &Resolution, config: &OrganizerConfig) -> String {
+    match resolution {
+        Resolution::Free(_) => action_name(config).to_string(),
+        Resolution::Renamed(_) => format!("{} (renamed)", action_name(config)),
+        Resolution::Overwritten(_) => format!("{} (overwrite)", action_name(config)),
+        Resolution::Skipped(_) => "skip (exists)".to_string(),
+        Resolution::Identical(_) => "skip (identical)".to_string(),
+    }
+}
+
+fn media_action(
+    original_file: &str,
+    destination: &Path,
+    config: &OrganizerConfig,
+) -> Result<(), MoveError> {
+    let result = if config.copy {
+        copy(original_file, destination)
+            .map(|_| ())
+            .map_err(|e| MoveError::new(e.to_string()))
     } else {
-        match rename(original_file, owned_dest_string) {
-            Ok(_e) => (),
-            Err(_) => handle_if_removable(original_file),
-        };
+        move_file(Path::new(original_file), destination)
+    };
+
+    result.map_err(|mut e| {
+        e.reason = format!(
+            "Could not {} to {}: {}. {}",
+            action_name(config),
+            destination.display(),
+            e.reason,
+            removable_hint(original_file)
+        );
+        e
+    })
+}
+
+fn dest_file_name(original_file: &str, config: &OrganizerConfig) -> Result<String, String> {
+    let original_file_path_buf: PathBuf = PathBuf::from(original_file);
+
+    match original_file_path_buf.file_name().and_then(|f| f.to_str()) {
+        Some(file_name) => Ok(apply_dimensions_to_filename(
+            file_name,
+            original_file,
+            config,
+        )),
+        None => Err(format!(
+            "Could not get file name from path: {}",
+            original_file
+        )),
+    }
+}
+
+/// A file whose date folder and destination file name are known.
+pub struct PreparedFile {
+    pub source: String,
+    pub date_folder: DateFolder,
+    pub file_name: String,
+}
+
+/// A prepared file whose collision has been resolved.
+pub struct PlannedFile {
+    pub date_folder: DateFolder,
+    resolution: Resolution,
+    entry: ReportEntry,
+}
+
+impl PlannedFile {
+    /// Whether applying the plan writes the file anywhere.
+    pub fn writes(&self) -> bool {
+        self.resolution.destination().is_some()
+    }
+
+    /// The name the file is planned under, after any collision rename.
+    pub fn file_name(&self) -> Option<&str> {
+        self.resolution.path().file_name().and_then(|n| n.to_str())
+    }
+
+    pub fn into_entry(self) -> ReportEntry {
+        self.entry
     }
 }
 
-pub fn handle_media(original_file: &str, dest_dir: &str) {
-    let mut original_file_path_buf: PathBuf = PathBuf::new();
+/// Reads the file's metadata to find its date folder. Does not touch the destination, so
+/// it can run for many files at once.
+pub fn prepare_media(
+    original_file: &str,
+    config: &OrganizerConfig,
+) -> Result<PreparedFile, String> {
+    if !Path::new(original_file).is_file() {
+        return Err("Path is not a file".to_string());
+    }
+
+    Ok(PreparedFile {
+        source: original_file.to_string(),
+        date_folder: sort_and_make(original_file, config)?,
+        file_name: dest_file_name(original_file, config)?,
+    })
+}
 
-    original_file_path_buf.push(original_file);
+/// Applies the collision policy against the destination and the files planned before
+/// this one. Files have to be planned one at a time, in order.
+pub fn plan_media(
+    prepared: PreparedFile,
+    config: &OrganizerConfig,
+    reservations: &mut Reservations,
+) -> PlannedFile {
+    let resolution = resolve_collision(
+        Path::new(&prepared.source),
+        &Path::new(&prepared.date_folder.path).join(&prepared.file_name),
+        config.on_collision,
+        reservations,
+    );
+    let entry = ReportEntry {
+        source: prepared.source,
+        destination: resolution.path().display().to_string(),
+        date_source: prepared.date_folder.date_source.clone(),
+        date: prepared.date_folder.date.map(|date| date.to_string()),
+        action: describe_action(&resolution, config),
+        note: resolution.describe(),
+        review: prepared.date_folder.review.clone(),
+    };
 
-    match original_file_path_buf.file_name() {
-        Some(file_name) => {
-            let file_name_str = file_name
-                .to_str()
-                .expect("could not read filename from path buffer.");
-            let dest_file_name = apply_dimensions_to_filename(file_name_str, original_file);
-            media_action(&dest_file_name, dest_dir, original_file);
+    PlannedFile {
+        date_folder: prepared.date_folder,
+        resolution,
+        entry,
+    }
+}
+
+/// Moves or copies a planned file into its date folder. Planned destinations never
+/// overlap, so this can run for many files at once.
+pub fn apply_media(
+    planned: PlannedFile,
+    config: &OrganizerConfig,
+    journal: &Journal,
+) -> Result<ReportEntry, Failure> {
+    let source = planned.entry.source.as_str();
+
+    if let Some(destination) = planned.resolution.destination() {
+        // The file being replaced is kept, so the run can still be undone
+        if let Resolution::Overwritten(_) = planned.resolution
+            && destination.exists()
+            && let Err(e) = journal.set_aside(destination)
+        {
+            return Err(Failure {
+                source: source.to_string(),
+                reason: format!("Could not set aside {}: {}", destination.display(), e),
+            });
         }
-        None => {
-            println!("Could not get file name from path: {}", original_file);
+        // A transfer that could not be journaled could not be undone, so it is not started
+        journal
+            .begin_file(action_name(config), Path::new(source), destination)
+            .map_err(|reason| Failure {
+                source: source.to_string(),
+                reason,
+            })?;
+        if let Err(mut e) = media_action(source, destination, config) {
+            // A move that got as far as copying leaves a file behind that undo should remove.
+            if e.copied
+                && let Err(journal_error) =
+                    journal.record_file("copy", Path::new(source), destination)
+            {
+                e.reason = format!("{} {}", e.reason, journal_error);
+            }
+            return Err(Failure {
+                source: source.to_string(),
+                reason: e.reason,
+            });
         }
+        journal
+            .record_file(action_name(config), Path::new(source), destination)
+            .map_err(|e| Failure {
+                source: source.to_string(),
+                reason: format!(
+                    "Could not confirm the {} to {}, undo may leave it in place: {}",
+                    action_name(config),
+                    destination.display(),
+                    e
+                ),
+            })?;
     }
+
+    Ok(planned.entry)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn config(dimensions: bool) -> OrganizerConfig {
+        OrganizerConfig {
+            dimensions,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn apply_dimensions_disabled_returns_original() {
-        unsafe {
-            env::remove_var("DIMENSIONS");
-        }
-        // Use a non-image file so result is deterministic regardless of env race
-        let result = apply_dimensions_to_filename("notes.txt", "../test-media/test.txt");
+        let result =
+            apply_dimensions_to_filename("notes.txt", "../test-media/test.txt", &config(false));
         assert_eq!(result, "notes.txt");
     }
 
     #[test]
     fn apply_dimensions_to_photo() {
-        unsafe {
-            env::set_var("DIMENSIONS", "true");
-        }
         let result = apply_dimensions_to_filename(
             "400a861d-014a-4dfb-9143-1a914212fd4d.jpg",
             "../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg",
+            &config(true),
         );
         assert_eq!(result, "400a861d-014a-4dfb-9143-1a914212fd4d_3022x4030.jpg");
     }
 
     #[test]
     fn apply_dimensions_to_video() {
-        unsafe {
-            env::set_var("DIMENSIONS", "true");
-        }
-        let result = apply_dimensions_to_filename("corgi_race.mp4", "../test-media/corgi_race.mp4");
+        let result = apply_dimensions_to_filename(
+            "corgi_race.mp4",
+            "../test-media/corgi_race.mp4",
+            &config(true),
+        );
         assert!(result.contains("x"));
         assert!(result.starts_with("corgi_race_"));
         assert!(result.ends_with(".mp4"));
@@ -132,19 +288,17 @@ mod tests {
 
     #[test]
     fn apply_dimensions_skips_audio() {
-        unsafe {
-            env::set_var("DIMENSIONS", "true");
-        }
-        let result = apply_dimensions_to_filename("Recording.m4a", "../test-media/Recording.m4a");
+        let result = apply_dimensions_to_filename(
+            "Recording.m4a",
+            "../test-media/Recording.m4a",
+            &config(true),
+        );
         assert_eq!(result, "Recording.m4a");
     }
 
     #[test]
     fn apply_dimensions_nonexistent_file_returns_original() {
-        unsafe {
-            env::set_var("DIMENSIONS", "true");
-        }
-        let result = apply_dimensions_to_filename("missing.jpg", "nonexistent.jpg");
+        let result = apply_dimensions_to_filename("missing.jpg", "nonexistent.jpg", &config(true));
         assert_eq!(result, "missing.jpg");
     }
 }
@@ -0,0 +1,529 @@
+use super::file_hash::file_hash;
+use super::report::Failure;
+use super::transfer::move_file;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const JOURNAL_DIR: &str = ".media_organizer";
+
+/// One durable record of a filesystem change made during a run.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    pub run_id: String,
+    pub timestamp: String,
+    pub source: String,
+    pub destination: String,
+    pub action: String,
+    pub hash: Option<String>,
+    /// Written before a file is moved or copied, with the hash of the source. Once the file
+    /// is in place, a confirmed entry follows; a pending entry without one may or may not
+    /// have been applied. Journals written before this field existed only hold confirmed
+    /// entries.
+    #[serde(default)]
+    pub pending: bool,
+}
+
+/// Append-only log of every move, copy and created directory of a single run, stored as
+/// JSON lines in `<destination>/.media_organizer/<run_id>.jsonl`. Files the run overwrites
+/// are set aside in `<destination>/.media_organizer/<run_id>/` first.
+///
+/// A journal can be shared between threads; appends are serialized so lines never interleave.
+pub struct Journal {
+    pub run_id: String,
+    path: PathBuf,
+    dest_folder: PathBuf,
+    append_lock: Mutex<()>,
+}
+
+pub fn new_run_id() -> String {
+    format!(
+        "{}-{}",
+        Local::now().format("%Y%m%dT%H%M%S"),
+        std::process::id()
+    )
+}
+
+fn journal_path(dest_folder: &Path, run_id: &str) -> PathBuf {
+    dest_folder
+        .join(JOURNAL_DIR)
+        .join(format!("{}.jsonl", run_id))
+}
+
+/// Journal paths are absolute so that `undo` works from any working directory.
+fn absolute_display(path: &Path) -> String {
+    std::path::absolute(path)
+        .unwrap_or(path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+impl Journal {
+    pub fn new(dest_folder: &Path, run_id: &str) -> Self {
+        Journal {
+            run_id: run_id.to_string(),
+            path: journal_path(dest_folder, run_id),
+            dest_folder: dest_folder.to_path_buf(),
+            append_lock: Mutex::new(()),
+        }
+    }
+
+    fn append(&self, entry: &JournalEntry) -> Result<(), String> {
+        let _guard = self.append_lock.lock().map_err(|e| e.to_string())?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| e.to_string())?;
+        file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+        file.sync_data().map_err(|e| e.to_string())
+    }
+
+    fn append_file(
+        &self,
+        action: &str,
+        source: &Path,
+        destination: &Path,
+        pending: bool,
+    ) -> Result<(), String> {
+        let entry = JournalEntry {
+            run_id: self.run_id.clone(),
+            timestamp: Local::now().to_rfc3339(),
+            source: absolute_display(source),
+            destination: absolute_display(destination),
+            action: action.to_string(),
+            hash: file_hash(if pending { source } else { destination }).ok(),
+            pending,
+        };
+
+        self.append(&entry)
+            .map_err(|e| format!("Could not write journal entry for {}: {}", entry.source, e))
+    }
+
+    /// Records a file that is about to be moved or copied to `destination`, so a run that
+    /// is interrupted during the transfer can still be undone.
+    pub fn begin_file(
+        &self,
+        action: &str,
+        source: &Path,
+        destination: &Path,
+    ) -> Result<(), String> {
+        self.append_file(action, source, destination, true)
+    }
+
+    /// Records a file that was moved or copied to `destination`.
+    pub fn record_file(
+        &self,
+        action: &str,
+        source: &Path,
+        destination: &Path,
+    ) -> Result<(), String> {
+        self.append_file(action, source, destination, false)
+    }
+
+    /// Moves a file the run is about to overwrite into `.media_organizer/<run_id>/`, under
+    /// its path in the destination folder, so `undo` can put it back.
+    pub fn set_aside(&self, file: &Path) -> Result<(), String> {
+        let relative = match (
+            std::path::absolute(file),
+            std::path::absolute(&self.dest_folder),
+        ) {
+            (Ok(file), Ok(dest_folder)) => {
+                file.strip_prefix(dest_folder).map(Path::to_path_buf).ok()
+            }
+            _ => None,
+        };
+        let relative = relative.unwrap_or_else(|| file.file_name().unwrap_or_default().into());
+        let backup = self
+            .dest_folder
+            .join(JOURNAL_DIR)
+            .join(&self.run_id)
+            .join(relative);
+
+        if let Some(parent) = backup.parent() {
+            let created_dirs = missing_dirs(parent);
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            for dir in created_dirs {
+                self.record_dir(&dir)?;
+            }
+        }
+        self.begin_file("backup", file, &backup)?;
+        move_file(file, &backup).map_err(|e| e.reason)?;
+        self.record_file("backup", file, &backup)
+    }
+
+    /// Records a directory created by the run, so `undo` can remove it again once empty.
+    pub fn record_dir(&self, dir: &Path) -> Result<(), String> {
+        let entry = JournalEntry {
+            run_id: self.run_id.clone(),
+            timestamp: Local::now().to_rfc3339(),
+            source: String::new(),
+            destination: absolute_display(dir),
+            action: "mkdir".to_string(),
+            hash: None,
+            pending: false,
+        };
+
+        self.append(&entry).map_err(|e| {
+            format!(
+                "Could not write journal entry for {}: {}",
+                entry.destination, e
+            )
+        })
+    }
+
+    /// Whether anything was journaled, so there is a run to undo.
+    pub fn written(&self) -> bool {
+        self.path.exists()
+    }
+}
+
+/// Returns the directories that creating `dir` would create, outermost first.
+pub fn missing_dirs(dir: &Path) -> Vec<PathBuf> {
+    let mut missing: Vec<PathBuf> = dir
+        .ancestors()
+        .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+        .map(Path::to_path_buf)
+        .collect();
+    missing.reverse();
+
+    missing
+}
+
+fn read_journal(path: &Path) -> Result<Vec<JournalEntry>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read journal {}: {}", path.display(), e))?;
+
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|e| format!("Corrupt journal line: {}", e)))
+        .collect()
+}
+
+fn undo_file(entry: &JournalEntry) -> Result<(), String> {
+    let source = Path::new(&entry.source);
+    let destination = Path::new(&entry.destination);
+
+    if !destination.exists() {
+        return Err(format!("{} no longer exists", destination.display()));
+    }
+    if entry.hash.is_some() && file_hash(destination).ok() != entry.hash {
+        return Err(format!(
+            "{} changed since it was organized",
+            destination.display()
+        ));
+    }
+
+    match entry.action.as_str() {
+        // A move interrupted after its copy was verified left the source in place
+        "move" | "backup" if entry.pending && source.exists() => {
+            fs::remove_file(destination).map_err(|e| e.to_string())
+        }
+        // A file that was overwritten goes back once the file that replaced it is undone
+        "move" | "backup" => {
+            if source.exists() {
+                return Err(format!("{} already exists", source.display()));
+            }
+            if let Some(parent) = source.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            move_file(destination, source).map_err(|e| e.reason)
+        }
+        "copy" => {
+            if !source.exists() {
+                return Err(format!(
+                    "{} is the only remaining copy of {}",
+                    destination.display(),
+                    source.display()
+                ));
+            }
+            fs::remove_file(destination).map_err(|e| e.to_string())
+        }
+        action => Err(format!("Unknown journal action: {}", action)),
+    }
+}
+
+/// What [`undo`] did with the journal of a run.
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    pub restored: u32,
+    pub removed_dirs: u32,
+    /// The entries that could not be undone, by destination, and why.
+    pub failures: Vec<Failure>,
+}
+
+/// Replaces a journal with `entries`, through a temporary file so the journal is never
+/// left half written.
+fn write_journal(path: &Path, entries: &[&JournalEntry]) -> Result<(), String> {
+    let mut lines = String::new();
+    for entry in entries {
+        lines.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+        lines.push('\n');
+    }
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, lines)
+        .and_then(|_| fs::rename(&temporary, path))
+        .map_err(|e| format!("Could not rewrite journal {}: {}", path.display(), e))
+}
+
+/// Replays the journal of `run_id` in reverse: moved files are moved back, copies are
+/// removed, files that were overwritten are put back, and directories created by the run
+/// are removed if they are empty again.
+///
+/// Transfers that were begun but never confirmed, because the run was interrupted, are
+/// undone when their destination holds the source's contents, and skipped when it does not
+/// exist. Files that changed since the run, or whose original location is occupied, are
+/// left in place and reported. Once everything is undone, the journal is renamed to
+/// `<run_id>.jsonl.undone`; otherwise it is rewritten with only what is left, so `undo`
+/// can be run again once the problems are sorted out.
+pub fn undo(dest_folder: &Path, run_id: &str) -> Result<UndoReport, String> {
+    let path = journal_path(dest_folder, run_id);
+    let entries = read_journal(&path)?;
+    let (mut dirs, files): (Vec<&JournalEntry>, Vec<&JournalEntry>) =
+        entries.iter().partition(|entry| entry.action == "mkdir");
+    let confirmed: HashSet<(&str, &str)> = files
+        .iter()
+        .filter(|entry| !entry.pending)
+        .map(|entry| (entry.source.as_str(), entry.destination.as_str()))
+        .collect();
+
+    let mut report = UndoReport::default();
+    let mut failed: Vec<&JournalEntry> = Vec::new();
+    for entry in files.iter().rev() {
+        if entry.pending
+            && (confirmed.contains(&(entry.source.as_str(), entry.destination.as_str()))
+                || !Path::new(&entry.destination).exists())
+        {
+            continue;
+        }
+        match undo_file(entry) {
+            Ok(()) => report.restored += 1,
+            Err(reason) => {
+                report.failures.push(Failure {
+                    source: entry.destination.clone(),
+                    reason,
+                });
+                failed.push(entry);
+            }
+        }
+    }
+
+    dirs.sort_by_key(|entry| Reverse(Path::new(&entry.destination).components().count()));
+    let mut remaining_dirs: Vec<&JournalEntry> = Vec::new();
+    for entry in dirs {
+        if fs::remove_dir(&entry.destination).is_ok() {
+            report.removed_dirs += 1;
+        } else if Path::new(&entry.destination).exists() {
+            remaining_dirs.push(entry);
+        }
+    }
+
+    if failed.is_empty() {
+        let mut undone_path = path.clone().into_os_string();
+        undone_path.push(".undone");
+        fs::rename(&path, undone_path).map_err(|e| e.to_string())?;
+    } else {
+        // Kept in journal order, directories first, so a later undo replays them the same way
+        remaining_dirs.reverse();
+        failed.reverse();
+        remaining_dirs.extend(failed.iter());
+        write_journal(&path, &remaining_dirs)?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_dirs_lists_outermost_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let date_dir = dir.path().join("sorted/2024/10/22");
+        fs::create_dir_all(dir.path().join("sorted")).unwrap();
+
+        assert_eq!(
+            missing_dirs(&date_dir),
+            vec![
+                dir.path().join("sorted/2024"),
+                dir.path().join("sorted/2024/10"),
+                date_dir.clone(),
+            ]
+        );
+    }
+
+    #[test]
+    fn journal_round_trips_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Journal::new(dir.path(), "run-1");
+        let file = dir.path().join("a.txt");
+        fs::write(&file, b"a").unwrap();
+
+        journal.record_dir(dir.path()).unwrap();
+        journal
+            .record_file("copy", Path::new("src/a.txt"), &file)
+            .unwrap();
+
+        let entries = read_journal(&journal_path(dir.path(), "run-1")).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, "mkdir");
+        assert!(entries[1].source.ends_with("src/a.txt"));
+        assert_eq!(entries[1].hash, file_hash(&file).ok());
+    }
+
+    #[test]
+    fn undo_moves_files_back_and_removes_created_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest_folder = dir.path().join("sorted");
+        let source = dir.path().join("import/IMG_0001.JPG");
+        let date_dir = dest_folder.join("2024/10/22");
+        let destination = date_dir.join("IMG_0001.JPG");
+        fs::create_dir_all(source.parent().unwrap()).unwrap();
+        fs::write(&source, b"photo").unwrap();
+
+        let journal = Journal::new(&dest_folder, "run-2");
+        let created = missing_dirs(&date_dir);
+        fs::create_dir_all(&date_dir).unwrap();
+        for created_dir in &created {
+            journal.record_dir(created_dir).unwrap();
+        }
+        fs::rename(&source, &destination).unwrap();
+        journal.record_file("move", &source, &destination).unwrap();
+
+        undo(&dest_folder, "run-2").unwrap();
+
+        assert_eq!(fs::read(&source).unwrap(), b"photo");
+        assert!(!dest_folder.join("2024").exists());
+        assert!(
+            dest_folder
+                .join(".media_organizer/run-2.jsonl.undone")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn undo_keeps_modified_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.txt");
+        let destination = dir.path().join("b.txt");
+        fs::write(&destination, b"original").unwrap();
+
+        let journal = Journal::new(dir.path(), "run-3");
+        journal.record_file("move", &source, &destination).unwrap();
+        fs::write(&destination, b"edited").unwrap();
+
+        let report = undo(dir.path(), "run-3").unwrap();
+
+        assert_eq!(report.failures.len(), 1);
+        assert!(destination.exists());
+        assert!(!source.exists());
+        // The journal is kept for another try once the edit is reverted
+        let path = journal_path(dir.path(), "run-3");
+        assert_eq!(read_journal(&path).unwrap().len(), 1);
+
+        fs::write(&destination, b"original").unwrap();
+        assert_eq!(undo(dir.path(), "run-3").unwrap().restored, 1);
+
+        assert_eq!(fs::read(&source).unwrap(), b"original");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn undo_handles_transfers_that_were_never_confirmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let import = dir.path().join("import");
+        let sorted = dir.path().join("sorted");
+        fs::create_dir_all(&import).unwrap();
+        fs::create_dir_all(&sorted).unwrap();
+        let moved = import.join("moved.jpg");
+        let copied = import.join("copied.jpg");
+        let untouched = import.join("untouched.jpg");
+        for file in [&moved, &copied, &untouched] {
+            fs::write(file, file.display().to_string()).unwrap();
+        }
+
+        let journal = Journal::new(&sorted, "run-4");
+        // Interrupted right after the rename
+        journal
+            .begin_file("move", &moved, &sorted.join("moved.jpg"))
+            .unwrap();
+        fs::rename(&moved, sorted.join("moved.jpg")).unwrap();
+        // Interrupted after a cross-device copy, before the source was removed
+        journal
+            .begin_file("move", &copied, &sorted.join("copied.jpg"))
+            .unwrap();
+        fs::copy(&copied, sorted.join("copied.jpg")).unwrap();
+        // Interrupted before anything was written
+        journal
+            .begin_file("move", &untouched, &sorted.join("untouched.jpg"))
+            .unwrap();
+
+        undo(&sorted, "run-4").unwrap();
+
+        for file in [&moved, &copied, &untouched] {
+            assert_eq!(
+                fs::read_to_string(file).unwrap(),
+                file.display().to_string()
+            );
+        }
+        assert_eq!(fs::read_dir(&sorted).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn undo_puts_overwritten_files_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let sorted = dir.path().join("sorted");
+        let source = dir.path().join("import/IMG_0001.JPG");
+        let destination = sorted.join("2024/10/22/IMG_0001.JPG");
+        fs::create_dir_all(source.parent().unwrap()).unwrap();
+        fs::create_dir_all(destination.parent().unwrap()).unwrap();
+        fs::write(&source, b"new photo").unwrap();
+        fs::write(&destination, b"old photo").unwrap();
+
+        let journal = Journal::new(&sorted, "run-5");
+        journal.set_aside(&destination).unwrap();
+        let backup = sorted.join(".media_organizer/run-5/2024/10/22/IMG_0001.JPG");
+        assert_eq!(fs::read(&backup).unwrap(), b"old photo");
+        journal.begin_file("copy", &source, &destination).unwrap();
+        fs::copy(&source, &destination).unwrap();
+        journal.record_file("copy", &source, &destination).unwrap();
+
+        undo(&sorted, "run-5").unwrap();
+
+        assert_eq!(fs::read(&destination).unwrap(), b"old photo");
+        assert_eq!(fs::read(&source).unwrap(), b"new photo");
+        assert!(!sorted.join(".media_organizer/run-5").exists());
+    }
+
+    #[test]
+    fn older_journals_are_read_as_confirmed() {
+        let entry: JournalEntry = serde_json::from_str(
+            r#"{"run_id":"run-1","timestamp":"","source":"a","destination":"b","action":"move","hash":null}"#,
+        )
+        .unwrap();
+        assert!(!entry.pending);
+    }
+
+    #[test]
+    fn undo_unknown_run_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(undo(dir.path(), "missing").is_err());
+    }
+}
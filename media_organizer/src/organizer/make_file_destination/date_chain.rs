@@ -0,0 +1,168 @@
+use media_info::MediaKind;
+
+/// A place a file's date can be taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStrategy {
+    /// The date stored in the file: EXIF for photos, container metadata for videos, tags
+    /// for audio and document properties for documents.
+    Metadata,
+    /// A date in the file name, such as `IMG_20240101_123456.jpg`.
+    FileName,
+    /// A date in the name of a folder above the file, such as `1998 - Summer Vacation/`.
+    Folder,
+    /// When the file was created on this file system, or where the file system does not
+    /// record that, the earlier of when it was last modified or changed.
+    Created,
+    /// When the file was last modified.
+    Modified,
+    /// The `photoshop:DateCreated` or `xmp:CreateDate` of the file's XMP sidecar, such as
+    /// `DSC_1234.xmp` or `DSC_1234.NEF.xmp`.
+    Xmp,
+}
+
+impl DateStrategy {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim() {
+            "metadata" => Ok(DateStrategy::Metadata),
+            "filename" => Ok(DateStrategy::FileName),
+            "folder" => Ok(DateStrategy::Folder),
+            "created" => Ok(DateStrategy::Created),
+            "modified" => Ok(DateStrategy::Modified),
+            "xmp" => Ok(DateStrategy::Xmp),
+            other => Err(format!(
+                "Unknown date source: {} (expected metadata, xmp, filename, folder, created or modified)",
+                other
+            )),
+        }
+    }
+}
+
+/// The date strategies tried in order for each media kind. A file whose strategies all
+/// fail is sorted into the `undated` folder.
+///
+/// # Example
+///
+/// ```rust
+/// use media_organizer::{DateChains, DateStrategy};
+///
+/// let mut chains = DateChains::default();
+/// chains.set_from("photo=metadata").unwrap();
+/// chains.set_from("video=metadata,modified").unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateChains {
+    pub photo: Vec<DateStrategy>,
+    pub video: Vec<DateStrategy>,
+    pub audio: Vec<DateStrategy>,
+    pub document: Vec<DateStrategy>,
+}
+
+impl Default for DateChains {
+    /// The date stored in the file, then a date in the file name, then the file system
+    /// creation date.
+    fn default() -> Self {
+        let chain = vec![
+            DateStrategy::Metadata,
+            DateStrategy::FileName,
+            DateStrategy::Created,
+        ];
+        DateChains {
+            photo: chain.clone(),
+            video: chain.clone(),
+            audio: chain.clone(),
+            document: chain,
+        }
+    }
+}
+
+impl DateChains {
+    pub fn for_kind(&self, kind: MediaKind) -> &[DateStrategy] {
+        match kind {
+            MediaKind::Photo => &self.photo,
+            MediaKind::Video => &self.video,
+            MediaKind::Audio => &self.audio,
+            MediaKind::Document => &self.document,
+            MediaKind::Unknown => &[],
+        }
+    }
+
+    /// Replaces chains from a `kind=source,source` spec, where kind is `photo`, `video`,
+    /// `audio`, `document` or `all`.
+    pub fn set_from(&mut self, spec: &str) -> Result<(), String> {
+        let (kind, sources) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("Expected kind=source,source in date sources: {}", spec))?;
+
+        let chains: Vec<&mut Vec<DateStrategy>> = match kind.trim() {
+            "photo" => vec![&mut self.photo],
+            "video" => vec![&mut self.video],
+            "audio" => vec![&mut self.audio],
+            "document" => vec![&mut self.document],
+            "all" => vec![
+                &mut self.photo,
+                &mut self.video,
+                &mut self.audio,
+                &mut self.document,
+            ],
+            other => return Err(format!("Unknown media kind in date sources: {}", other)),
+        };
+        let chain = sources
+            .split(',')
+            .map(DateStrategy::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for existing in chains {
+            *existing = chain.clone();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_chain_trusts_metadata_then_file_name_then_creation_date() {
+        let chains = DateChains::default();
+        assert_eq!(
+            chains.for_kind(MediaKind::Video),
+            [
+                DateStrategy::Metadata,
+                DateStrategy::FileName,
+                DateStrategy::Created
+            ]
+        );
+        assert!(chains.for_kind(MediaKind::Unknown).is_empty());
+    }
+
+    #[test]
+    fn specs_replace_chains_in_order() {
+        let mut chains = DateChains::default();
+        chains.set_from("all=modified").unwrap();
+        chains
+            .set_from("photo=xmp,metadata, filename,modified")
+            .unwrap();
+
+        assert_eq!(
+            chains.photo,
+            [
+                DateStrategy::Xmp,
+                DateStrategy::Metadata,
+                DateStrategy::FileName,
+                DateStrategy::Modified
+            ]
+        );
+        assert_eq!(chains.audio, [DateStrategy::Modified]);
+    }
+
+    #[test]
+    fn rejects_unknown_kinds_and_sources() {
+        let mut chains = DateChains::default();
+        assert!(chains.set_from("photo").is_err());
+        assert!(chains.set_from("sculpture=metadata").is_err());
+        assert!(chains.set_from("photo=exif,guess").is_err());
+        assert_eq!(chains, DateChains::default());
+    }
+}
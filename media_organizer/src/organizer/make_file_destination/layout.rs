@@ -0,0 +1,318 @@
+use chrono::{Datelike, NaiveDate};
+use std::path::{Component, Path};
+
+pub const DEFAULT_LAYOUT: &str = "{year}/{month:02}/{day:02}";
+
+const UNDATED_FOLDER: &str = "undated";
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Year,
+    Month,
+    MonthName,
+    Day,
+    Type,
+    CameraMake,
+    CameraModel,
+    Artist,
+    Album,
+    Ext,
+    SourceDir,
+}
+
+impl Field {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "year" => Ok(Field::Year),
+            "month" => Ok(Field::Month),
+            "month_name" => Ok(Field::MonthName),
+            "day" => Ok(Field::Day),
+            "type" => Ok(Field::Type),
+            "camera_make" => Ok(Field::CameraMake),
+            "camera_model" => Ok(Field::CameraModel),
+            "artist" => Ok(Field::Artist),
+            "album" => Ok(Field::Album),
+            "ext" => Ok(Field::Ext),
+            "source_dir" => Ok(Field::SourceDir),
+            _ => Err(format!("Unknown layout field: {{{}}}", name)),
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Field::Year | Field::Month | Field::Day)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field { field: Field, width: usize },
+}
+
+/// A destination path template such as `{year}/{month:02}-{month_name}/{day}`.
+///
+/// Numeric fields (`year`, `month`, `day`) accept a zero-padded width after a colon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    segments: Vec<Segment>,
+}
+
+/// The values a layout is rendered with. Missing metadata renders as `unknown`.
+#[derive(Debug, Default)]
+pub struct LayoutValues {
+    pub date: Option<NaiveDate>,
+    pub media_type: String,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub ext: Option<String>,
+    pub source_dir: Option<String>,
+}
+
+/// Metadata values become single path components, so separators and quotes are replaced.
+pub(super) fn sanitize(value: Option<&str>) -> String {
+    let cleaned: String = value
+        .unwrap_or_default()
+        .trim()
+        .trim_matches('"')
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        "unknown".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Text between fields is kept as is, so a stray `}` there is a typo rather than a folder name.
+fn literal(text: &str, template: &str) -> Result<Segment, String> {
+    if text.contains('}') {
+        return Err(format!("Unmatched '}}' in layout: {}", template));
+    }
+    Ok(Segment::Literal(text.to_string()))
+}
+
+impl Layout {
+    /// Parses a template, which must stay below the destination: it cannot start at a root
+    /// or drive, nor climb out with `..` folders.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let escapes = template.starts_with(['/', '\\'])
+            || template.as_bytes().get(1) == Some(&b':')
+            || template.split(['/', '\\']).any(|folder| folder == "..")
+            || Path::new(template)
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+        if escapes {
+            return Err(format!(
+                "Layout must stay inside the destination: {}",
+                template
+            ));
+        }
+
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut rest = template;
+
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                segments.push(literal(&rest[..open], template)?);
+            }
+
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| format!("Unclosed '{{' in layout: {}", template))?;
+            let spec = &rest[open + 1..open + close];
+            let (name, width) = match spec.split_once(':') {
+                Some((name, width)) => {
+                    let width: usize = width
+                        .parse()
+                        .map_err(|_| format!("Invalid width in layout field: {{{}}}", spec))?;
+                    (name, width)
+                }
+                None => (spec, 0),
+            };
+            let field = Field::parse(name)?;
+
+            if width > 0 && !field.is_numeric() {
+                return Err(format!("Layout field {{{}}} cannot be padded", name));
+            }
+
+            segments.push(Segment::Field { field, width });
+            rest = &rest[open + close + 1..];
+        }
+
+        if !rest.is_empty() {
+            segments.push(literal(rest, template)?);
+        }
+
+        Ok(Layout { segments })
+    }
+
+    pub(crate) fn uses(&self, wanted: &[Field]) -> bool {
+        self.segments.iter().any(|segment| match segment {
+            Segment::Field { field, .. } => wanted.contains(field),
+            Segment::Literal(_) => false,
+        })
+    }
+
+    /// Renders a single field, or `None` for a date field when there is no date.
+    fn render_field(field: Field, width: usize, values: &LayoutValues) -> Option<String> {
+        let date = values.date;
+
+        Some(match field {
+            Field::Year => format!("{:0width$}", date?.year(), width = width),
+            Field::Month => format!("{:0width$}", date?.month(), width = width),
+            Field::MonthName => MONTH_NAMES[date?.month0() as usize].to_string(),
+            Field::Day => format!("{:0width$}", date?.day(), width = width),
+            Field::Type => values.media_type.clone(),
+            Field::CameraMake => sanitize(values.camera_make.as_deref()),
+            Field::CameraModel => sanitize(values.camera_model.as_deref()),
+            Field::Artist => sanitize(values.artist.as_deref()),
+            Field::Album => sanitize(values.album.as_deref()),
+            Field::Ext => sanitize(values.ext.as_deref()).to_lowercase(),
+            Field::SourceDir => sanitize(values.source_dir.as_deref()),
+        })
+    }
+
+    /// Renders the layout into a relative folder path.
+    ///
+    /// Without a date, the path is cut at the folder holding the first date field and
+    /// ends in an `undated` folder instead.
+    pub(crate) fn render(&self, values: &LayoutValues) -> String {
+        let mut rendered = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => rendered.push_str(literal),
+                Segment::Field { field, width } => {
+                    match Layout::render_field(*field, *width, values) {
+                        Some(value) => rendered.push_str(&value),
+                        None => {
+                            let folder_start = rendered.rfind('/').map_or(0, |i| i + 1);
+                            rendered.truncate(folder_start);
+                            rendered.push_str(UNDATED_FOLDER);
+                            return rendered;
+                        }
+                    }
+                }
+            }
+        }
+
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> LayoutValues {
+        LayoutValues {
+            date: NaiveDate::from_ymd_opt(2024, 3, 7),
+            media_type: "photos".to_string(),
+            camera_make: Some("\"Apple\"".to_string()),
+            camera_model: Some("iPhone 15 Pro/Max".to_string()),
+            ext: Some("JPG".to_string()),
+            source_dir: Some("DCIM".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_layout_is_year_month_day() {
+        let layout = Layout::parse(DEFAULT_LAYOUT).unwrap();
+        assert_eq!(layout.render(&values()), "2024/03/07");
+    }
+
+    #[test]
+    fn renders_month_names_and_unpadded_fields() {
+        let layout = Layout::parse("{year}/{month:02}-{month_name}/{day}").unwrap();
+        assert_eq!(layout.render(&values()), "2024/03-March/7");
+    }
+
+    #[test]
+    fn renders_metadata_fields() {
+        let layout =
+            Layout::parse("{type}/{camera_make}/{camera_model}/{ext}/{source_dir}").unwrap();
+        assert_eq!(
+            layout.render(&values()),
+            "photos/Apple/iPhone 15 Pro_Max/jpg/DCIM"
+        );
+    }
+
+    #[test]
+    fn missing_metadata_renders_unknown() {
+        let layout = Layout::parse("{artist}/{album}").unwrap();
+        assert_eq!(layout.render(&values()), "unknown/unknown");
+    }
+
+    #[test]
+    fn missing_date_ends_in_undated_folder() {
+        let mut undated = values();
+        undated.date = None;
+
+        assert_eq!(
+            Layout::parse(DEFAULT_LAYOUT).unwrap().render(&undated),
+            "undated"
+        );
+        assert_eq!(
+            Layout::parse("{type}/{camera_make}/{year}-{month}")
+                .unwrap()
+                .render(&undated),
+            "photos/Apple/undated"
+        );
+    }
+
+    #[test]
+    fn uses_reports_referenced_fields() {
+        let layout = Layout::parse("{year}/{camera_model}").unwrap();
+        assert!(layout.uses(&[Field::CameraMake, Field::CameraModel]));
+        assert!(!layout.uses(&[Field::Artist]));
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        assert!(Layout::parse("{year").is_err());
+        assert!(Layout::parse("{year}}").is_err());
+        assert!(Layout::parse("{decade}").is_err());
+        assert!(Layout::parse("{type:02}").is_err());
+        assert!(Layout::parse("{month:xx}").is_err());
+        assert!(Layout::parse("a}b/{year}").is_err());
+        assert!(Layout::parse("{year}/a}b/{day}").is_err());
+    }
+
+    #[test]
+    fn rejects_templates_that_leave_the_destination() {
+        assert!(Layout::parse("/{year}").is_err());
+        assert!(Layout::parse("C:/{year}").is_err());
+        assert!(Layout::parse("\\\\server\\{year}").is_err());
+        assert!(Layout::parse("{year}/../../x").is_err());
+        assert!(Layout::parse("..\\{year}").is_err());
+        assert!(Layout::parse("{year}/..").is_err());
+
+        let layout = Layout::parse("{year}..{month}/...").unwrap();
+        assert_eq!(layout.render(&values()), "2024..3/...");
+    }
+}
@@ -1,163 +1,566 @@
+use chrono::NaiveDate;
+use media_info::audio_info::struct_audio_info::AudioInfo;
+use media_info::photo_info::struct_photo_info::PhotoInfo;
 use media_info::{
-    read_audio_creation_date, read_doc_creation_date, read_photo_creation_date,
-    read_video_creation_date,
+    DateSource, MediaDate, MediaInfoError, MediaKind, VideoInfo, read_audio_creation_date,
+    read_doc_creation_date, read_file_creation_date, read_file_modified_date, read_file_name_date,
+    read_folder_date, read_photo_creation_date, read_photo_location, read_video_creation_date,
+    read_video_location, read_xmp_date, resolve_time_zone,
 };
-use std::env;
 use std::path::Path;
 
-fn make_dir_string(date_time: &str) -> String {
-    let replace_date_hyphens = str::replace(date_time, "-", "/");
-    let dest_folder = env::var("DEST_FOLDER").expect("DEST_FOLDER not set");
-    let mut regular_date_folder: String = String::new();
+use super::DateFolder;
+use super::date_chain::DateStrategy;
+use super::layout::{Field, Layout, LayoutValues, sanitize};
+use super::time_correction::Camera;
+use crate::organizer::config::OrganizerConfig;
+use crate::organizer::pairing::xmp_sidecar;
+
+const PHOTOS: &str = "photos";
+const VIDEOS: &str = "videos";
+const AUDIO: &str = "audio";
+const DOCUMENTS: &str = "documents";
+
+/// Collects the values the layout refers to. Media metadata is only read when the layout
+/// actually uses one of its fields.
+fn layout_values(
+    file: &str,
+    media_type: &str,
+    date: Option<NaiveDate>,
+    layout: &Layout,
+) -> LayoutValues {
+    let path = Path::new(file);
+    let mut values = LayoutValues {
+        date,
+        media_type: media_type.to_string(),
+        ext: path.extension().and_then(|e| e.to_str()).map(str::to_string),
+        source_dir: path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .map(str::to_string),
+        ..Default::default()
+    };
+
+    if layout.uses(&[Field::CameraMake, Field::CameraModel]) {
+        let camera = match media_type {
+            PHOTOS => PhotoInfo::new(path).ok().map(|info| (info.make, info.model)),
+            VIDEOS => VideoInfo::new(path).ok().map(|info| (info.make, info.model)),
+            _ => None,
+        };
+        if let Some((make, model)) = camera {
+            values.camera_make = Some(make);
+            values.camera_model = Some(model);
+        }
+    }
 
-    regular_date_folder.push_str("./");
-    regular_date_folder.push_str(&dest_folder);
-    regular_date_folder.push('/');
-    regular_date_folder.push_str(&replace_date_hyphens);
+    if media_type == AUDIO
+        && layout.uses(&[Field::Artist, Field::Album])
+        && let Ok(info) = AudioInfo::new(path)
+    {
+        values.artist = Some(info.artist);
+        values.album = Some(info.album);
+    }
 
-    regular_date_folder
+    values
 }
 
-pub fn make_photo_dir_str(dir_str: &str) -> String {
-    let photo_date =
-        read_photo_creation_date(Path::new(dir_str)).unwrap_or("no_date_found".to_string());
+fn make_dir_string(
+    date: Option<NaiveDate>,
+    file: &str,
+    media_type: &str,
+    config: &OrganizerConfig,
+) -> String {
+    let values = layout_values(file, media_type, date, &config.layout);
+
+    Path::new(".")
+        .join(&config.destination)
+        .join(config.layout.render(&values))
+        .display()
+        .to_string()
+}
 
-    make_dir_string(photo_date.split_whitespace().next().unwrap_or_default())
+fn read_date(
+    strategy: DateStrategy,
+    kind: MediaKind,
+    path: &Path,
+    config: &OrganizerConfig,
+) -> Result<MediaDate, MediaInfoError> {
+    match (strategy, kind) {
+        (DateStrategy::Metadata, MediaKind::Photo) => read_photo_creation_date(path),
+        (DateStrategy::Metadata, MediaKind::Video) => read_video_creation_date(path),
+        (DateStrategy::Metadata, MediaKind::Audio) => read_audio_creation_date(path),
+        (DateStrategy::Metadata, MediaKind::Document) => read_doc_creation_date(path),
+        (DateStrategy::Metadata, MediaKind::Unknown) => Err(MediaInfoError::Unsupported(format!(
+            "No metadata reader for {:?}",
+            path
+        ))),
+        (DateStrategy::FileName, _) => read_file_name_date(path, &config.file_name_patterns),
+        (DateStrategy::Folder, _) => read_folder_date(path).map(|found| found.date),
+        (DateStrategy::Created, _) => read_file_creation_date(path),
+        (DateStrategy::Modified, _) => read_file_modified_date(path),
+        (DateStrategy::Xmp, _) => match xmp_sidecar(path) {
+            Some(sidecar) => read_xmp_date(&sidecar),
+            None => Err(MediaInfoError::NoMetadata(format!(
+                "No XMP sidecar for {:?}",
+                path
+            ))),
+        },
+    }
 }
 
-pub fn make_video_dir_str(dir_str: &str) -> String {
-    let date = read_video_creation_date(Path::new(dir_str)).unwrap_or("no_date_found".to_string());
+/// Corrects a date recorded by a camera whose clock was wrong. The camera is only read when
+/// there are corrections to match it against.
+fn correct_date(
+    date: MediaDate,
+    kind: MediaKind,
+    path: &Path,
+    config: &OrganizerConfig,
+) -> MediaDate {
+    if config.time_corrections.is_empty() {
+        return date;
+    }
 
-    make_dir_string(date.split('T').next().unwrap_or_default())
+    config
+        .time_corrections
+        .correct(&Camera::read(kind, path), date)
 }
 
-pub fn make_audio_dir_str(dir_str: &str) -> String {
-    let audio_date =
-        read_audio_creation_date(Path::new(dir_str)).unwrap_or("no_date_found".to_string());
+/// Places a date the file stored only in UTC in the time zone of the file's GPS location,
+/// or else the configured time zone, so it lands in the folder of its local day.
+fn localize_date(
+    date: MediaDate,
+    kind: MediaKind,
+    path: &Path,
+    config: &OrganizerConfig,
+) -> MediaDate {
+    if !date.utc_only {
+        return date;
+    }
+
+    let location = match kind {
+        MediaKind::Photo => read_photo_location(path).ok(),
+        MediaKind::Video => read_video_location(path).ok(),
+        _ => None,
+    };
+    resolve_time_zone(date, location, &config.time_zone)
+}
 
-    make_dir_string(&audio_date)
+/// Whether a date comes from the file system, which copying and syncing reset.
+pub(super) fn from_file_system(date: &MediaDate) -> bool {
+    matches!(
+        date.source,
+        DateSource::FileBirthTime | DateSource::FileModifiedTime | DateSource::FileChangedTime
+    )
 }
 
-pub fn make_doc_dir_str(dir_str: &str) -> String {
-    let doc_date =
-        read_doc_creation_date(Path::new(dir_str)).unwrap_or("no_date_found".to_string());
+/// Takes the date from the first strategy in the media kind's date chain that finds a
+/// plausible one. Files for which every strategy fails end up in the `undated` folder.
+///
+/// Placeholder and out of range dates are passed over and noted for review, as are dates
+/// further than `max_disagreement` from the date of a later strategy. File system dates are
+/// not compared, since copying resets them.
+///
+/// With `keep_folder_name`, a date read from a folder name keeps that name after the date
+/// folder, so `1998 - Summer Vacation/` is not reduced to `1998/01/01`.
+fn make_date_folder(
+    kind: MediaKind,
+    file: &str,
+    media_type: &str,
+    config: &OrganizerConfig,
+) -> DateFolder {
+    let validation = &config.date_validation;
+    let mut review = Vec::new();
+    let mut dates = config
+        .date_sources
+        .for_kind(kind)
+        .iter()
+        .filter_map(|strategy| read_date(*strategy, kind, Path::new(file), config).ok());
+    let date = dates.by_ref().find(|date| match validation.check(date) {
+        Ok(()) => true,
+        Err(problem) => {
+            review.push(format!("Passed over {}: {}", date.source, problem));
+            false
+        }
+    });
+
+    if let Some(date) = &date
+        && validation.max_disagreement.is_some()
+    {
+        for other in
+            dates.filter(|other| !from_file_system(other) && validation.check(other).is_ok())
+        {
+            if let Some(gap) = validation.disagreement(date, &other) {
+                review.push(format!(
+                    "{} {} is {} days from {} {}",
+                    date.source,
+                    date,
+                    gap.num_days(),
+                    other.source,
+                    other
+                ));
+            }
+        }
+    }
 
-    make_dir_string(&doc_date)
+    match date
+        .map(|date| correct_date(date, kind, Path::new(file), config))
+        .map(|date| localize_date(date, kind, Path::new(file), config))
+    {
+        Some(date) => {
+            let mut path = make_dir_string(Some(date.date()), file, media_type, config);
+            if config.keep_folder_name
+                && date.source == DateSource::ParentFolder
+                && let Ok(found) = read_folder_date(Path::new(file))
+            {
+                path = format!("{} {}", path, sanitize(Some(&found.folder)));
+            }
+
+            DateFolder {
+                path,
+                date_source: date.source.to_string(),
+                date: Some(date),
+                review,
+            }
+        }
+        None => DateFolder {
+            path: make_dir_string(None, file, media_type, config),
+            date_source: "undated".to_string(),
+            date: None,
+            review,
+        },
+    }
+}
+
+pub fn make_photo_dir_str(dir_str: &str, config: &OrganizerConfig) -> DateFolder {
+    make_date_folder(MediaKind::Photo, dir_str, PHOTOS, config)
+}
+
+pub fn make_video_dir_str(dir_str: &str, config: &OrganizerConfig) -> DateFolder {
+    make_date_folder(MediaKind::Video, dir_str, VIDEOS, config)
+}
+
+pub fn make_audio_dir_str(dir_str: &str, config: &OrganizerConfig) -> DateFolder {
+    make_date_folder(MediaKind::Audio, dir_str, AUDIO, config)
+}
+
+pub fn make_doc_dir_str(dir_str: &str, config: &OrganizerConfig) -> DateFolder {
+    make_date_folder(MediaKind::Document, dir_str, DOCUMENTS, config)
 }
 
 #[cfg(test)]
 pub mod date_read_tests {
     use super::*;
 
-    #[test]
-    fn can_read_photo_creation_date() {
-        unsafe {
-            env::set_var("DEST_FOLDER", &"tests/test_files");
+    fn config() -> OrganizerConfig {
+        OrganizerConfig {
+            destination: "tests/test_files".into(),
+            ..Default::default()
         }
+    }
 
+    #[test]
+    fn can_read_photo_creation_date() {
         let photo_date = read_photo_creation_date(Path::new(
             "../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg",
         ))
-        .unwrap_or("no_date_found".to_string());
-        let date_info = make_dir_string(photo_date.split_whitespace().next().unwrap_or_default());
+        .ok();
+        let date_info = make_dir_string(
+            photo_date.map(|date| date.date()),
+            "../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg",
+            PHOTOS,
+            &config(),
+        );
 
         assert_eq!("./tests/test_files/2024/10/22", date_info);
     }
 
     #[test]
     fn can_read_video_creation_date() {
-        unsafe {
-            env::set_var("DEST_FOLDER", &"tests/test_files");
-        }
-
-        let video_date = read_video_creation_date(Path::new("../test-media/corgi_race.mp4"))
-            .unwrap_or("no_date_found".to_string());
-        let date_info = make_dir_string(video_date.split('T').next().unwrap_or_default());
+        let video_date = read_video_creation_date(Path::new("../test-media/corgi_race.mp4")).ok();
+        let date_info = make_dir_string(
+            video_date.map(|date| date.date()),
+            "../test-media/corgi_race.mp4",
+            VIDEOS,
+            &config(),
+        );
 
         assert_eq!("./tests/test_files/2024/10/20", date_info);
     }
 
     #[test]
     fn can_read_audio_creation_date() {
-        unsafe {
-            env::set_var("DEST_FOLDER", &"tests/test_files");
-        }
-
-        let audio_date = read_audio_creation_date(Path::new("../test-media/Recording.m4a"))
-            .unwrap_or("no_date_found".to_string());
-        let date_info = make_dir_string(&audio_date);
+        let audio_date = read_audio_creation_date(Path::new("../test-media/Recording.m4a")).ok();
+        let date_info = make_dir_string(
+            audio_date.map(|date| date.date()),
+            "../test-media/Recording.m4a",
+            AUDIO,
+            &config(),
+        );
 
         assert_eq!("./tests/test_files/2024/11/11", date_info);
     }
 
     #[test]
     fn can_read_doc_creation_date() {
-        unsafe {
-            env::set_var("DEST_FOLDER", &"tests/test_files");
-        }
-
-        let doc_date = read_doc_creation_date(Path::new("../test-media/TESTDOCUMENT.docx"))
-            .unwrap_or("no_date_found".to_string());
-        let date_info = make_dir_string(&doc_date);
+        let date_info = make_doc_dir_str("../test-media/TESTDOCUMENT.docx", &config());
 
         assert!(
-            date_info.starts_with("./tests/test_files/"),
+            date_info.path.starts_with("./tests/test_files/"),
             "Expected path to start with dest folder, got: {}",
-            date_info
+            date_info.path
         );
         assert_ne!(
-            date_info, "./tests/test_files/no_date_found",
+            date_info.path, "./tests/test_files/undated",
             "Should extract a real date, not fallback"
         );
     }
 
     #[test]
-    fn make_dir_string_with_no_date_found() {
-        unsafe {
-            env::set_var("DEST_FOLDER", &"tests/test_files");
-        }
-
-        let date_info = make_dir_string("no_date_found");
-        assert_eq!("./tests/test_files/no_date_found", date_info);
+    fn make_dir_string_with_undated() {
+        let date_info = make_dir_string(None, "missing.jpg", PHOTOS, &config());
+        assert_eq!("./tests/test_files/undated", date_info);
     }
 
     #[test]
     fn make_doc_dir_str_with_nonexistent_file() {
-        unsafe {
-            env::set_var("DEST_FOLDER", &"tests/test_files");
-        }
-
-        let result = make_doc_dir_str("nonexistent.pdf");
-        assert_eq!("./tests/test_files/no_date_found", result);
+        let result = make_doc_dir_str("nonexistent.pdf", &config());
+        assert_eq!("./tests/test_files/undated", result.path);
+        assert_eq!("undated", result.date_source);
     }
 
     #[test]
     fn make_photo_dir_str_with_nonexistent_file() {
-        unsafe {
-            env::set_var("DEST_FOLDER", &"tests/test_files");
-        }
-
-        let result = make_photo_dir_str("nonexistent.jpg");
-        assert_eq!("./tests/test_files/no_date_found", result);
+        let result = make_photo_dir_str("nonexistent.jpg", &config());
+        assert_eq!("./tests/test_files/undated", result.path);
     }
 
     #[test]
     fn make_video_dir_str_with_nonexistent_file() {
-        unsafe {
-            env::set_var("DEST_FOLDER", &"tests/test_files");
-        }
-
-        let result = make_video_dir_str("nonexistent.mp4");
-        assert_eq!("./tests/test_files/no_date_found", result);
+        let result = make_video_dir_str("nonexistent.mp4", &config());
+        assert_eq!("./tests/test_files/undated", result.path);
     }
 
     #[test]
     fn make_audio_dir_str_with_nonexistent_file() {
-        unsafe {
-            env::set_var("DEST_FOLDER", &"tests/test_files");
-        }
+        let result = make_audio_dir_str("nonexistent.mp3", &config());
+        assert_eq!("./tests/test_files/undated", result.path);
+    }
+
+    #[test]
+    fn missing_metadata_falls_back_to_file_creation_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let untagged = dir.path().join("untagged.mp3");
+        std::fs::write(&untagged, [0xFF, 0xFB, 0x90, 0x64]).unwrap();
+
+        let result = make_audio_dir_str(untagged.to_str().unwrap(), &config());
+        // File systems without birth times fall back to another file time
+        let created = read_file_creation_date(&untagged).unwrap();
+
+        assert_eq!(created.source.as_str(), result.date_source);
+        assert_eq!(
+            format!("./tests/test_files/{}", created.date().format("%Y/%m/%d")),
+            result.path
+        );
+    }
+
+    #[test]
+    fn files_exhausting_the_chain_are_undated() {
+        let dir = tempfile::tempdir().unwrap();
+        let broken = dir.path().join("broken.jpg");
+        std::fs::write(&broken, b"not a photo").unwrap();
+        let mut config = config();
+        config.date_sources.set_from("photo=metadata").unwrap();
+
+        let result = make_photo_dir_str(broken.to_str().unwrap(), &config);
+        assert_eq!("./tests/test_files/undated", result.path);
+        assert_eq!("undated", result.date_source);
+        assert!(result.date.is_none());
+    }
+
+    #[test]
+    fn chain_is_tried_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let broken = dir.path().join("broken.jpg");
+        std::fs::write(&broken, b"not a photo").unwrap();
+        let mut config = config();
+        config
+            .date_sources
+            .set_from("photo=modified,metadata")
+            .unwrap();
+
+        let result = make_photo_dir_str(broken.to_str().unwrap(), &config);
+        assert_eq!("file_modified_time", result.date_source);
+
+        let result = make_photo_dir_str(
+            "../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg",
+            &config,
+        );
+        assert_eq!("file_modified_time", result.date_source);
+    }
+
+    #[test]
+    fn file_name_dates_come_before_file_system_dates() {
+        let dir = tempfile::tempdir().unwrap();
+        let stripped = dir.path().join("IMG_20190704_183000.jpg");
+        std::fs::write(&stripped, b"not a photo").unwrap();
+
+        let result = make_photo_dir_str(stripped.to_str().unwrap(), &config());
+        assert_eq!("./tests/test_files/2019/07/04", result.path);
+        assert_eq!("file_name", result.date_source);
+    }
+
+    #[test]
+    fn folder_dates_are_opt_in_and_can_keep_the_folder_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("1998 - Summer Vacation");
+        std::fs::create_dir(&folder).unwrap();
+        let scan = folder.join("scan_001.tif");
+        std::fs::write(&scan, b"not a photo").unwrap();
+        let scan = scan.to_str().unwrap();
+
+        let mut config = config();
+        config.date_sources.set_from("photo=metadata").unwrap();
+        assert_eq!("undated", make_photo_dir_str(scan, &config).date_source);
+
+        config
+            .date_sources
+            .set_from("photo=metadata,folder")
+            .unwrap();
+        let result = make_photo_dir_str(scan, &config);
+        assert_eq!("parent_folder", result.date_source);
+        assert_eq!("./tests/test_files/1998/01/01", result.path);
+        assert_eq!("1998", result.date.unwrap().to_string());
+
+        config.keep_folder_name = true;
+        let result = make_photo_dir_str(scan, &config);
+        assert_eq!(
+            "./tests/test_files/1998/01/01 1998 - Summer Vacation",
+            result.path
+        );
+    }
+
+    #[test]
+    fn xmp_sidecars_date_their_photo_when_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("DSC_1234.NEF");
+        std::fs::write(&raw, b"not a photo").unwrap();
+        std::fs::write(
+            dir.path().join("DSC_1234.xmp"),
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmlns:photoshop="http://ns.adobe.com/photoshop/1.0/" xmp:CreateDate="2012-03-04T05:06:07" photoshop:DateCreated="1987-06-05"/></rdf:RDF></x:xmpmeta>"#,
+        )
+        .unwrap();
+        let raw = raw.to_str().unwrap();
+
+        let mut config = config();
+        config.date_sources.set_from("photo=metadata").unwrap();
+        assert_eq!("undated", make_photo_dir_str(raw, &config).date_source);
+
+        config.date_sources.set_from("photo=metadata,xmp").unwrap();
+        let result = make_photo_dir_str(raw, &config);
+        assert_eq!("photoshop_date_created", result.date_source);
+        assert_eq!("./tests/test_files/1987/06/05", result.path);
+    }
+
+    #[test]
+    fn utc_only_dates_are_filed_on_their_local_day() {
+        // An M4A whose movie header holds 2024-10-21T01:30:00Z
+        let seconds: u32 = 2_082_844_800 + 1_729_474_200;
+        let mut mvhd = vec![0, 0, 0, 20];
+        mvhd.extend_from_slice(b"mvhd");
+        mvhd.extend_from_slice(&[0; 4]);
+        mvhd.extend_from_slice(&seconds.to_be_bytes());
+        mvhd.extend_from_slice(&[0; 4]);
+        let mut m4a = vec![0, 0, 0, 12];
+        m4a.extend_from_slice(b"ftypM4A ");
+        m4a.extend_from_slice(&(mvhd.len() as u32 + 8).to_be_bytes());
+        m4a.extend_from_slice(b"moov");
+        m4a.extend(mvhd);
+
+        let dir = tempfile::tempdir().unwrap();
+        let memo = dir.path().join("memo.m4a");
+        std::fs::write(&memo, m4a).unwrap();
+        let mut config = config();
+        config.time_zone = media_info::TimeZoneSetting::parse("America/Denver").unwrap();
+
+        let result = make_audio_dir_str(memo.to_str().unwrap(), &config);
+        assert_eq!("mp4_creation_time", result.date_source);
+        assert_eq!("./tests/test_files/2024/10/20", result.path);
+        assert_eq!(
+            "2024-10-20T19:30:00-06:00",
+            result.date.unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn time_corrections_apply_before_the_date_folder_is_chosen() {
+        let mut config = config();
+        config.time_corrections = crate::TimeCorrections::parse(
+            r#"[{ "from": "2024-06-01", "to": "2024-06-14", "camera_zone": "-06:00", "actual_zone": "+09:00" }]"#,
+        )
+        .unwrap();
+
+        let result = make_photo_dir_str("IMG_20240602_180000.jpg", &config);
+        assert_eq!("file_name", result.date_source);
+        assert_eq!("./tests/test_files/2024/06/03", result.path);
+        assert_eq!(
+            "2024-06-03T09:00:00+09:00",
+            result.date.unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn placeholder_dates_fall_through_to_the_next_source_for_review() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("IMG_20000101_000312.jpg");
+        std::fs::write(&photo, b"").unwrap();
+        let mut config = config();
+        config
+            .date_sources
+            .set_from("photo=filename,modified")
+            .unwrap();
+
+        let result = make_photo_dir_str(photo.to_str().unwrap(), &config);
+        assert_eq!("file_modified_time", result.date_source);
+        assert_eq!(
+            vec!["Passed over file_name: 2000-01-01 is a placeholder date"],
+            result.review
+        );
+
+        config.date_sources.set_from("photo=filename").unwrap();
+        let result = make_photo_dir_str(photo.to_str().unwrap(), &config);
+        assert_eq!("./tests/test_files/undated", result.path);
+        assert_eq!(1, result.review.len());
+    }
+
+    #[test]
+    fn disagreeing_sources_are_flagged_for_review() {
+        let scan = "scans/2019 - Trip/IMG_20240602_180000.jpg";
+        let mut config = config();
+        config
+            .date_sources
+            .set_from("photo=filename,folder")
+            .unwrap();
+
+        assert!(make_photo_dir_str(scan, &config).review.is_empty());
+
+        config.date_validation.max_disagreement = Some(chrono::TimeDelta::days(30));
+        let result = make_photo_dir_str(scan, &config);
+        assert_eq!("./tests/test_files/2024/06/02", result.path);
+        assert_eq!(
+            vec!["file_name 2024-06-02T18:00:00 is 1614 days from parent_folder 2019"],
+            result.review
+        );
+    }
+
+    #[test]
+    fn date_folder_keeps_the_date_and_its_source() {
+        let result = make_photo_dir_str(
+            "../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg",
+            &config(),
+        );
 
-        let result = make_audio_dir_str("nonexistent.mp3");
-        assert_eq!("./tests/test_files/no_date_found", result);
+        assert_eq!("exif_date_time_original", result.date_source);
+        assert!(result.date.unwrap().to_string().starts_with("2024-10-22T"));
     }
 }
@@ -1,194 +1,186 @@
+pub mod date_chain;
+pub mod layout;
 mod make_dir_str;
-use make_dir_str::{make_audio_dir_str, make_doc_dir_str, make_photo_dir_str, make_video_dir_str};
-
-fn get_white_list_video_types<'a>() -> Vec<&'a str> {
-    vec!["mp4", "MP4", "mov", "MOV"]
-}
-
-fn get_white_list_photo_types<'a>() -> Vec<&'a str> {
-    vec![
-        "tiff", "TIFF", "heif", "HEIF", "HEIC", "heic", "AVIF", "avif", "jpeg", "jpg", "JPEG",
-        "JPG", "HEIC", "heic", "PNG", "png", "webp", "WEBP",
-    ]
-}
-
-fn get_white_list_audio_types<'a>() -> Vec<&'a str> {
-    vec!["mp3", "MP3", "wav", "WAV", "aiff", "AIFF", "m4a", "M4A", "flac", "FLAC"]
-}
-
-fn get_white_list_doc_types<'a>() -> Vec<&'a str> {
-    vec![
-        "docx", "DOCX", "doc", "DOC", "pdf", "PDF", "epub", "EPUB", "mobi", "MOBI",
-        "odt", "ODT", "txt", "TXT", "md", "MD", "rtf", "RTF",
-    ]
+pub mod time_correction;
+use super::config::OrganizerConfig;
+use make_dir_str::{
+    from_file_system, make_audio_dir_str, make_doc_dir_str, make_photo_dir_str, make_video_dir_str,
+};
+use media_info::{DatePrecision, MediaDate, MediaKind, detect};
+use std::path::Path;
+
+/// The date folder a file is sorted into, along with the metadata that produced the date.
+#[derive(Debug, Clone)]
+pub struct DateFolder {
+    pub path: String,
+    pub date_source: String,
+    /// The date the folder was named after, or `None` for the `undated` folder.
+    pub date: Option<MediaDate>,
+    /// Why the date should be checked by hand, such as placeholder dates that were passed
+    /// over.
+    pub review: Vec<String>,
 }
 
-fn contains_type(types: Vec<&str>, name: &str) -> bool {
-    for file_type in types {
-        if name.contains(file_type) {
-            return true;
-        }
+impl DateFolder {
+    /// How far the date can be trusted, for choosing between files that are sorted
+    /// together. Any date beats none, a date from the file's contents or name beats one
+    /// from the file system, and finer precision beats coarser.
+    pub fn trust(&self) -> (bool, bool, Option<DatePrecision>) {
+        (
+            self.date.is_some(),
+            self.date.is_some_and(|date| !from_file_system(&date)),
+            self.date.map(|date| date.precision),
+        )
     }
-
-    false
-}
-
-fn is_video(file_name: &str) -> bool {
-    contains_type(get_white_list_video_types(), file_name)
-}
-
-fn is_photo(file_name: &str) -> bool {
-    contains_type(get_white_list_photo_types(), file_name)
 }
 
-fn is_audio(file_name: &str) -> bool {
-    contains_type(get_white_list_audio_types(), file_name)
-}
-
-fn is_document(file_name: &str) -> bool {
-    contains_type(get_white_list_doc_types(), file_name)
-}
-
-pub fn sort_and_make(file_name: &str) -> Result<String, String> {
-    if is_video(file_name) {
-        return Ok(make_video_dir_str(file_name));
-    }
-    if is_photo(file_name) {
-        return Ok(make_photo_dir_str(file_name));
-    }
-    if is_audio(file_name) {
-        return Ok(make_audio_dir_str(file_name));
+/// Finds the date folder for a file, reading the date with the reader for its detected
+/// media kind.
+pub fn sort_and_make(file_name: &str, config: &OrganizerConfig) -> Result<DateFolder, String> {
+    match detect(Path::new(file_name)) {
+        MediaKind::Video => Ok(make_video_dir_str(file_name, config)),
+        MediaKind::Photo => Ok(make_photo_dir_str(file_name, config)),
+        MediaKind::Audio => Ok(make_audio_dir_str(file_name, config)),
+        MediaKind::Document => Ok(make_doc_dir_str(file_name, config)),
+        MediaKind::Unknown => Err(format!("'{}', File type not supported", file_name)),
     }
-    if is_document(file_name) {
-        return Ok(make_doc_dir_str(file_name));
-    }
-
-    Err(format!("'{}', File type not supported", file_name))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::layout::Layout;
     use super::*;
 
     // sort_and_make type detection tests
     #[test]
     fn sort_and_make_detects_video_mp4() {
-        let result = sort_and_make("video.mp4");
+        let result = sort_and_make("video.mp4", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_video_mov() {
-        let result = sort_and_make("video.MOV");
+        let result = sort_and_make("video.MOV", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_photo_jpg() {
-        let result = sort_and_make("photo.jpg");
+        let result = sort_and_make("photo.jpg", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_photo_heic() {
-        let result = sort_and_make("photo.HEIC");
+        let result = sort_and_make("photo.HEIC", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_audio_mp3() {
-        let result = sort_and_make("song.mp3");
+        let result = sort_and_make("song.mp3", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_audio_flac() {
-        let result = sort_and_make("song.FLAC");
+        let result = sort_and_make("song.FLAC", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_doc_pdf() {
-        let result = sort_and_make("file.pdf");
+        let result = sort_and_make("file.pdf", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_doc_docx() {
-        let result = sort_and_make("file.DOCX");
+        let result = sort_and_make("file.DOCX", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_doc_epub() {
-        let result = sort_and_make("book.epub");
+        let result = sort_and_make("book.epub", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_doc_txt() {
-        let result = sort_and_make("notes.txt");
+        let result = sort_and_make("notes.txt", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_doc_md() {
-        let result = sort_and_make("readme.md");
+        let result = sort_and_make("readme.md", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_doc_rtf() {
-        let result = sort_and_make("doc.RTF");
+        let result = sort_and_make("doc.RTF", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_detects_doc_odt() {
-        let result = sort_and_make("doc.odt");
+        let result = sort_and_make("doc.odt", &OrganizerConfig::default());
         assert!(result.is_ok());
     }
 
     #[test]
     fn sort_and_make_rejects_unsupported_type() {
-        let result = sort_and_make("file.xyz");
+        let result = sort_and_make("file.xyz", &OrganizerConfig::default());
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("File type not supported"));
     }
 
     #[test]
     fn sort_and_make_rejects_no_extension() {
-        let result = sort_and_make("justfilename");
+        let result = sort_and_make("justfilename", &OrganizerConfig::default());
         assert!(result.is_err());
     }
 
-    // is_* function edge case tests
-    #[test]
-    fn is_video_false_for_non_video() {
-        assert!(!is_video("photo.jpg"));
-    }
-
-    #[test]
-    fn is_photo_false_for_non_photo() {
-        assert!(!is_photo("video.mp4"));
+    // routing tests, using a layout that only renders the media type
+    fn routed_to(file_name: &str) -> String {
+        let config = OrganizerConfig {
+            destination: "sorted".into(),
+            layout: Layout::parse("{type}").unwrap(),
+            ..Default::default()
+        };
+        sort_and_make(file_name, &config).unwrap().path
     }
 
     #[test]
-    fn is_audio_false_for_non_audio() {
-        assert!(!is_audio("photo.jpg"));
+    fn routes_by_extension_when_file_is_unreadable() {
+        assert_eq!(routed_to("photo.jpg"), "./sorted/photos");
+        assert_eq!(routed_to("video.mp4"), "./sorted/videos");
+        assert_eq!(routed_to("song.m4a"), "./sorted/audio");
+        assert_eq!(routed_to("doc.pdf"), "./sorted/documents");
     }
 
     #[test]
-    fn is_document_false_for_non_doc() {
-        assert!(!is_document("video.mp4"));
+    fn directory_names_do_not_decide_the_type() {
+        // Classification used to match substrings anywhere in the path
+        assert_eq!(
+            routed_to("/home/mp4_backups/notes.txt"),
+            "./sorted/documents"
+        );
+        assert!(sort_and_make("/some/path/photo.jpg.bak", &OrganizerConfig::default()).is_err());
     }
 
     #[test]
-    fn contains_type_with_path() {
-        // sort_and_make receives full paths, ensure extension matching works with paths
-        assert!(is_photo("/some/path/to/photo.jpg"));
-        assert!(is_video("/some/path/to/video.mp4"));
-        assert!(is_audio("/some/path/to/song.m4a"));
-        assert!(is_document("/some/path/to/doc.pdf"));
+    fn routes_by_content() {
+        assert_eq!(
+            routed_to("../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg"),
+            "./sorted/photos"
+        );
+        assert_eq!(routed_to("../test-media/Recording.m4a"), "./sorted/audio");
+        assert_eq!(
+            routed_to("../test-media/TESTDOCUMENT.docx"),
+            "./sorted/documents"
+        );
     }
 }
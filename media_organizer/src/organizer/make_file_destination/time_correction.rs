@@ -0,0 +1,399 @@
+use chrono::{NaiveDate, TimeDelta};
+use media_info::photo_info::struct_photo_info::PhotoInfo;
+use media_info::{
+    DatePrecision, DateSource, MediaDate, MediaKind, TimeZoneSetting, VideoInfo,
+    photo_dates_rewritten,
+};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// The camera a file was recorded with, as far as its metadata tells.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Camera {
+    pub make: String,
+    pub model: String,
+    pub serial: String,
+    /// The file's dates were already written back by `fix-times`, so no profile applies
+    /// to them again.
+    pub corrected: bool,
+}
+
+impl Camera {
+    /// Reads the camera of a photo or video. Other files, and files without camera
+    /// metadata, have an unknown camera with every field empty.
+    pub fn read(kind: MediaKind, path: &Path) -> Camera {
+        match kind {
+            MediaKind::Photo => PhotoInfo::new(path)
+                .map(|info| Camera {
+                    make: info.make,
+                    model: info.model,
+                    serial: info.body_serial_number,
+                    corrected: photo_dates_rewritten(path),
+                })
+                .unwrap_or_default(),
+            MediaKind::Video => VideoInfo::new(path)
+                .map(|info| Camera {
+                    make: info.make,
+                    model: info.model,
+                    ..Camera::default()
+                })
+                .unwrap_or_default(),
+            _ => Camera::default(),
+        }
+    }
+}
+
+/// How a correction profile changes a date.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Adjustment {
+    /// The camera clock was off by a fixed amount, which is added to its dates.
+    Shift(TimeDelta),
+    /// The camera clock was set to the `camera` time zone while it recorded in `actual`.
+    Zone {
+        camera: TimeZoneSetting,
+        actual: TimeZoneSetting,
+    },
+}
+
+/// A correction for the dates one camera recorded while its clock was wrong.
+///
+/// `make`, `model` and `serial` are compared to the file's camera ignoring case, and the
+/// ones left out match any camera. `from` and `to` are the first and last day, as the
+/// camera's clock had it, the correction applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeCorrection {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub adjustment: Adjustment,
+}
+
+/// A correction profile as written in the profiles file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileSpec {
+    make: Option<String>,
+    model: Option<String>,
+    serial: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    shift: Option<String>,
+    camera_zone: Option<String>,
+    actual_zone: Option<String>,
+}
+
+fn parse_day(day: Option<String>) -> Result<Option<NaiveDate>, String> {
+    day.map(|day| {
+        NaiveDate::parse_from_str(day.trim(), "%Y-%m-%d").map_err(|_| {
+            format!(
+                "Invalid day in time corrections: {} (expected YYYY-MM-DD)",
+                day
+            )
+        })
+    })
+    .transpose()
+}
+
+/// Parses a duration such as `+1h12m`, `-30s` or `2d`.
+fn parse_shift(spec: &str) -> Result<TimeDelta, String> {
+    let invalid = || {
+        format!(
+            "Invalid shift: {} (expected days, hours, minutes and seconds such as +1h12m or -30s)",
+            spec
+        )
+    };
+    let (sign, mut rest) = match spec.trim() {
+        rest if rest.starts_with('-') => (-1, &rest[1..]),
+        rest => (1, rest.strip_prefix('+').unwrap_or(rest)),
+    };
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut shift = TimeDelta::zero();
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let amount: i64 = rest[..digits].parse().map_err(|_| invalid())?;
+        let unit = rest[digits..].chars().next().ok_or_else(invalid)?;
+        let amount = match unit {
+            'd' => TimeDelta::try_days(amount),
+            'h' => TimeDelta::try_hours(amount),
+            'm' => TimeDelta::try_minutes(amount),
+            's' => TimeDelta::try_seconds(amount),
+            _ => None,
+        };
+        shift = amount
+            .and_then(|amount| shift.checked_add(&amount))
+            .ok_or_else(invalid)?;
+        rest = &rest[digits + unit.len_utf8()..];
+    }
+    Ok(shift * sign)
+}
+
+impl TryFrom<ProfileSpec> for TimeCorrection {
+    type Error = String;
+
+    fn try_from(spec: ProfileSpec) -> Result<Self, String> {
+        let adjustment = match (spec.shift, spec.actual_zone) {
+            (Some(shift), None) if spec.camera_zone.is_none() => {
+                Adjustment::Shift(parse_shift(&shift)?)
+            }
+            (None, Some(actual)) => Adjustment::Zone {
+                camera: TimeZoneSetting::parse(spec.camera_zone.as_deref().unwrap_or("local"))?,
+                actual: TimeZoneSetting::parse(&actual)?,
+            },
+            _ => {
+                return Err(
+                    "Each time correction needs either a shift or an actual_zone".to_string(),
+                );
+            }
+        };
+
+        Ok(TimeCorrection {
+            make: spec.make,
+            model: spec.model,
+            serial: spec.serial,
+            from: parse_day(spec.from)?,
+            to: parse_day(spec.to)?,
+            adjustment,
+        })
+    }
+}
+
+/// Compares a profile's camera field to the file's, ignoring case and the quotes EXIF
+/// values are displayed with.
+fn same(expected: &Option<String>, actual: &str) -> bool {
+    expected.as_ref().is_none_or(|expected| {
+        expected
+            .trim()
+            .eq_ignore_ascii_case(actual.trim_matches(|c: char| c == '"' || c.is_whitespace()))
+    })
+}
+
+/// Whether a date was set by the camera's own clock. GPS times and file system, folder,
+/// audio and document dates are left alone.
+fn from_camera_clock(date: &MediaDate) -> bool {
+    date.precision == DatePrecision::Second
+        && matches!(
+            date.source,
+            DateSource::ExifDateTime
+                | DateSource::ExifDateTimeOriginal
+                | DateSource::ExifDateTimeDigitized
+                | DateSource::QuickTimeCreationDate
+                | DateSource::QuickTimeDate
+                | DateSource::FfmpegCreationTime
+                | DateSource::StreamCreationTime
+                | DateSource::MatroskaDateRecorded
+                | DateSource::AviDateTimeOriginal
+                | DateSource::AvchdRecordingDate
+                | DateSource::FileName
+        )
+}
+
+impl TimeCorrection {
+    /// Whether this profile covers a date the camera recorded and that was not corrected
+    /// yet.
+    pub fn matches(&self, camera: &Camera, date: &MediaDate) -> bool {
+        !camera.corrected
+            && from_camera_clock(date)
+            && same(&self.make, &camera.make)
+            && same(&self.model, &camera.model)
+            && same(&self.serial, &camera.serial)
+            && self.from.is_none_or(|from| date.date() >= from)
+            && self.to.is_none_or(|to| date.date() <= to)
+    }
+
+    /// Corrects a date. A zone correction leaves the date in the local time of the actual
+    /// time zone, and leaves dates the camera clock skipped over as they are. Shifts past
+    /// the range of dates leave the date as it is.
+    pub fn apply(&self, date: MediaDate) -> MediaDate {
+        match self.adjustment {
+            Adjustment::Shift(shift) => match date.date_time.checked_add_signed(shift) {
+                Some(date_time) => MediaDate { date_time, ..date },
+                None => date,
+            },
+            Adjustment::Zone { camera, actual } => {
+                let instant = match date.to_fixed() {
+                    Some(instant) => Some(instant.naive_utc()),
+                    None => camera
+                        .offset_at(&date.date_time)
+                        .map(|offset| date.date_time - offset),
+                };
+                match instant {
+                    Some(instant) => actual.localize(MediaDate {
+                        precision: date.precision,
+                        ..MediaDate::from_utc(instant, date.source)
+                    }),
+                    None => date,
+                }
+            }
+        }
+    }
+}
+
+/// Correction profiles for cameras whose clocks were wrong, loaded from a JSON file such as:
+///
+/// ```json
+/// [
+///   { "make": "Canon", "model": "Canon EOS R6", "from": "2022-03-01", "to": "2024-02-29", "shift": "+1h12m" },
+///   { "model": "iPhone 15", "from": "2024-06-01", "to": "2024-06-14", "camera_zone": "America/Denver", "actual_zone": "Asia/Tokyo" }
+/// ]
+/// ```
+///
+/// The first profile that matches a date corrects it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeCorrections {
+    pub profiles: Vec<TimeCorrection>,
+}
+
+impl TimeCorrections {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let specs: Vec<ProfileSpec> =
+            serde_json::from_str(json).map_err(|e| format!("Invalid time corrections: {}", e))?;
+        let profiles = specs
+            .into_iter()
+            .map(TimeCorrection::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TimeCorrections { profiles })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read time corrections {}: {}", path, e))?;
+        TimeCorrections::parse(&json)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    /// Corrects a date with the first profile that matches it, or returns it unchanged.
+    pub fn correct(&self, camera: &Camera, date: MediaDate) -> MediaDate {
+        match self
+            .profiles
+            .iter()
+            .find(|profile| profile.matches(camera, &date))
+        {
+            Some(profile) => profile.apply(date),
+            None => date,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(make: &str, model: &str) -> Camera {
+        Camera {
+            make: format!("\"{}\"", make),
+            model: format!("\"{}\"", model),
+            ..Camera::default()
+        }
+    }
+
+    fn exif_date(date_time: &str) -> MediaDate {
+        MediaDate::new(
+            date_time.parse().unwrap(),
+            DatePrecision::Second,
+            DateSource::ExifDateTimeOriginal,
+        )
+    }
+
+    #[test]
+    fn parses_shifts() {
+        assert_eq!(parse_shift("+1h12m"), Ok(TimeDelta::minutes(72)));
+        assert_eq!(parse_shift("-30s"), Ok(TimeDelta::seconds(-30)));
+        assert_eq!(parse_shift("2d3s"), Ok(TimeDelta::seconds(2 * 86400 + 3)));
+        assert!(parse_shift("").is_err());
+        assert!(parse_shift("1h12").is_err());
+        assert!(parse_shift("5w").is_err());
+        assert!(parse_shift("+1µs").is_err());
+        assert!(parse_shift("+99999999999999d").is_err());
+        assert!(parse_shift("+99999999999d99999999999d").is_err());
+    }
+
+    #[test]
+    fn shifts_out_of_range_leave_the_date_alone() {
+        let corrections = TimeCorrections::parse(r#"[{ "shift": "+99999999d" }]"#).unwrap();
+        let date = exif_date("2024-06-02T18:00:00");
+
+        assert_eq!(corrections.correct(&Camera::default(), date), date);
+    }
+
+    #[test]
+    fn shifts_the_matching_camera_within_its_dates() {
+        let corrections = TimeCorrections::parse(
+            r#"[{ "make": "canon", "model": "Canon EOS R6", "from": "2022-03-01", "to": "2024-02-29", "shift": "+1h12m" }]"#,
+        )
+        .unwrap();
+        let r6 = camera("Canon", "Canon EOS R6");
+
+        let corrected = corrections.correct(&r6, exif_date("2023-07-04T23:00:00"));
+        assert_eq!(corrected.to_string(), "2023-07-05T00:12:00");
+        assert_eq!(
+            corrections.correct(&r6, exif_date("2024-03-01T23:00:00")),
+            exif_date("2024-03-01T23:00:00")
+        );
+        assert_eq!(
+            corrections.correct(
+                &camera("Apple", "iPhone 15"),
+                exif_date("2023-07-04T23:00:00")
+            ),
+            exif_date("2023-07-04T23:00:00")
+        );
+    }
+
+    #[test]
+    fn corrected_files_are_left_alone() {
+        let corrections = TimeCorrections::parse(r#"[{ "shift": "+1h" }]"#).unwrap();
+        let corrected = Camera {
+            corrected: true,
+            ..Camera::default()
+        };
+        let date = exif_date("2024-06-02T18:00:00");
+
+        assert_eq!(corrections.correct(&corrected, date), date);
+    }
+
+    #[test]
+    fn travel_profiles_move_home_time_into_the_actual_zone() {
+        let corrections = TimeCorrections::parse(
+            r#"[{ "from": "2024-06-01", "to": "2024-06-14", "camera_zone": "America/Denver", "actual_zone": "Asia/Tokyo" }]"#,
+        )
+        .unwrap();
+
+        let corrected = corrections.correct(&Camera::default(), exif_date("2024-06-02T18:00:00"));
+        assert_eq!(corrected.to_string(), "2024-06-03T09:00:00+09:00");
+        assert!(!corrected.utc_only);
+    }
+
+    #[test]
+    fn gps_and_file_system_dates_are_left_alone() {
+        let corrections = TimeCorrections::parse(r#"[{ "shift": "+1h" }]"#).unwrap();
+        let gps = MediaDate::from_utc(
+            "2024-06-02T18:00:00".parse().unwrap(),
+            DateSource::ExifGpsTime,
+        );
+        let day = MediaDate::from_date("2024-06-02".parse().unwrap(), DateSource::FileBirthTime);
+
+        assert_eq!(corrections.correct(&Camera::default(), gps), gps);
+        assert_eq!(corrections.correct(&Camera::default(), day), day);
+    }
+
+    #[test]
+    fn rejects_profiles_without_exactly_one_adjustment() {
+        assert!(TimeCorrections::parse(r#"[{ "make": "Canon" }]"#).is_err());
+        assert!(
+            TimeCorrections::parse(r#"[{ "shift": "+1h", "actual_zone": "Asia/Tokyo" }]"#).is_err()
+        );
+        assert!(TimeCorrections::parse(r#"[{ "shift": "+1h", "lens": "50mm" }]"#).is_err());
+        assert!(TimeCorrections::parse(r#"[{ "actual_zone": "Mars/Base" }]"#).is_err());
+    }
+}
@@ -1,51 +1,395 @@
+mod collision;
+mod config;
+mod file_hash;
+mod fix_times;
 mod handle_media;
+mod journal;
 mod make_file_destination;
+mod pairing;
+mod report;
+mod transfer;
 
+pub use collision::CollisionPolicy;
+use collision::Reservations;
+pub use config::OrganizerConfig;
+pub use fix_times::fix_times;
 use glob::glob;
-use handle_media::handle_media;
-use make_file_destination::sort_and_make;
+use handle_media::{PlannedFile, apply_media, prepare_media};
+use journal::{Journal, missing_dirs, new_run_id};
+pub use journal::{UndoReport, undo};
+pub use make_file_destination::date_chain::{DateChains, DateStrategy};
+pub use make_file_destination::layout::Layout;
+pub use make_file_destination::time_correction::{
+    Adjustment, Camera, TimeCorrection, TimeCorrections,
+};
+use media_info::is_avchd_index;
 use mkdirp::mkdirp;
-use std::env;
+use pairing::{group_files, live_photo_identifier, plan_group};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+pub use report::{Failure, Report, ReportEntry};
+use std::collections::HashSet;
 use std::path::Path;
 
-pub fn handle_path(path: &str) {
-    if Path::new(&path).is_file() {
-        match sort_and_make(path) {
-            Ok(date) => {
-                mkdirp(&date).expect("Could not create directory");
-                handle_media(path, &date);
+/// Sorts files into date folders according to an [`OrganizerConfig`].
+pub struct Organizer {
+    config: OrganizerConfig,
+    run_id: String,
+}
+
+impl Organizer {
+    pub fn new(config: OrganizerConfig) -> Self {
+        Organizer {
+            config,
+            run_id: new_run_id(),
+        }
+    }
+
+    pub fn config(&self) -> &OrganizerConfig {
+        &self.config
+    }
+
+    /// The id of this run's journal, used to `undo` it.
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Organizes a single file or every matching file below a directory.
+    ///
+    /// Files that could not be organized are collected in the report's failures rather
+    /// than stopping the run. Entries are reported in path order, whatever `jobs` is.
+    pub fn run(&self, target: &Path) -> Report {
+        let mut report = Report::new(&self.run_id, self.config.dry_run);
+        let journal = Journal::new(&self.config.destination, &self.run_id);
+        let target_str = target.display().to_string();
+
+        let pool = match ThreadPoolBuilder::new()
+            .num_threads(self.config.jobs)
+            .build()
+        {
+            Ok(pool) => pool,
+            Err(e) => {
+                report.fail(
+                    &target_str,
+                    format!("Could not start worker threads: {}", e),
+                );
+                return report;
+            }
+        };
+
+        if !target.exists() {
+            report.fail(&target_str, "Path does not exist".to_string());
+        } else if target.is_dir() {
+            let files = self.collect_dir(&target_str, &mut report);
+            self.organize_files(&files, &pool, &journal, &mut report);
+        } else if target.is_file() {
+            match target.to_str() {
+                Some(path) => {
+                    self.organize_files(&[path.to_string()], &pool, &journal, &mut report)
+                }
+                None => report.fail(&target_str, "Path is not valid UTF-8".to_string()),
             }
-            Err(err) => println!("Error: {}", err),
+        } else {
+            report.fail(&target_str, "Path is not a file or directory".to_string());
         }
-    } else {
-        println!("Path is not a file: {}", path);
+        report.journaled = !self.config.dry_run && journal.written();
+
+        report
     }
-}
 
-pub fn organize_dir(dir_str: &str) {
-    let mut glob_path: String = String::new();
-    let file_type = env::var("FILE_TYPE").expect("FILE_TYPE not set");
-    let mut count_filepaths: u32 = 0;
+    /// Organizes `files` in three phases: metadata is read in parallel, collisions are
+    /// resolved one file at a time in order, then the transfers run in parallel again.
+    ///
+    /// RAW and JPEG pairs and the photo and video of Live Photos are planned as a unit, so
+    /// they share a date folder and a name, and sidecars are planned along with the file they
+    /// describe rather than dated themselves.
+    fn organize_files(
+        &self,
+        files: &[String],
+        pool: &ThreadPool,
+        journal: &Journal,
+        report: &mut Report,
+    ) {
+        let identifiers: Vec<_> = pool.install(|| {
+            files
+                .par_iter()
+                .map(|file| live_photo_identifier(file))
+                .collect()
+        });
+        let groups = group_files(files, &identifiers);
+        let sidecars: HashSet<usize> = groups
+            .iter()
+            .flat_map(|group| group.sidecars.iter().map(|sidecar| sidecar.index))
+            .collect();
 
-    glob_path.push_str(dir_str);
-    glob_path.push_str("/**/*.");
-    glob_path.push_str(&file_type);
+        let mut prepared: Vec<_> = pool.install(|| {
+            files
+                .par_iter()
+                .enumerate()
+                .map(|(index, file)| {
+                    (!sidecars.contains(&index)).then(|| prepare_media(file, &self.config))
+                })
+                .collect()
+        });
 
-    for entry in glob(&glob_path).expect("Failed to read glob pattern") {
-        match entry {
-            Ok(path) => match path.to_str() {
-                Some(path_str) => {
-                    handle_path(path_str);
+        let mut reservations = Reservations::default();
+        let mut planned: Vec<(usize, PlannedFile)> = Vec::new();
+        for group in &groups {
+            let mut members = Vec::with_capacity(group.members.len());
+            for &index in &group.members {
+                match prepared[index].take() {
+                    Some(Ok(member)) => members.push((index, member)),
+                    Some(Err(err)) => report.fail(&files[index], err),
+                    None => {}
+                }
+            }
+            for (index, result) in
+                plan_group(group, members, files, &self.config, &mut reservations)
+            {
+                match result {
+                    Ok(file) => planned.push((index, file)),
+                    Err(err) => report.fail(&files[index], err),
                 }
-                None => println!("Failed to convert path to string"),
-            },
-            Err(e) => println!("Glop path entry failed: {:?}", e),
+            }
+        }
+        planned.sort_by_key(|(index, _)| *index);
+        let planned = planned.into_iter().map(|(_, file)| file);
+
+        if self.config.dry_run {
+            report.entries.extend(planned.map(PlannedFile::into_entry));
+            return;
         }
 
-        count_filepaths += 1;
+        let mut ready: Vec<PlannedFile> = Vec::new();
+        for file in planned {
+            if file.writes()
+                && let Err(e) = create_date_folder(&file.date_folder.path, journal)
+            {
+                let source = file.into_entry().source;
+                report.fail(&source, format!("Could not create directory: {}", e));
+            } else {
+                ready.push(file);
+            }
+        }
+
+        let applied: Vec<_> = pool.install(|| {
+            ready
+                .into_par_iter()
+                .map(|file| apply_media(file, &self.config, journal))
+                .collect()
+        });
+
+        for result in applied {
+            match result {
+                Ok(entry) => report.entries.push(entry),
+                Err(failure) => report.failures.push(failure),
+            }
+        }
     }
 
-    if count_filepaths == 0 {
-        println!("No files found in directory: {}", dir_str);
+    fn collect_dir(&self, dir_str: &str, report: &mut Report) -> Vec<String> {
+        let mut glob_path: String = String::new();
+        let mut files: Vec<String> = Vec::new();
+        let mut count_filepaths: u32 = 0;
+
+        glob_path.push_str(dir_str);
+        glob_path.push_str("/**/*.");
+        glob_path.push_str(&self.config.file_type);
+
+        let paths = match glob(&glob_path) {
+            Ok(paths) => paths,
+            Err(e) => {
+                report.fail(dir_str, format!("Invalid glob pattern: {}", e));
+                return files;
+            }
+        };
+
+        for entry in paths {
+            match entry {
+                // The index an AVCHD camcorder keeps next to its clips is no media of its own
+                Ok(path) if is_avchd_index(&path) => {}
+                Ok(path) => match path.to_str() {
+                    Some(path_str) => files.push(path_str.to_string()),
+                    None => report.fail(
+                        &path.display().to_string(),
+                        "Failed to convert path to string".to_string(),
+                    ),
+                },
+                Err(e) => report.fail(
+                    &e.path().display().to_string(),
+                    format!("Glob path entry failed: {}", e),
+                ),
+            }
+
+            count_filepaths += 1;
+        }
+
+        if count_filepaths == 0 {
+            report.fail(dir_str, "No files found in directory".to_string());
+        }
+
+        files
+    }
+}
+
+/// Creates a date folder, journaling every directory that did not exist before.
+fn create_date_folder(path: &str, journal: &Journal) -> Result<(), String> {
+    let created_dirs = missing_dirs(Path::new(path));
+    mkdirp(path).map_err(|e| e.to_string())?;
+
+    for dir in created_dirs {
+        journal.record_dir(&dir)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn parallel_run_reports_in_path_order_without_clobbering() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("import");
+        for folder in 0..8 {
+            let folder = target.join(format!("folder_{}", folder));
+            fs::create_dir_all(&folder).unwrap();
+            fs::write(folder.join("notes.txt"), folder.display().to_string()).unwrap();
+        }
+
+        let organizer = Organizer::new(OrganizerConfig {
+            destination: dir.path().join("sorted"),
+            copy: true,
+            layout: Layout::parse("{type}").unwrap(),
+            jobs: 4,
+            ..Default::default()
+        });
+        let report = organizer.run(&target);
+
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        let sources: Vec<&str> = report.entries.iter().map(|e| e.source.as_str()).collect();
+        let mut sorted = sources.clone();
+        sorted.sort();
+        assert_eq!(sources, sorted);
+
+        let documents = dir.path().join("sorted/documents");
+        assert_eq!(fs::read_dir(&documents).unwrap().count(), 8);
+        assert!(documents.join("notes.txt").exists());
+        assert!(documents.join("notes_7.txt").exists());
+    }
+
+    #[test]
+    fn runs_that_write_nothing_are_not_journaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("import");
+        let sorted = dir.path().join("sorted");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(sorted.join("documents")).unwrap();
+        fs::write(target.join("notes.txt"), b"notes").unwrap();
+        fs::write(sorted.join("documents/notes.txt"), b"other notes").unwrap();
+
+        let organizer = Organizer::new(OrganizerConfig {
+            destination: sorted.clone(),
+            copy: true,
+            on_collision: CollisionPolicy::Skip,
+            layout: Layout::parse("{type}").unwrap(),
+            ..Default::default()
+        });
+        let report = organizer.run(&target);
+
+        assert_eq!(report.entries[0].action, "skip (exists)");
+        assert!(!report.journaled);
+
+        fs::remove_file(sorted.join("documents/notes.txt")).unwrap();
+        assert!(organizer.run(&target).journaled);
+    }
+
+    #[test]
+    fn raw_and_jpeg_pairs_share_the_best_date_and_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let card = dir.path().join("card");
+        fs::create_dir_all(&card).unwrap();
+        fs::copy(
+            "../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg",
+            card.join("DSC_1234.JPG"),
+        )
+        .unwrap();
+        // Without readable metadata the RAW alone would be dated by the file system.
+        fs::write(card.join("DSC_1234.NEF"), b"not really a raw").unwrap();
+        let sorted = dir.path().join("sorted");
+        fs::create_dir_all(sorted.join("2024/10/22")).unwrap();
+        fs::write(sorted.join("2024/10/22/DSC_1234.JPG"), b"an older photo").unwrap();
+
+        let organizer = Organizer::new(OrganizerConfig {
+            destination: sorted.clone(),
+            copy: true,
+            raw_folder: true,
+            layout: Layout::parse("{year}/{month:02}/{day:02}").unwrap(),
+            ..Default::default()
+        });
+        let report = organizer.run(&card);
+
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert!(sorted.join("2024/10/22/DSC_1234_1.JPG").exists());
+        assert!(sorted.join("2024/10/22/RAW/DSC_1234_1.NEF").exists());
+        assert_eq!(report.entries[1].date_source, "exif_date_time_original");
+    }
+
+    #[test]
+    fn sidecars_move_with_their_renamed_primary() {
+        let dir = tempfile::tempdir().unwrap();
+        let card = dir.path().join("card");
+        fs::create_dir_all(&card).unwrap();
+        fs::copy(
+            "../test-media/400a861d-014a-4dfb-9143-1a914212fd4d.jpg",
+            card.join("IMG_0001.JPG"),
+        )
+        .unwrap();
+        fs::write(card.join("IMG_0001.xmp"), b"<x:xmpmeta/>").unwrap();
+        fs::write(card.join("IMG_0001.JPG.json"), b"{}").unwrap();
+        let sorted = dir.path().join("sorted");
+        fs::create_dir_all(sorted.join("2024/10/22")).unwrap();
+        fs::write(sorted.join("2024/10/22/IMG_0001.JPG"), b"an older photo").unwrap();
+
+        let organizer = Organizer::new(OrganizerConfig {
+            destination: sorted.clone(),
+            copy: true,
+            layout: Layout::parse("{year}/{month:02}/{day:02}").unwrap(),
+            ..Default::default()
+        });
+        let report = organizer.run(&card);
+
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(report.entries.len(), 3);
+        assert!(sorted.join("2024/10/22/IMG_0001_1.JPG").exists());
+        assert!(sorted.join("2024/10/22/IMG_0001_1.xmp").exists());
+        assert!(sorted.join("2024/10/22/IMG_0001_1.JPG.json").exists());
+    }
+
+    #[test]
+    fn avchd_index_files_are_left_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let bdmv = dir.path().join("card/PRIVATE/AVCHD/BDMV");
+        for folder in ["STREAM", "CLIPINF", "PLAYLIST"] {
+            fs::create_dir_all(bdmv.join(folder)).unwrap();
+        }
+        fs::write(bdmv.join("STREAM/00000.MTS"), [0x47; 192]).unwrap();
+        fs::write(bdmv.join("CLIPINF/00000.CPI"), b"HDMV0100").unwrap();
+        fs::write(bdmv.join("PLAYLIST/00000.MPL"), b"MPLS0100").unwrap();
+        fs::write(bdmv.join("INDEX.BDM"), b"INDX0100").unwrap();
+        let sorted = dir.path().join("sorted");
+
+        let organizer = Organizer::new(OrganizerConfig {
+            destination: sorted,
+            dry_run: true,
+            ..Default::default()
+        });
+        let report = organizer.run(&dir.path().join("card"));
+
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(report.entries.len(), 1);
+        assert!(report.entries[0].source.ends_with("00000.MTS"));
     }
 }
@@ -0,0 +1,532 @@
+use super::collision::Reservations;
+use super::config::OrganizerConfig;
+use super::handle_media::{PlannedFile, PreparedFile, plan_media};
+use media_info::{
+    MediaKind, is_raw, kind_from_extension, read_photo_content_identifier,
+    read_video_content_identifier,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The sub-folder of a date folder that RAWs are routed into with `raw_folder`.
+const RAW_FOLDER: &str = "RAW";
+/// The sub-folder of a date folder that Live Photo videos are routed into with
+/// `live_folder`.
+const LIVE_FOLDER: &str = "live";
+
+/// The extensions of the photo half of a Live Photo, in lower case. The video is a `mov`.
+const LIVE_PHOTO_EXTENSIONS: [&str; 4] = ["heic", "heif", "jpg", "jpeg"];
+
+/// The extensions of sidecar files, in lower case: edits from Lightroom, darktable and iOS
+/// (`xmp`, `aae`), GoPro thumbnails and low resolution videos (`thm`, `lrv`), DJI
+/// telemetry (`srt`), cue sheets (`cue`) and Google Takeout metadata (`json`).
+const SIDECAR_EXTENSIONS: [&str; 7] = ["xmp", "aae", "thm", "lrv", "srt", "cue", "json"];
+
+/// A sidecar file and the file it describes, by their indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sidecar {
+    pub index: usize,
+    primary: usize,
+    /// Whether the sidecar is named after the primary's whole name, as in
+    /// `DSC_1234.NEF.xmp`, rather than its stem, as in `DSC_1234.xmp`.
+    by_name: bool,
+}
+
+/// Files that are sorted as a unit, by their indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub members: Vec<usize>,
+    pub sidecars: Vec<Sidecar>,
+}
+
+impl Group {
+    fn of(members: Vec<usize>) -> Self {
+        Group {
+            members,
+            sidecars: Vec::new(),
+        }
+    }
+}
+
+fn file_name(file: &str) -> &str {
+    Path::new(file)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(file)
+}
+
+fn file_stem(file: &str) -> &str {
+    Path::new(file)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(file)
+}
+
+fn parent(file: &str) -> &Path {
+    Path::new(file).parent().unwrap_or(Path::new(""))
+}
+
+fn is_photo(file: &str) -> bool {
+    kind_from_extension(Path::new(file)) == MediaKind::Photo
+}
+
+fn is_sidecar(file: &str) -> bool {
+    Path::new(file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| SIDECAR_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// The names a sidecar's primary may have, the sidecar's name without its extension first.
+/// Google Takeout also writes `<name>.supplemental-metadata.json`, and GoPro names the low
+/// resolution copy of `GX010001.MP4` or `GH010001.MP4` `GL010001.LRV`.
+fn primary_names(sidecar: &str) -> Vec<String> {
+    let base = file_stem(sidecar);
+    let mut names = vec![base.to_string()];
+    if let Some(name) = base.strip_suffix(".supplemental-metadata") {
+        names.push(name.to_string());
+    }
+    if sidecar.to_lowercase().ends_with(".lrv")
+        && let Some(number) = base.strip_prefix("GL")
+    {
+        names.push(format!("GX{}", number));
+        names.push(format!("GH{}", number));
+    }
+    names
+}
+
+/// Finds the XMP sidecar of a file, named after its whole name, as in `DSC_1234.NEF.xmp`,
+/// or else its stem, as in `DSC_1234.xmp`, the way [`group_files`] pairs sidecars.
+pub fn xmp_sidecar(file: &Path) -> Option<PathBuf> {
+    let file = file.to_str()?;
+    [file_name(file), file_stem(file)]
+        .into_iter()
+        .flat_map(|base| ["xmp", "XMP"].map(|ext| parent(file).join(format!("{}.{}", base, ext))))
+        .find(|sidecar| sidecar.is_file())
+}
+
+/// Reads the identifier an iPhone gives both the photo and the video of a Live Photo, for
+/// the file types a Live Photo is made of.
+pub fn live_photo_identifier(file: &str) -> Option<String> {
+    let path = Path::new(file);
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    if LIVE_PHOTO_EXTENSIONS.contains(&ext.as_str()) {
+        read_photo_content_identifier(path).ok()
+    } else if ext == "mov" {
+        read_video_content_identifier(path).ok()
+    } else {
+        None
+    }
+}
+
+/// Groups the indices of `files` that are sorted as a unit. A RAW and the JPEG or HEIC the
+/// camera wrote alongside it share a folder and a name up to the extension, the video of a
+/// Live Photo has the same entry in `identifiers` as its photo, and sidecars share a folder
+/// with their primary and are named after its name or stem. Every other file is a group of
+/// its own, and so is a sidecar without a primary. Groups are in the order of their first
+/// file.
+pub fn group_files(files: &[String], identifiers: &[Option<String>]) -> Vec<Group> {
+    let mut photos: Vec<Vec<usize>> = Vec::new();
+    let mut photo_stems: HashMap<(&Path, &str), usize> = HashMap::new();
+    let mut sidecars = Vec::new();
+
+    for (index, file) in files.iter().enumerate() {
+        if is_sidecar(file) {
+            sidecars.push(index);
+        } else if is_photo(file) {
+            match photo_stems.get(&(parent(file), file_stem(file))) {
+                Some(&group) => photos[group].push(index),
+                None => {
+                    photo_stems.insert((parent(file), file_stem(file)), photos.len());
+                    photos.push(vec![index]);
+                }
+            }
+        } else {
+            photos.push(vec![index]);
+        }
+    }
+
+    // Only a RAW ties photos of the same name together.
+    let mut groups: Vec<Group> = Vec::with_capacity(photos.len());
+    for members in photos {
+        if members
+            .iter()
+            .any(|&index| is_raw(Path::new(&files[index])))
+        {
+            groups.push(Group::of(members));
+        } else {
+            groups.extend(members.into_iter().map(|index| Group::of(vec![index])));
+        }
+    }
+
+    // Other files are groups of their own, so a Live Photo video joins its photo's group.
+    let mut live_photos: HashMap<&str, usize> = HashMap::new();
+    for (position, group) in groups.iter().enumerate() {
+        for &index in &group.members {
+            if let Some(identifier) = identifiers[index].as_deref()
+                && is_photo(&files[index])
+            {
+                live_photos.entry(identifier).or_insert(position);
+            }
+        }
+    }
+    for position in 0..groups.len() {
+        if let [index] = groups[position].members[..]
+            && !is_photo(&files[index])
+            && let Some(&photo) = identifiers[index]
+                .as_deref()
+                .and_then(|identifier| live_photos.get(identifier))
+        {
+            let video = std::mem::take(&mut groups[position].members);
+            groups[photo].members.extend(video);
+        }
+    }
+    groups.retain(|group| !group.members.is_empty());
+
+    let mut group_of: HashMap<usize, usize> = HashMap::new();
+    let mut by_name: HashMap<(&Path, &str), usize> = HashMap::new();
+    let mut by_stem: HashMap<(&Path, &str), usize> = HashMap::new();
+    for (position, group) in groups.iter().enumerate() {
+        for &index in &group.members {
+            let file = files[index].as_str();
+            group_of.insert(index, position);
+            by_name
+                .entry((parent(file), file_name(file)))
+                .or_insert(index);
+            by_stem
+                .entry((parent(file), file_stem(file)))
+                .or_insert(index);
+        }
+    }
+
+    for index in sidecars {
+        let dir = parent(&files[index]);
+        let primary = primary_names(&files[index]).iter().find_map(|name| {
+            let key = (dir, name.as_str());
+            by_name
+                .get(&key)
+                .map(|&primary| (primary, true))
+                .or_else(|| by_stem.get(&key).map(|&primary| (primary, false)))
+        });
+
+        match primary {
+            Some((primary, by_name)) => groups[group_of[&primary]].sidecars.push(Sidecar {
+                index,
+                primary,
+                by_name,
+            }),
+            None => groups.push(Group::of(vec![index])),
+        }
+    }
+
+    groups.sort_by_key(|group| group.members[0]);
+    groups
+}
+
+/// Replaces the stem of a file name, keeping its extension.
+fn with_stem(file_name: &str, stem: &str) -> String {
+    match Path::new(file_name).extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}.{}", stem, ext),
+        None => stem.to_string(),
+    }
+}
+
+/// Names a sidecar after the name its primary is planned under. A sidecar keeps its own
+/// name while its primary does; otherwise the part named after the primary is replaced. A
+/// GoPro low resolution video keeps its `GL` in front of the primary's new number.
+fn sidecar_name(sidecar: &str, primary: &str, planned: &str, by_name: bool) -> String {
+    let sidecar = file_name(sidecar);
+    let (base, planned_base) = if by_name {
+        (file_name(primary), planned)
+    } else {
+        (file_stem(primary), file_stem(planned))
+    };
+    if base == planned_base {
+        return sidecar.to_string();
+    }
+
+    if let Some(rest) = sidecar.strip_prefix(base) {
+        format!("{}{}", planned_base, rest)
+    } else if let Some(number) = sidecar.strip_prefix("GL")
+        && let Some(rest) = base.get(2..).and_then(|base| number.strip_prefix(base))
+        && let Some(planned_number) = planned_base.get(2..)
+    {
+        format!("GL{}{}", planned_number, rest)
+    } else {
+        sidecar.to_string()
+    }
+}
+
+fn orphaned(sidecar: &Sidecar, files: &[String]) -> (usize, Result<PlannedFile, String>) {
+    let reason = format!(
+        "Its primary file {} was not organized",
+        files[sidecar.primary]
+    );
+    (sidecar.index, Err(reason))
+}
+
+/// Moves a prepared file into a sub-folder of its date folder.
+fn into_sub_folder(file: &mut PreparedFile, folder: &str) {
+    file.date_folder.path = Path::new(&file.date_folder.path)
+        .join(folder)
+        .display()
+        .to_string();
+}
+
+/// Plans the prepared members of a group, then its sidecars, as a unit.
+///
+/// A photo leads over a video, so a Live Photo is dated by its photo, then the member with
+/// the most trusted date, preferring the RAW on a tie. The others follow the lead into its
+/// date folder under its name, including any dimensions suffix or collision rename. With
+/// `raw_folder`, RAWs go into the `RAW` sub-folder of the date folder, and with
+/// `live_folder`, the videos of Live Photos go into its `live` sub-folder. Sidecars go
+/// wherever their primary goes, renamed with it; a sidecar named after the stem a pair
+/// shares belongs to the RAW. Sidecars whose primary could not be planned fail.
+pub fn plan_group(
+    group: &Group,
+    mut members: Vec<(usize, PreparedFile)>,
+    files: &[String],
+    config: &OrganizerConfig,
+    reservations: &mut Reservations,
+) -> Vec<(usize, Result<PlannedFile, String>)> {
+    let Some(lead) = members
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, (_, member))| {
+            (
+                is_photo(&member.source),
+                member.date_folder.trust(),
+                is_raw(Path::new(&member.source)),
+            )
+        })
+        .map(|(position, _)| position)
+    else {
+        return group
+            .sidecars
+            .iter()
+            .map(|sidecar| orphaned(sidecar, files))
+            .collect();
+    };
+    members.swap(0, lead);
+
+    let date_folder = members[0].1.date_folder.clone();
+    let live = is_photo(&members[0].1.source);
+    let mut planned: Vec<(usize, PlannedFile)> = Vec::with_capacity(members.len());
+    for (index, mut member) in members {
+        if let Some((_, lead)) = planned.first() {
+            member.date_folder = date_folder.clone();
+            if let Some(stem) = lead.file_name().map(file_stem) {
+                member.file_name = with_stem(&member.file_name, stem);
+            }
+        }
+        if config.raw_folder && is_raw(Path::new(&member.source)) {
+            into_sub_folder(&mut member, RAW_FOLDER);
+        } else if config.live_folder && live && !is_photo(&member.source) {
+            into_sub_folder(&mut member, LIVE_FOLDER);
+        }
+        planned.push((index, plan_media(member, config, reservations)));
+    }
+
+    let raw = planned
+        .iter()
+        .position(|(index, _)| is_raw(Path::new(&files[*index])))
+        .unwrap_or(0);
+    let mut sidecars = Vec::with_capacity(group.sidecars.len());
+    for sidecar in &group.sidecars {
+        let primary = if sidecar.by_name {
+            planned.iter().find(|(index, _)| *index == sidecar.primary)
+        } else {
+            planned.get(raw)
+        };
+        let Some((primary, planned_primary)) = primary else {
+            sidecars.push(orphaned(sidecar, files));
+            continue;
+        };
+
+        let source = &files[sidecar.index];
+        let prepared = PreparedFile {
+            source: source.clone(),
+            date_folder: planned_primary.date_folder.clone(),
+            file_name: sidecar_name(
+                source,
+                &files[*primary],
+                planned_primary.file_name().unwrap_or_default(),
+                sidecar.by_name,
+            ),
+        };
+        sidecars.push((
+            sidecar.index,
+            Ok(plan_media(prepared, config, reservations)),
+        ));
+    }
+
+    planned
+        .into_iter()
+        .map(|(index, file)| (index, Ok(file)))
+        .chain(sidecars)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn ungrouped(files: &[String]) -> Vec<Group> {
+        group_files(files, &vec![None; files.len()])
+    }
+
+    #[test]
+    fn pairs_raws_with_photos_of_the_same_name() {
+        let files = files(&[
+            "card/DSC_1234.JPG",
+            "card/DSC_1234.NEF",
+            "card/DSC_1235.JPG",
+            "card/DSC_1235.jpeg",
+            "backup/DSC_1234.NEF",
+            "card/DSC_1236.NEF",
+            "card/DSC_1236.mov",
+        ]);
+
+        let mut expected = vec![Group::of(vec![0, 1])];
+        expected.extend((2..7).map(|index| Group::of(vec![index])));
+        assert_eq!(ungrouped(&files), expected);
+    }
+
+    #[test]
+    fn sidecars_join_the_file_they_are_named_after() {
+        let files = files(&[
+            "card/DSC_1234.JPG",
+            "card/DSC_1234.NEF",
+            "card/DSC_1234.NEF.xmp",
+            "card/DSC_1234.xmp",
+            "takeout/IMG_0001.jpg",
+            "takeout/IMG_0001.jpg.supplemental-metadata.json",
+            "gopro/GX010001.MP4",
+            "gopro/GL010001.LRV",
+            "gopro/GX010001.THM",
+            "card/notes.json",
+            "elsewhere/DSC_1234.xmp",
+        ]);
+        let sidecar = |index, primary, by_name| Sidecar {
+            index,
+            primary,
+            by_name,
+        };
+
+        assert_eq!(
+            ungrouped(&files),
+            vec![
+                Group {
+                    members: vec![0, 1],
+                    sidecars: vec![sidecar(2, 1, true), sidecar(3, 0, false)],
+                },
+                Group {
+                    members: vec![4],
+                    sidecars: vec![sidecar(5, 4, true)],
+                },
+                Group {
+                    members: vec![6],
+                    sidecars: vec![sidecar(7, 6, false), sidecar(8, 6, false)],
+                },
+                Group::of(vec![9]),
+                Group::of(vec![10]),
+            ]
+        );
+    }
+
+    #[test]
+    fn live_photo_videos_join_the_photo_with_their_identifier() {
+        let files = files(&[
+            "iphone/IMG_0001.MOV",
+            "iphone/IMG_0001.HEIC",
+            "iphone/IMG_0001.AAE",
+            "export/IMG_0002.MOV",
+            "export/IMG_0002.jpg",
+            "export/IMG_0002.HEIC",
+            "export/IMG_0003.MOV",
+        ]);
+        let identifier = |id: &str| Some(id.to_string());
+        let identifiers = vec![
+            identifier("A"),
+            identifier("A"),
+            None,
+            identifier("B"),
+            identifier("B"),
+            identifier("B"),
+            identifier("C"),
+        ];
+
+        assert_eq!(
+            group_files(&files, &identifiers),
+            vec![
+                Group {
+                    members: vec![1, 0],
+                    sidecars: vec![Sidecar {
+                        index: 2,
+                        primary: 1,
+                        by_name: false,
+                    }],
+                },
+                Group::of(vec![4, 3]),
+                Group::of(vec![5]),
+                Group::of(vec![6]),
+            ]
+        );
+    }
+
+    #[test]
+    fn xmp_sidecars_are_found_by_name_then_stem() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("DSC_1234.NEF");
+        let jpeg = dir.path().join("DSC_1234.JPG");
+        assert_eq!(xmp_sidecar(&raw), None);
+
+        std::fs::write(dir.path().join("DSC_1234.xmp"), b"").unwrap();
+        std::fs::write(dir.path().join("DSC_1234.NEF.xmp"), b"").unwrap();
+        assert_eq!(xmp_sidecar(&raw), Some(dir.path().join("DSC_1234.NEF.xmp")));
+        assert_eq!(xmp_sidecar(&jpeg), Some(dir.path().join("DSC_1234.xmp")));
+    }
+
+    #[test]
+    fn sidecars_follow_the_name_of_their_primary() {
+        assert_eq!(
+            sidecar_name("a/DSC_1234.xmp", "a/DSC_1234.NEF", "DSC_1234.NEF", false),
+            "DSC_1234.xmp"
+        );
+        assert_eq!(
+            sidecar_name("a/DSC_1234.xmp", "a/DSC_1234.NEF", "DSC_1234_1.NEF", false),
+            "DSC_1234_1.xmp"
+        );
+        assert_eq!(
+            sidecar_name(
+                "a/IMG_0001.jpg.supplemental-metadata.json",
+                "a/IMG_0001.jpg",
+                "IMG_0001_3024x4032.jpg",
+                true
+            ),
+            "IMG_0001_3024x4032.jpg.supplemental-metadata.json"
+        );
+        assert_eq!(
+            sidecar_name("a/GL010001.LRV", "a/GX010001.MP4", "GX010001.MP4", false),
+            "GL010001.LRV"
+        );
+        assert_eq!(
+            sidecar_name("a/GL010001.LRV", "a/GX010001.MP4", "GX010001_1.MP4", false),
+            "GL010001_1.LRV"
+        );
+        assert_eq!(
+            sidecar_name("a/notes.xmp", "a/DSC_1234.NEF", "DSC_1234_1.NEF", false),
+            "notes.xmp"
+        );
+    }
+
+    #[test]
+    fn followers_take_the_stem_and_keep_their_extension() {
+        assert_eq!(with_stem("DSC_1234.NEF", "DSC_1234_1"), "DSC_1234_1.NEF");
+        assert_eq!(with_stem("DSC_1234", "DSC_1234_1"), "DSC_1234_1");
+    }
+}
@@ -0,0 +1,152 @@
+use serde::Serialize;
+
+/// What happened, or would happen in a dry run, to a single file.
+#[derive(Debug, Serialize)]
+pub struct ReportEntry {
+    pub source: String,
+    pub destination: String,
+    pub date_source: String,
+    /// The date the destination was chosen by, as ISO 8601.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    pub action: String,
+    /// Set when the destination collided with an existing file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Why the date should be checked by hand.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub review: Vec<String>,
+}
+
+/// A file that could not be organized.
+#[derive(Debug, Serialize)]
+pub struct Failure {
+    pub source: String,
+    pub reason: String,
+}
+
+/// The outcome of an organizer run.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub run_id: String,
+    pub dry_run: bool,
+    /// Whether the run wrote a journal, so it can be undone.
+    pub journaled: bool,
+    pub entries: Vec<ReportEntry>,
+    pub failures: Vec<Failure>,
+}
+
+impl Report {
+    pub fn new(run_id: &str, dry_run: bool) -> Self {
+        Report {
+            run_id: run_id.to_string(),
+            dry_run,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn fail(&mut self, source: &str, reason: String) {
+        self.failures.push(Failure {
+            source: source.to_string(),
+            reason,
+        });
+    }
+
+    /// Formats the entries as an aligned table with a header row.
+    pub fn format_table(&self) -> String {
+        let headers = ["SOURCE", "DESTINATION", "DATE SOURCE", "ACTION"];
+        let rows: Vec<[&str; 4]> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                [
+                    entry.source.as_str(),
+                    entry.destination.as_str(),
+                    entry.date_source.as_str(),
+                    entry.action.as_str(),
+                ]
+            })
+            .collect();
+
+        let mut widths = headers.map(|header| header.len());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let format_row = |cells: [&str; 4]| -> String {
+            let padded: Vec<String> = cells
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            padded.join("  ").trim_end().to_string()
+        };
+
+        let mut table = format_row(headers);
+        for row in rows {
+            table.push('\n');
+            table.push_str(&format_row(row));
+        }
+
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(source: &str, destination: &str) -> ReportEntry {
+        ReportEntry {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            date_source: "exif".to_string(),
+            date: None,
+            action: "move".to_string(),
+            note: None,
+            review: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn table_aligns_columns() {
+        let mut report = Report::new("run", true);
+        report.entries = vec![
+            entry("a.jpg", "./sorted_media/2024/10/22/a.jpg"),
+            entry("longer_name.jpg", "./x/longer_name.jpg"),
+        ];
+        let table = report.format_table();
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("SOURCE           DESTINATION"));
+        assert_eq!(lines[1].find("./sorted_media"), lines[2].find("./x/"));
+        assert!(lines[1].ends_with("exif         move"));
+    }
+
+    #[test]
+    fn table_with_no_entries_only_has_headers() {
+        let table = Report::new("run", true).format_table();
+        assert_eq!(table, "SOURCE  DESTINATION  DATE SOURCE  ACTION");
+    }
+
+    #[test]
+    fn entries_serialize_to_json() {
+        let json = serde_json::to_string(&[entry("a.jpg", "./b/a.jpg")]).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"source":"a.jpg","destination":"./b/a.jpg","date_source":"exif","action":"move"}]"#
+        );
+    }
+
+    #[test]
+    fn failures_are_collected() {
+        let mut report = Report::new("run", false);
+        report.fail("file.xyz", "File type not supported".to_string());
+
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].source, "file.xyz");
+    }
+}
@@ -0,0 +1,155 @@
+use super::file_hash::file_hash;
+use std::ffi::OsString;
+use std::fs::{self, File, FileTimes};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Why a file could not be moved.
+#[derive(Debug)]
+pub struct MoveError {
+    pub reason: String,
+    /// The file was copied to the destination but the source could not be removed, so it
+    /// now exists in both places.
+    pub copied: bool,
+}
+
+impl MoveError {
+    pub fn new(reason: String) -> Self {
+        MoveError {
+            reason,
+            copied: false,
+        }
+    }
+}
+
+/// The hidden file a cross-filesystem copy is written to before it is renamed into place,
+/// so an interrupted copy never looks like a finished file.
+fn partial_path(destination: &Path) -> PathBuf {
+    let mut file_name = OsString::from(".");
+    file_name.push(destination.file_name().unwrap_or_default());
+    file_name.push(".partial");
+
+    destination.with_file_name(file_name)
+}
+
+fn write_partial(source: &Path, partial: &Path) -> io::Result<()> {
+    let metadata = fs::metadata(source)?;
+    fs::copy(source, partial)?;
+
+    let file = File::options().write(true).open(partial)?;
+    file.set_times(
+        FileTimes::new()
+            .set_accessed(metadata.accessed()?)
+            .set_modified(metadata.modified()?),
+    )?;
+    file.sync_all()
+}
+
+/// Copies `source` to `destination`, syncs it to disk and compares checksums before the
+/// copy is renamed into place. The destination is never left half written.
+pub fn copy_verified(source: &Path, destination: &Path) -> Result<(), String> {
+    let partial = partial_path(destination);
+
+    let verified = write_partial(source, &partial)
+        .map_err(|e| format!("Could not copy {}: {}", source.display(), e))
+        .and_then(|_| match (file_hash(source), file_hash(&partial)) {
+            (Ok(source_hash), Ok(copy_hash)) if source_hash == copy_hash => Ok(()),
+            (Ok(_), Ok(_)) => Err(format!(
+                "Checksum of the copy of {} does not match",
+                source.display()
+            )),
+            (Err(e), _) | (_, Err(e)) => Err(format!("Could not verify copy: {}", e)),
+        })
+        .and_then(|_| fs::rename(&partial, destination).map_err(|e| e.to_string()));
+
+    if verified.is_err() {
+        let _ = fs::remove_file(&partial);
+        return verified;
+    }
+
+    // Persist the rename itself. Directories cannot be opened for syncing on every
+    // platform, so this is best effort.
+    if let Some(parent) = destination.parent()
+        && let Ok(dir) = File::open(parent)
+    {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+/// Moves `source` to `destination`. When they are on different filesystems the file is
+/// copied with [`copy_verified`] and the source is only removed once the copy checks out.
+pub fn move_file(source: &Path, destination: &Path) -> Result<(), MoveError> {
+    match fs::rename(source, destination) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_verified(source, destination).map_err(MoveError::new)?;
+
+            fs::remove_file(source).map_err(|e| MoveError {
+                reason: format!(
+                    "Copied to {} but could not remove the original: {}",
+                    destination.display(),
+                    e
+                ),
+                copied: true,
+            })
+        }
+        Err(e) => Err(MoveError::new(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_path_is_hidden_next_to_destination() {
+        assert_eq!(
+            partial_path(Path::new("sorted/2024/IMG_0001.JPG")),
+            PathBuf::from("sorted/2024/.IMG_0001.JPG.partial")
+        );
+    }
+
+    #[test]
+    fn copy_verified_keeps_contents_and_modified_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.jpg");
+        let destination = dir.path().join("b.jpg");
+        fs::write(&source, b"photo").unwrap();
+        let modified = fs::metadata(&source).unwrap().modified().unwrap();
+
+        copy_verified(&source, &destination).unwrap();
+
+        assert_eq!(fs::read(&destination).unwrap(), b"photo");
+        assert_eq!(
+            fs::metadata(&destination).unwrap().modified().unwrap(),
+            modified
+        );
+        assert!(source.exists());
+        assert!(!partial_path(&destination).exists());
+    }
+
+    #[test]
+    fn copy_verified_cleans_up_after_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("b.jpg");
+
+        assert!(copy_verified(&dir.path().join("missing.jpg"), &destination).is_err());
+        assert!(!destination.exists());
+        assert!(!partial_path(&destination).exists());
+    }
+
+    #[test]
+    fn move_file_renames_on_the_same_filesystem() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.jpg");
+        let destination = dir.path().join("b.jpg");
+        fs::write(&source, b"photo").unwrap();
+
+        move_file(&source, &destination).unwrap();
+
+        assert!(!source.exists());
+        assert_eq!(fs::read(&destination).unwrap(), b"photo");
+    }
+}